@@ -249,6 +249,14 @@ fn generate_fmt<W: io::Write>(w: &mut IndentingWriter<W>, instructions: &Vec<Ins
     })
 }
 
+fn text_names(record: &InstructionRecord) -> String {
+    if record.old_name == record.new_name {
+        format!("\"{}\"", record.new_name)
+    } else {
+        format!("\"{}\" | \"{}\"", record.new_name, record.old_name)
+    }
+}
+
 fn generate_instruction_methods<W: io::Write>(w: &mut IndentingWriter<W>, instructions: &Vec<InstructionRecord>) -> io::Result<()> {
     w.block("pub fn read<R: std::io::Read>(reader: &mut R) -> Result<Instruction, crate::Error> {", |w| {
         w.writeln("let opcode = byteorder::ReadBytesExt::read_u8(reader)?;")?;
@@ -272,6 +280,31 @@ fn generate_instruction_methods<W: io::Write>(w: &mut IndentingWriter<W>, instru
     })?;
     w.writeln("")?;
 
+    w.block("pub fn write<W: std::io::Write>(&self, writer: &mut W) -> Result<(), crate::Error> {", |w| {
+        w.writeln("byteorder::WriteBytesExt::write_u8(writer, self.opcode())?;")?;
+        w.block("match self {", |w| {
+            for record in instructions {
+                match record.typ {
+                    Empty => {}
+                    Const => writeln!(w, "{}(x) => write_{}(writer, x),", record.enum_ref, get_value_type(&record.new_name))?,
+                    Block | BranchTable => writeln!(w, "{}(x) => x.write(writer),", record.enum_ref)?,
+                    Index => writeln!(w, "{}(x) => write_idx(writer, *x),", record.enum_ref)?,
+                    TableIndex | MemArg => {
+                        w.block(&format!("{}(x, y) => {{", record.enum_ref), |w| {
+                            w.writeln("write_idx(writer, *x)?;")?;
+                            w.writeln("write_idx(writer, *y)")?;
+                            Ok(())
+                        })?;
+                    }
+                }
+            }
+            writeln!(w, "_ => Ok(()),")?;
+            Ok(())
+        })?;
+        Ok(())
+    })?;
+    w.writeln("")?;
+
     w.block("pub fn opcode(&self) -> u8 {", |w| {
         w.block("match self {", |w| {
             for record in instructions {
@@ -287,6 +320,30 @@ fn generate_instruction_methods<W: io::Write>(w: &mut IndentingWriter<W>, instru
     })?;
     w.writeln("")?;
 
+    w.writeln("/// Looks up an instruction without immediates by its text format name (old or new).")?;
+    w.block("pub fn from_name(name: &str) -> Option<Instruction> {", |w| {
+        w.block("match name {", |w| {
+            for record in instructions.iter().filter(|i| i.typ == InstructionType::Empty) {
+                writeln!(w, "{} => Some({}),", text_names(record), record.enum_ref)?;
+            }
+            writeln!(w, "_ => None,")?;
+            Ok(())
+        })
+    })?;
+    w.writeln("")?;
+
+    w.writeln("/// Looks up a memory instruction by its text format name (old or new).")?;
+    w.block("pub fn from_memarg_name(name: &str, align: u32, offset: u32) -> Option<Instruction> {", |w| {
+        w.block("match name {", |w| {
+            for record in instructions.iter().filter(|i| i.typ == InstructionType::MemArg) {
+                writeln!(w, "{} => Some({}(align, offset)),", text_names(record), record.enum_ref)?;
+            }
+            writeln!(w, "_ => None,")?;
+            Ok(())
+        })
+    })?;
+    w.writeln("")?;
+
     w.block("pub fn is_block(&self) -> bool {", |w| {
         w.block("match self {", |w| {
             for record in instructions.iter().filter(|i| i.typ == InstructionType::Block) {
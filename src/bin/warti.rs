@@ -4,13 +4,7 @@ extern crate warthog;
 
 use std::{borrow::Cow, env, fs, path::Path, process};
 
-use warthog::{
-    hosting::{ExternVal, Host},
-    interp::Thread,
-    module::Module,
-    reader::Reader,
-    runtime,
-};
+use warthog::{hosting::Host, module::Module, reader::Reader, runtime, text};
 
 fn main() {
     // Arg 0 is the executable name
@@ -21,7 +15,7 @@ fn main() {
         let file = &args[0];
         run(Path::new(file));
     } else {
-        eprintln!("Usage: {} <wasm file>", arg0);
+        eprintln!("Usage: {} <wasm or wat file>", arg0);
         process::exit(1);
     }
 }
@@ -37,7 +31,16 @@ pub fn run(file: &Path) {
     };
 
     // Load the module
-    let module = {
+    let module = if file.extension().is_some_and(|e| e == "wat") {
+        let source = fs::read_to_string(file).unwrap();
+        match text::parse_module(&source) {
+            Ok(module) => module,
+            Err(e) => {
+                eprintln!("{}:{}", file.display(), e);
+                process::exit(1);
+            }
+        }
+    } else {
         // Close the file once we're done loading
         let file = fs::File::open(file).unwrap();
         let reader = Reader::new(file);
@@ -48,19 +51,16 @@ pub fn run(file: &Path) {
     host.external(runtime::Env::new()).unwrap();
 
     // Instantiate the module
-    let entry_point = host.instantiate(name, module).unwrap();
+    let instance = host.instantiate(name, module).unwrap();
 
     // Look for the main entry point
-    let main_func = match host.resolve_import(entry_point, "_main").unwrap().value() {
-        ExternVal::Func(f) => *f,
-        _ => panic!("'_main' is not a function!"),
+    let main_func = match instance.get_func(&host, "_main") {
+        Some(func) => func,
+        None => panic!("'_main' is not a function!"),
     };
 
-    // Create a thread
-    let mut thread = Thread::new();
-
     // Invoke the entry point
-    if let Err(trap) = thread.invoke(&mut host, main_func) {
+    if let Err(trap) = main_func.call(&mut host, &[]) {
         eprintln!("trap! {}", trap.cause());
         if let Some(trace) = trap.trace() {
             for frame in trace.frames() {
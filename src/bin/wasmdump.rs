@@ -5,8 +5,9 @@ extern crate warthog;
 use std::{env, fs, io, process};
 
 use warthog::reader::{
-    CodeSection, CustomSection, DataSection, ExportSection, FunctionSection, ImportSection,
-    NameSection, Reader, SectionHeader, SectionId, TypeSection,
+    CodeSection, CustomSection, DataSection, ElementSection, ExportSection, FunctionSection,
+    GlobalSection, ImportSection, NameSection, Reader, SectionHeader, SectionId, StartSection,
+    TableSection, TypeSection,
 };
 
 fn main() {
@@ -36,7 +37,11 @@ pub fn run(file: &str) {
             SectionId::Type => dump_type_section(&mut r, header),
             SectionId::Import => dump_import_section(&mut r, header),
             SectionId::Function => dump_function_section(&mut r, header),
+            SectionId::Table => dump_table_section(&mut r, header),
+            SectionId::Global => dump_global_section(&mut r, header),
             SectionId::Export => dump_export_section(&mut r, header),
+            SectionId::Start => dump_start_section(&mut r, header),
+            SectionId::Element => dump_element_section(&mut r, header),
             SectionId::Data => dump_data_section(&mut r, header),
             SectionId::Code => dump_code_section(&mut r, header),
             SectionId::Custom => dump_custom_section(&mut r, header),
@@ -93,6 +98,20 @@ fn dump_function_section<R: io::Read>(r: &mut Reader<R>, header: SectionHeader)
     }
 }
 
+fn dump_table_section<R: io::Read>(r: &mut Reader<R>, header: SectionHeader) {
+    let section: TableSection = r.read_section(header).unwrap();
+    for (i, table) in section.tables.iter().enumerate() {
+        println!("* {:04} {}", i, table);
+    }
+}
+
+fn dump_global_section<R: io::Read>(r: &mut Reader<R>, header: SectionHeader) {
+    let section: GlobalSection = r.read_section(header).unwrap();
+    for (i, global) in section.globals.iter().enumerate() {
+        println!("* {:04} {}", i, global);
+    }
+}
+
 fn dump_export_section<R: io::Read>(r: &mut Reader<R>, header: SectionHeader) {
     let section: ExportSection = r.read_section(header).unwrap();
     for (i, export) in section.exports.iter().enumerate() {
@@ -100,6 +119,18 @@ fn dump_export_section<R: io::Read>(r: &mut Reader<R>, header: SectionHeader) {
     }
 }
 
+fn dump_start_section<R: io::Read>(r: &mut Reader<R>, header: SectionHeader) {
+    let section: StartSection = r.read_section(header).unwrap();
+    println!("* (func {})", section.func);
+}
+
+fn dump_element_section<R: io::Read>(r: &mut Reader<R>, header: SectionHeader) {
+    let section: ElementSection = r.read_section(header).unwrap();
+    for (i, item) in section.elems.iter().enumerate() {
+        println!("* {:04} {}", i, item);
+    }
+}
+
 fn dump_data_section<R: io::Read>(r: &mut Reader<R>, header: SectionHeader) {
     let section: DataSection = r.read_section(header).unwrap();
     for (i, item) in section.data.iter().enumerate() {
@@ -52,15 +52,17 @@ pub fn run(file: &Path) {
     // Dump the host
     println!("Host information:");
     dump_funcs(&host);
+    dump_tables(&host);
     dump_mems(&host);
-    dump_instances(entry_point, &host);
+    dump_globals(&host);
+    dump_instances(entry_point.addr(), &host);
 }
 
 fn dump_funcs(host: &Host) {
     println!("  Functions:");
     for (i, func_inst) in host.funcs().enumerate() {
         match func_inst.imp() {
-            FuncImpl::Local(_, func_id) => {
+            FuncImpl::Local(_, func_id, _) => {
                 println!(
                     "  * {:04} {} {} {:04}",
                     i + 1,
@@ -74,6 +76,13 @@ fn dump_funcs(host: &Host) {
     }
 }
 
+fn dump_tables(host: &Host) {
+    println!("  Tables:");
+    for (i, table_inst) in host.tables().enumerate() {
+        println!("  * {:04} {}", i + 1, table_inst.typ());
+    }
+}
+
 fn dump_mems(host: &Host) {
     println!("  Memories:");
     for (i, mem_inst) in host.mems().enumerate() {
@@ -92,27 +101,38 @@ fn dump_mems(host: &Host) {
     }
 }
 
+fn dump_globals(host: &Host) {
+    println!("  Globals:");
+    for (i, global_inst) in host.globals().enumerate() {
+        println!(
+            "  * {:04} {} {}",
+            i + 1,
+            global_inst.typ(),
+            global_inst.value()
+        );
+    }
+}
+
 fn dump_initialized_ranges(mem: &MemInst) {
     let mut range_start = None;
-    let mem = mem.memory();
-    unsafe {
-        for (i, v) in mem.data().iter().enumerate() {
-            match (v, range_start) {
-                (0, Some(start)) => {
-                    // End of a range
-                    let end = i - 1;
-                    println!(
-                        "    * 0x{:08x} - 0x{:08x} (size: {})",
-                        start,
-                        end,
-                        end - start
-                    );
-                    range_start = None;
-                }
-                (0, None) => { /* no-op */ }
-                (_, None) => range_start = Some(i),
-                _ => { /* no-op */ }
+    let view = mem.view();
+    let data = view.read(0, view.len()).unwrap();
+    for (i, v) in data.iter().enumerate() {
+        match (v, range_start) {
+            (0, Some(start)) => {
+                // End of a range
+                let end = i - 1;
+                println!(
+                    "    * 0x{:08x} - 0x{:08x} (size: {})",
+                    start,
+                    end,
+                    end - start
+                );
+                range_start = None;
             }
+            (0, None) => { /* no-op */ }
+            (_, None) => range_start = Some(i),
+            _ => { /* no-op */ }
         }
     }
 }
@@ -124,7 +144,9 @@ fn dump_instances(entry_point: ModuleAddr, host: &Host) {
             println!("  Entry Point");
         }
         dump_instance_funcs(&module_inst);
+        dump_instance_tables(&module_inst);
         dump_instance_mems(&module_inst);
+        dump_instance_globals(&module_inst);
         dump_instance_exports(&module_inst);
 
         if let Some(names) = module_inst.names() {
@@ -164,6 +186,15 @@ fn dump_instance_funcs(module_inst: &ModuleInst) {
     }
 }
 
+fn dump_instance_tables(module_inst: &ModuleInst) {
+    if module_inst.tables().len() > 0 {
+        println!("  Tables:");
+        for (i, table_addr) in module_inst.tables().iter().enumerate() {
+            println!("  * {:04} {}", i, table_addr);
+        }
+    }
+}
+
 fn dump_instance_mems(module_inst: &ModuleInst) {
     if module_inst.mems().len() > 0 {
         println!("  Memories:");
@@ -173,6 +204,15 @@ fn dump_instance_mems(module_inst: &ModuleInst) {
     }
 }
 
+fn dump_instance_globals(module_inst: &ModuleInst) {
+    if module_inst.globals().len() > 0 {
+        println!("  Globals:");
+        for (i, global_addr) in module_inst.globals().iter().enumerate() {
+            println!("  * {:04} {}", i, global_addr);
+        }
+    }
+}
+
 fn dump_instance_exports(module_inst: &ModuleInst) {
     if module_inst.exports().len() > 0 {
         println!("  Exports:");
@@ -0,0 +1,60 @@
+#![deny(warnings)]
+
+extern crate warthog;
+
+use std::{env, fs, path::Path, process};
+
+use warthog::wast::{Runner, Script};
+
+fn main() {
+    // Arg 0 is the executable name
+    let arg0 = env::args().next().unwrap();
+    let args: Vec<_> = env::args().skip(1).collect();
+
+    if args.is_empty() {
+        eprintln!("Usage: {} [-v] <wast file>...", arg0);
+        process::exit(1);
+    }
+
+    let verbose = args.iter().any(|a| a == "-v");
+    let mut failed = 0;
+    for file in args.iter().filter(|a| *a != "-v") {
+        failed += run(Path::new(file), verbose);
+    }
+
+    if failed > 0 {
+        process::exit(1);
+    }
+}
+
+/// Runs a script, printing the result of each directive, and returns the number of failures.
+fn run(file: &Path, verbose: bool) -> usize {
+    let text = match fs::read_to_string(file) {
+        Ok(text) => text,
+        Err(e) => {
+            eprintln!("{}: {}", file.display(), e);
+            return 1;
+        }
+    };
+    let script = match Script::parse(&text) {
+        Ok(script) => script,
+        Err(e) => {
+            eprintln!("{}:{}", file.display(), e);
+            return 1;
+        }
+    };
+
+    let report = Runner::new().run(&script);
+    for result in report.results() {
+        if verbose || result.outcome.is_err() {
+            println!("{}:{}", file.display(), result);
+        }
+    }
+    println!(
+        "{}: {} passed, {} failed",
+        file.display(),
+        report.passed(),
+        report.failed()
+    );
+    report.failed()
+}
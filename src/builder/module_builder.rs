@@ -1,13 +1,21 @@
 use crate::{
     builder::{FuncBuilder, TypeUse},
-    module::{DataItem, Export, FuncBody, FuncType, Import, MemberDesc, Module, ModuleNames},
+    module::{
+        DataItem, ElemItem, Export, FuncBody, FuncType, Global, Import, MemberDesc, MemoryType,
+        Module, ModuleNames, TableType,
+    },
 };
 
 pub struct ModuleBuilder {
     pub types: Vec<FuncType>,
     pub imports: Vec<Import>,
     pub funcs: Vec<usize>,
+    pub tables: Vec<TableType>,
+    pub mems: Vec<MemoryType>,
+    pub globals: Vec<Global>,
     pub exports: Vec<Export>,
+    pub start: Option<usize>,
+    pub elems: Vec<ElemItem>,
     pub code: Vec<FuncBody>,
     pub data: Vec<DataItem>,
     pub names: Option<ModuleNames>,
@@ -19,7 +27,12 @@ impl ModuleBuilder {
             types: Vec::new(),
             imports: Vec::new(),
             funcs: Vec::new(),
+            tables: Vec::new(),
+            mems: Vec::new(),
+            globals: Vec::new(),
             exports: Vec::new(),
+            start: None,
+            elems: Vec::new(),
             code: Vec::new(),
             data: Vec::new(),
             names: None,
@@ -58,16 +71,16 @@ impl ModuleBuilder {
                 self.funcs.len(),
                 "Cannot add imports after local functions are defined!"
             );
-            let func_id = self.imports.len();
+            let func_id = self.imported_funcs();
             self.imports
                 .push(Import::new(module, name, MemberDesc::Function(type_id)));
             func_id
         } else {
-            let func_id = self.imports.len() + self.funcs.len();
+            let func_id = self.imported_funcs() + self.funcs.len();
             self.funcs.push(type_id);
 
             // Add the body
-            debug_assert_eq!(func_id - self.imports.len(), self.code.len());
+            debug_assert_eq!(func_id - self.imported_funcs(), self.code.len());
             let body = FuncBody::new(func.locals, func.body);
             self.code.push(body);
 
@@ -80,12 +93,101 @@ impl ModuleBuilder {
         }
     }
 
+    /// Adds a table to the builder, returning its index in the table index space.
+    pub fn add_table(&mut self, table: TableType) -> usize {
+        let table_id = self.imported_tables() + self.tables.len();
+        self.tables.push(table);
+        table_id
+    }
+
+    /// Adds a table to the builder (chaining variant)
+    pub fn table(mut self, table: TableType) -> Self {
+        self.add_table(table);
+        self
+    }
+
+    /// Adds a memory to the builder, returning its index in the memory index space.
+    pub fn add_memory(&mut self, mem: MemoryType) -> usize {
+        let mem_id = self.imported_mems() + self.mems.len();
+        self.mems.push(mem);
+        mem_id
+    }
+
+    /// Adds a memory to the builder (chaining variant)
+    pub fn memory(mut self, mem: MemoryType) -> Self {
+        self.add_memory(mem);
+        self
+    }
+
+    /// Adds an element segment to the builder (chaining variant)
+    pub fn elem(mut self, elem: ElemItem) -> Self {
+        self.elems.push(elem);
+        self
+    }
+
+    /// Adds a data segment to the builder (chaining variant)
+    pub fn data(mut self, data: DataItem) -> Self {
+        self.data.push(data);
+        self
+    }
+
+    /// Sets the function to invoke when the module is instantiated.
+    pub fn start(mut self, func_idx: usize) -> Self {
+        self.start = Some(func_idx);
+        self
+    }
+
+    /// Adds a global to the builder, returning its index in the global index space.
+    pub fn add_global(&mut self, global: Global) -> usize {
+        let global_id = self.imported_globals() + self.globals.len();
+        self.globals.push(global);
+        global_id
+    }
+
+    /// Adds a global to the builder (chaining variant)
+    pub fn global(mut self, global: Global) -> Self {
+        self.add_global(global);
+        self
+    }
+
     /// Adds a function to the builder (chaining variant)
     pub fn func(mut self, func: FuncBuilder) -> Self {
         self.add_func(func);
         self
     }
 
+    /// Gets the number of imports in the function index space.
+    fn imported_funcs(&self) -> usize {
+        self.imports
+            .iter()
+            .filter(|i| matches!(i.description(), MemberDesc::Function(_)))
+            .count()
+    }
+
+    /// Gets the number of imports in the table index space.
+    fn imported_tables(&self) -> usize {
+        self.imports
+            .iter()
+            .filter(|i| matches!(i.description(), MemberDesc::Table(_)))
+            .count()
+    }
+
+    /// Gets the number of imports in the memory index space.
+    fn imported_mems(&self) -> usize {
+        self.imports
+            .iter()
+            .filter(|i| matches!(i.description(), MemberDesc::Memory(_)))
+            .count()
+    }
+
+    /// Gets the number of imports in the global index space.
+    fn imported_globals(&self) -> usize {
+        self.imports
+            .iter()
+            .filter(|i| matches!(i.description(), MemberDesc::Global(_)))
+            .count()
+    }
+
     pub fn build(self) -> Module {
         Module::from_builder(self)
     }
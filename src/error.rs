@@ -1,16 +1,40 @@
-use crate::Trap;
+use crate::{hosting::ExternType, module::ValidationError, Trap};
 
 #[derive(Debug)]
 pub enum Error {
     InvalidModule,
-    ModuleNotFound { module: String },
-    ExportNotFound { module: String, name: String },
-    ExportTypeMismatch { module: String, name: String },
-    UnsupportedVersion { version: u32 },
+    Validation(ValidationError),
+    ModuleNotFound {
+        module: String,
+    },
+    ExportNotFound {
+        module: String,
+        name: String,
+    },
+    ExportTypeMismatch {
+        module: String,
+        name: String,
+        expected: Box<ExternType>,
+        actual: Box<ExternType>,
+    },
+    InvalidMagic,
+    UnsupportedVersion {
+        version: u32,
+    },
+    UnknownSection(u8),
+    SectionSizeMismatch,
+    IntegerTooLong,
+    IntegerTooLarge,
     LayoutError,
     Utf8Error(std::string::FromUtf8Error),
     IoError(String),
     UnknownOpcode(u8),
+    ElemSegmentOutOfBounds {
+        index: usize,
+    },
+    DataSegmentOutOfBounds {
+        index: usize,
+    },
     Trap(Trap),
 }
 
@@ -44,6 +68,12 @@ impl From<std::alloc::LayoutErr> for Error {
     }
 }
 
+impl From<ValidationError> for Error {
+    fn from(e: ValidationError) -> Error {
+        Error::Validation(e)
+    }
+}
+
 impl From<Trap> for Error {
     fn from(t: Trap) -> Error {
         Error::Trap(t)
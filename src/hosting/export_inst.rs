@@ -1,6 +1,6 @@
 use std::fmt;
 
-use crate::hosting::{FuncAddr, MemAddr};
+use crate::hosting::{FuncAddr, GlobalAddr, MemAddr, TableAddr};
 
 pub struct ExportInst {
     name: String,
@@ -15,6 +15,13 @@ impl ExportInst {
         }
     }
 
+    pub fn table<S: Into<String>>(name: S, addr: TableAddr) -> ExportInst {
+        ExportInst {
+            name: name.into(),
+            value: ExternVal::Table(addr),
+        }
+    }
+
     pub fn mem<S: Into<String>>(name: S, addr: MemAddr) -> ExportInst {
         ExportInst {
             name: name.into(),
@@ -22,6 +29,13 @@ impl ExportInst {
         }
     }
 
+    pub fn global<S: Into<String>>(name: S, addr: GlobalAddr) -> ExportInst {
+        ExportInst {
+            name: name.into(),
+            value: ExternVal::Global(addr),
+        }
+    }
+
     pub fn name(&self) -> &str {
         &self.name
     }
@@ -31,16 +45,21 @@ impl ExportInst {
     }
 }
 
+#[derive(Clone, Copy)]
 pub enum ExternVal {
     Func(FuncAddr),
+    Table(TableAddr),
     Mem(MemAddr),
+    Global(GlobalAddr),
 }
 
 impl fmt::Debug for ExternVal {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             ExternVal::Func(a) => write!(f, "{}", a),
+            ExternVal::Table(a) => write!(f, "{}", a),
             ExternVal::Mem(a) => write!(f, "{}", a),
+            ExternVal::Global(a) => write!(f, "{}", a),
         }
     }
 }
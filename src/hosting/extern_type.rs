@@ -0,0 +1,140 @@
+use std::fmt;
+
+use crate::module::{FuncType, GlobalType, MemoryType, TableType};
+
+/// The type of an external value, which is what imports are matched against.
+#[derive(Clone, PartialEq)]
+pub enum ExternType {
+    Func(FuncType),
+    Table(TableType),
+    Memory(MemoryType),
+    Global(GlobalType),
+}
+
+impl ExternType {
+    /// Checks if a value of this type can satisfy an import of the `expected` type.
+    ///
+    /// Functions and globals must have exactly the expected type. Tables and memories may be
+    /// larger than expected, and may have a smaller maximum size, but must have a maximum
+    /// size if one is expected.
+    pub fn matches(&self, expected: &ExternType) -> bool {
+        match (self, expected) {
+            (ExternType::Func(actual), ExternType::Func(expected)) => actual == expected,
+            (ExternType::Table(actual), ExternType::Table(expected)) => {
+                actual.elem_type() == expected.elem_type()
+                    && limits_match(actual.min(), actual.max(), expected.min(), expected.max())
+            }
+            (ExternType::Memory(actual), ExternType::Memory(expected)) => {
+                limits_match(actual.min(), actual.max(), expected.min(), expected.max())
+            }
+            (ExternType::Global(actual), ExternType::Global(expected)) => actual == expected,
+            _ => false,
+        }
+    }
+}
+
+fn limits_match(
+    actual_min: usize,
+    actual_max: Option<usize>,
+    expected_min: usize,
+    expected_max: Option<usize>,
+) -> bool {
+    actual_min >= expected_min
+        && match (actual_max, expected_max) {
+            (_, None) => true,
+            (Some(actual_max), Some(expected_max)) => actual_max <= expected_max,
+            (None, Some(_)) => false,
+        }
+}
+
+impl fmt::Display for ExternType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExternType::Func(x) => write!(f, "(func {})", x),
+            ExternType::Table(x) => write!(f, "{}", x),
+            ExternType::Memory(x) => write!(f, "{}", x),
+            ExternType::Global(x) => write!(f, "{}", x),
+        }
+    }
+}
+
+impl fmt::Debug for ExternType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{interp::exec::tests::link, Error};
+
+    fn assert_link_mismatch(text: &str, expected_type: &str, actual_type: &str) {
+        match link(text) {
+            Err(Error::ExportTypeMismatch {
+                expected, actual, ..
+            }) => {
+                assert_eq!(expected_type, expected.to_string());
+                assert_eq!(actual_type, actual.to_string());
+            }
+            Err(e) => panic!("Unexpected error: {:?}", e),
+            Ok(_) => panic!("Expected instantiation to fail"),
+        }
+    }
+
+    #[test]
+    pub fn func_import_must_match_type() {
+        assert!(link(r#"(import "spectest" "print_i32" (func (param i32)))"#).is_ok());
+        assert_link_mismatch(
+            r#"(import "spectest" "print_i32" (func (result i32)))"#,
+            "(func (result i32))",
+            "(func (param i32))",
+        );
+    }
+
+    #[test]
+    pub fn import_must_match_kind() {
+        assert_link_mismatch(
+            r#"(import "spectest" "print_i32" (global i32))"#,
+            "(global i32)",
+            "(func (param i32))",
+        );
+        assert_link_mismatch(
+            r#"(import "spectest" "table" (memory 1))"#,
+            "(memory 1)",
+            "(table 10 20 anyfunc)",
+        );
+    }
+
+    #[test]
+    pub fn table_import_limits_must_match() {
+        assert!(link(r#"(import "spectest" "table" (table 10 20 funcref))"#).is_ok());
+        assert!(link(r#"(import "spectest" "table" (table 5 funcref))"#).is_ok());
+        assert!(link(r#"(import "spectest" "table" (table 5 25 funcref))"#).is_ok());
+        assert_link_mismatch(
+            r#"(import "spectest" "table" (table 11 funcref))"#,
+            "(table 11 anyfunc)",
+            "(table 10 20 anyfunc)",
+        );
+        assert_link_mismatch(
+            r#"(import "spectest" "table" (table 10 15 funcref))"#,
+            "(table 10 15 anyfunc)",
+            "(table 10 20 anyfunc)",
+        );
+    }
+
+    #[test]
+    pub fn memory_import_limits_must_match() {
+        assert!(link(r#"(import "env" "memory" (memory 1))"#).is_ok());
+        assert!(link(r#"(import "env" "memory" (memory 256 256))"#).is_ok());
+        assert_link_mismatch(
+            r#"(import "env" "memory" (memory 257))"#,
+            "(memory 257)",
+            "(memory 256 256)",
+        );
+        assert_link_mismatch(
+            r#"(import "env" "memory" (memory 1 100))"#,
+            "(memory 1 100)",
+            "(memory 256 256)",
+        );
+    }
+}
@@ -1,16 +1,18 @@
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, TryLockError};
 
 use crate::{
-    hosting::{Host, HostFunc},
+    hosting::{Host, HostFunc, IntoHostFunc},
     interp::Thread,
-    module::{FuncType, MemoryType},
+    module::{ElemType, FuncType, GlobalType, MemoryType, TableType},
     Trap, Value,
 };
 
 pub trait ExternalModule {
     fn name(&self) -> &str;
     fn funcs(&self) -> &[Arc<ExternalFunc>];
+    fn tables(&self) -> &[ExternalTable];
     fn mems(&self) -> &[ExternalMemory];
+    fn globals(&self) -> &[ExternalGlobal];
 }
 
 #[derive(Clone)]
@@ -21,7 +23,11 @@ pub struct ExternalFunc {
 }
 
 impl ExternalFunc {
-    pub fn new<S: Into<String>>(name: S, typ: FuncType, imp: HostFunc) -> ExternalFunc {
+    pub fn new<S, F>(name: S, typ: FuncType, imp: F) -> ExternalFunc
+    where
+        S: Into<String>,
+        F: Fn(&mut Host, &mut Thread, &[Value]) -> Result<Vec<Value>, Trap> + Send + Sync + 'static,
+    {
         ExternalFunc {
             name: name.into(),
             typ,
@@ -29,6 +35,51 @@ impl ExternalFunc {
         }
     }
 
+    /// Creates a function from a closure that mutates its own state.
+    ///
+    /// Only one call to the closure can run at a time, so if the closure calls back into
+    /// WebAssembly code which calls the function again, that call traps.
+    pub fn new_mut<S, F>(name: S, typ: FuncType, imp: F) -> ExternalFunc
+    where
+        S: Into<String>,
+        F: FnMut(&mut Host, &mut Thread, &[Value]) -> Result<Vec<Value>, Trap> + Send + 'static,
+    {
+        let name = name.into();
+        let imp = Mutex::new(imp);
+        let func_name = name.clone();
+        ExternalFunc::new(
+            name,
+            typ,
+            move |host: &mut Host, thread: &mut Thread, values: &[Value]| {
+                let mut imp = match imp.try_lock() {
+                    Ok(imp) => imp,
+                    // A previous call panicked, which doesn't prevent the closure from being called again
+                    Err(TryLockError::Poisoned(e)) => e.into_inner(),
+                    Err(TryLockError::WouldBlock) => {
+                        return Err(
+                            format!("Function '{}' was called recursively", func_name).into()
+                        )
+                    }
+                };
+                (*imp)(host, thread, values)
+            },
+        )
+    }
+
+    /// Creates a function from a Rust closure, deriving the function's type from the closure's.
+    ///
+    /// Parameters can be of any [`WasmType`](crate::WasmType), and the closure can return
+    /// nothing, a single value, a tuple of values or a `Result` of those (see
+    /// [`HostResults`](crate::hosting::HostResults)), for example
+    /// `ExternalFunc::wrap("add", |a: i32, b: i64| -> f32 { ... })`.
+    pub fn wrap<S, F, P, R>(name: S, func: F) -> ExternalFunc
+    where
+        S: Into<String>,
+        F: IntoHostFunc<P, R>,
+    {
+        func.into_external(name.into())
+    }
+
     pub fn name(&self) -> &str {
         &self.name
     }
@@ -38,25 +89,63 @@ impl ExternalFunc {
     }
 
     pub fn invoke(&self, host: &mut Host, thread: &mut Thread) -> Result<Vec<Value>, Trap> {
-        // Pop values off the stack
+        // Pop values off the stack, the last parameter is on top of the stack
         let values = {
             let mut vals = Vec::new();
-            for param in self.typ.params().iter() {
+            for param in self.typ.params().iter().rev() {
                 match thread.stack_mut().pop()? {
                     v if v.typ() != *param => {
                         return Err(format!(
                             "Type mismatch. Function expects '{}' but '{}' is on top of the stack.",
                             param,
                             v.typ()
-                        ).into())
+                        )
+                        .into())
                     }
                     v => vals.push(v),
                 }
             }
+            vals.reverse();
             vals
         };
 
-        (self.imp)(host, thread, &values)
+        self.call(host, thread, &values)
+    }
+
+    /// Calls the function with the provided parameters, which must match its type.
+    pub fn call(
+        &self,
+        host: &mut Host,
+        thread: &mut Thread,
+        values: &[Value],
+    ) -> Result<Vec<Value>, Trap> {
+        (self.imp)(host, thread, values)
+    }
+}
+
+pub struct ExternalTable {
+    name: String,
+    typ: TableType,
+}
+
+impl ExternalTable {
+    pub fn new<S: Into<String>>(
+        name: S,
+        min_size: usize,
+        max_size: Option<usize>,
+    ) -> ExternalTable {
+        ExternalTable {
+            name: name.into(),
+            typ: TableType::new(ElemType::AnyFunc, min_size, max_size),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn typ(&self) -> &TableType {
+        &self.typ
     }
 }
 
@@ -85,3 +174,194 @@ impl ExternalMemory {
         &self.typ
     }
 }
+
+pub struct ExternalGlobal {
+    name: String,
+    typ: GlobalType,
+    value: Value,
+}
+
+impl ExternalGlobal {
+    pub fn new<S: Into<String>>(name: S, typ: GlobalType, value: Value) -> ExternalGlobal {
+        ExternalGlobal {
+            name: name.into(),
+            typ,
+            value,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn typ(&self) -> &GlobalType {
+        &self.typ
+    }
+
+    pub fn value(&self) -> Value {
+        self.value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        hosting::ExternVal,
+        interp::exec::tests::{host, Funcs},
+        FromValue, ValType,
+    };
+
+    #[test]
+    pub fn host_functions_can_use_captured_state_and_host_data() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        struct Config {
+            factor: u32,
+        }
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counted = calls.clone();
+        let mut total = 0;
+        let mut host = host();
+        host.set_data(Config { factor: 3 });
+        host.external(Funcs {
+            name: "stateful",
+            funcs: vec![
+                Arc::new(ExternalFunc::new(
+                    "scale",
+                    FuncType::new(vec![ValType::I32], vec![ValType::I32]),
+                    move |host: &mut Host, _thread: &mut Thread, values: &[Value]| {
+                        counted.fetch_add(1, Ordering::SeqCst);
+                        let factor = host.data::<Config>().unwrap().factor;
+                        Ok(vec![Value::I32(u32::from_value(values[0])? * factor)])
+                    },
+                )),
+                Arc::new(ExternalFunc::new_mut(
+                    "accumulate",
+                    FuncType::new(vec![ValType::I32], vec![ValType::I32]),
+                    move |_host: &mut Host, _thread: &mut Thread, values: &[Value]| {
+                        total += u32::from_value(values[0])?;
+                        Ok(vec![Value::I32(total)])
+                    },
+                )),
+            ],
+        })
+        .unwrap();
+        let module_addr = host
+            .instantiate(
+                "test",
+                crate::text::parse_module(
+                    r#"
+                    (import "stateful" "scale" (func $scale (param i32) (result i32)))
+                    (import "stateful" "accumulate" (func $accumulate (param i32) (result i32)))
+                    (func (export "test") (param i32) (result i32)
+                        (call $accumulate (call $scale (local.get 0))))"#,
+                )
+                .unwrap(),
+            )
+            .unwrap()
+            .addr();
+        let func_addr = match host.resolve_import(module_addr, "test").unwrap().value() {
+            ExternVal::Func(f) => *f,
+            _ => panic!("'test' is not a function!"),
+        };
+
+        let mut thread = Thread::new();
+        assert_eq!(
+            Ok(vec![Value::I32(6)]),
+            thread.call(&mut host, module_addr, func_addr, vec![Value::I32(2)])
+        );
+        assert_eq!(
+            Ok(vec![Value::I32(21)]),
+            thread.call(&mut host, module_addr, func_addr, vec![Value::I32(5)])
+        );
+        assert_eq!(2, calls.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    pub fn wrapped_closures_derive_their_type() {
+        let add = ExternalFunc::wrap("add", |a: i32, b: i64| -> f32 { a as f32 + b as f32 });
+        assert_eq!(
+            FuncType::new(vec![ValType::I32, ValType::I64], vec![ValType::F32]),
+            *add.typ()
+        );
+        let split = ExternalFunc::wrap("split", |x: u64| (x as u32, (x >> 32) as u32));
+        assert_eq!(
+            FuncType::new(vec![ValType::I64], vec![ValType::I32, ValType::I32]),
+            *split.typ()
+        );
+        let check = ExternalFunc::wrap("check", || -> Result<(), Trap> { Ok(()) });
+        assert_eq!(FuncType::empty(), *check.typ());
+    }
+
+    #[test]
+    pub fn wrapped_closures_can_be_called() {
+        let offset = 0.5;
+        let mut host = host();
+        let wrapped_addr = host
+            .external(Funcs {
+                name: "wrapped",
+                funcs: vec![
+                    Arc::new(ExternalFunc::wrap("add", move |a: i32, b: i64| {
+                        a as f32 + b as f32 + offset
+                    })),
+                    Arc::new(ExternalFunc::wrap("check", |x: i32| {
+                        if x < 0 {
+                            Err(Trap::new("negative"))
+                        } else {
+                            Ok(x)
+                        }
+                    })),
+                    Arc::new(ExternalFunc::wrap("split", |x: u64| {
+                        (x as u32, (x >> 32) as u32)
+                    })),
+                ],
+            })
+            .unwrap();
+        let module_addr = host
+            .instantiate(
+                "test",
+                crate::text::parse_module(
+                    r#"
+                    (import "wrapped" "add" (func $add (param i32 i64) (result f32)))
+                    (import "wrapped" "check" (func $check (param i32) (result i32)))
+                    (func (export "test") (param i32) (result f32)
+                        (call $add (call $check (local.get 0)) (i64.const 10)))"#,
+                )
+                .unwrap(),
+            )
+            .unwrap()
+            .addr();
+        let func = |host: &Host, module_addr, name| match host
+            .resolve_import(module_addr, name)
+            .unwrap()
+            .value()
+        {
+            ExternVal::Func(f) => *f,
+            _ => panic!("'{}' is not a function!", name),
+        };
+
+        let test_addr = func(&host, module_addr, "test");
+        let mut thread = Thread::new();
+        assert_eq!(
+            Ok(vec![Value::F32(12.5)]),
+            thread.call(&mut host, module_addr, test_addr, vec![Value::I32(2)])
+        );
+        let trap = thread
+            .call(&mut host, module_addr, test_addr, vec![Value::from(-2)])
+            .unwrap_err();
+        assert_eq!("negative", trap.cause().message());
+
+        let split_addr = func(&host, wrapped_addr, "split");
+        assert_eq!(
+            Ok(vec![Value::I32(2), Value::I32(1)]),
+            thread.call(
+                &mut host,
+                wrapped_addr,
+                split_addr,
+                vec![Value::I64(0x1_0000_0002)]
+            )
+        );
+    }
+}
@@ -1,7 +1,8 @@
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
 
 use crate::{
     hosting::{ExternalFunc, ModuleAddr},
+    interp::{Op, SlotCode},
     module::{FuncBody, FuncType},
 };
 
@@ -11,14 +12,23 @@ pub struct FuncInst {
     typ: FuncType,
     module: ModuleAddr,
     imp: FuncImpl,
+    /// The function's code compiled for the slot engine, the first time it runs on that engine.
+    slot_code: OnceLock<Arc<SlotCode>>,
 }
 
 impl FuncInst {
-    pub fn local(typ: FuncType, module: ModuleAddr, func_id: usize, code: FuncBody) -> FuncInst {
+    pub fn local(
+        typ: FuncType,
+        module: ModuleAddr,
+        func_id: usize,
+        code: FuncBody,
+        ops: Vec<Op>,
+    ) -> FuncInst {
         FuncInst {
             typ,
             module,
-            imp: FuncImpl::Local(code, func_id),
+            imp: FuncImpl::Local(code, func_id, ops),
+            slot_code: OnceLock::new(),
         }
     }
 
@@ -27,6 +37,7 @@ impl FuncInst {
             typ,
             module,
             imp: FuncImpl::External(func),
+            slot_code: OnceLock::new(),
         }
     }
 
@@ -41,9 +52,15 @@ impl FuncInst {
     pub fn imp(&self) -> &FuncImpl {
         &self.imp
     }
+
+    pub(crate) fn slot_code(&self) -> &OnceLock<Arc<SlotCode>> {
+        &self.slot_code
+    }
 }
 
 pub enum FuncImpl {
-    Local(FuncBody, usize),
+    /// A function defined by a module, with its code, its index in the module and its code
+    /// lowered for the interpreter.
+    Local(FuncBody, usize, Vec<Op>),
     External(Arc<ExternalFunc>),
 }
@@ -0,0 +1,44 @@
+use std::sync::RwLock;
+
+use crate::{module::GlobalType, TrapCause, Value};
+
+addr_type!(GlobalAddr);
+
+pub struct GlobalInst {
+    typ: GlobalType,
+    value: RwLock<Value>,
+}
+
+impl GlobalInst {
+    pub fn new(typ: GlobalType, value: Value) -> GlobalInst {
+        GlobalInst {
+            typ,
+            value: RwLock::new(value),
+        }
+    }
+
+    pub fn typ(&self) -> &GlobalType {
+        &self.typ
+    }
+
+    pub fn value(&self) -> Value {
+        *self.value.read().expect("Global lock was poisoned!")
+    }
+
+    /// Sets the value of the global.
+    ///
+    /// Fails if the global is immutable, or the value doesn't match the type of the global.
+    pub fn set(&self, value: Value) -> Result<(), TrapCause> {
+        if !self.typ.mutable() {
+            Err("Cannot set an immutable global".into())
+        } else if value.typ() != self.typ.typ() {
+            Err(TrapCause::TypeMismatch {
+                expected: self.typ.typ(),
+                actual: value.typ(),
+            })
+        } else {
+            *self.value.write().expect("Global lock was poisoned!") = value;
+            Ok(())
+        }
+    }
+}
@@ -1,19 +1,27 @@
-use std::sync::Arc;
+use std::{any::Any, collections::HashMap, sync::Arc};
 
 use crate::{
     hosting::{
-        ExportInst, ExternVal, ExternalModule, FuncAddr, FuncImpl, FuncInst, MemAddr, MemInst,
-        ModuleAddr, ModuleInst,
+        ExportInst, ExternType, ExternVal, ExternalModule, FuncAddr, FuncImpl, FuncInst,
+        GlobalAddr, GlobalInst, Instance, MemAddr, MemInst, ModuleAddr, ModuleInst, TableAddr,
+        TableInst,
     },
-    module::{Export, Expr, MemberDesc, Module},
-    Error, Instruction, Location, Value,
+    interp::{self, Thread},
+    module::{validate, Export, ExportDesc, Expr, MemberDesc, Module},
+    Error, Location, Value,
 };
 
 #[derive(Clone)]
 pub struct Host {
     modules: Vec<Arc<ModuleInst>>,
     funcs: Vec<Arc<FuncInst>>,
+    tables: Vec<Arc<TableInst>>,
     mems: Vec<Arc<MemInst>>,
+    globals: Vec<Arc<GlobalInst>>,
+    /// Additional names that modules have been registered under, see [`Host::register`].
+    aliases: HashMap<String, ModuleAddr>,
+    /// Data provided by the embedder, see [`Host::set_data`].
+    data: Option<Arc<dyn Any + Send + Sync>>,
 }
 
 // TODO: Consider if this type needs to be thread-safe
@@ -22,10 +30,44 @@ impl Host {
         Host {
             modules: Vec::new(),
             funcs: Vec::new(),
+            tables: Vec::new(),
             mems: Vec::new(),
+            globals: Vec::new(),
+            aliases: HashMap::new(),
+            data: None,
         }
     }
 
+    /// Stores data for host functions to use, replacing any data stored before.
+    ///
+    /// This is where an embedder keeps state that host functions need, such as handles to
+    /// resources or configuration. Host functions get it back with [`Host::data`], which
+    /// requires the type stored here. Clones of the host share the data.
+    pub fn set_data<T: Any + Send + Sync>(&mut self, data: T) {
+        self.data = Some(Arc::new(data));
+    }
+
+    /// Gets the data stored by [`Host::set_data`], or `None` if there is none or it's not a `T`.
+    pub fn data<T: Any>(&self) -> Option<&T> {
+        self.data.as_ref().and_then(|data| data.downcast_ref())
+    }
+
+    /// Gets mutable access to the data stored by [`Host::set_data`].
+    ///
+    /// Returns `None` if there is no data, it's not a `T`, or it's shared with a clone of the
+    /// host. Data that is shared can still use interior mutability.
+    pub fn data_mut<T: Any>(&mut self) -> Option<&mut T> {
+        self.data
+            .as_mut()
+            .and_then(Arc::get_mut)
+            .and_then(|data| data.downcast_mut())
+    }
+
+    /// Removes the data stored by [`Host::set_data`].
+    pub fn clear_data(&mut self) {
+        self.data = None;
+    }
+
     pub fn get_module(&self, addr: ModuleAddr) -> Arc<ModuleInst> {
         self.modules[addr.val()].clone()
     }
@@ -34,10 +76,18 @@ impl Host {
         self.funcs[addr.val()].clone()
     }
 
+    pub fn get_table(&self, addr: TableAddr) -> Arc<TableInst> {
+        self.tables[addr.val()].clone()
+    }
+
     pub fn get_mem(&self, addr: MemAddr) -> Arc<MemInst> {
         self.mems[addr.val()].clone()
     }
 
+    pub fn get_global(&self, addr: GlobalAddr) -> Arc<GlobalInst> {
+        self.globals[addr.val()].clone()
+    }
+
     pub fn modules<'a>(&'a self) -> impl 'a + Iterator<Item = Arc<ModuleInst>> {
         self.modules.iter().cloned()
     }
@@ -46,22 +96,53 @@ impl Host {
         self.funcs.iter().cloned()
     }
 
+    pub fn tables<'a>(&'a self) -> impl 'a + Iterator<Item = Arc<TableInst>> {
+        self.tables.iter().cloned()
+    }
+
     pub fn mems<'a>(&'a self) -> impl 'a + Iterator<Item = Arc<MemInst>> {
         self.mems.iter().cloned()
     }
 
+    pub fn globals<'a>(&'a self) -> impl 'a + Iterator<Item = Arc<GlobalInst>> {
+        self.globals.iter().cloned()
+    }
+
+    /// Registers an instantiated module under an additional name, so that modules instantiated
+    /// later can import its exports using that name.
+    ///
+    /// Registering a name again replaces the previous registration, and registered names take
+    /// precedence over the names modules were instantiated with.
+    pub fn register<S: Into<String>>(&mut self, name: S, module: ModuleAddr) {
+        self.aliases.insert(name.into(), module);
+    }
+
+    /// Finds a module by a name it was registered or instantiated with.
     pub fn find_module(&self, name: &str) -> Option<ModuleAddr> {
+        if let Some(addr) = self.aliases.get(name) {
+            return Some(*addr);
+        }
         self.modules
             .iter()
             .position(|m| m.name() == name)
             .map(|a| ModuleAddr::new(a + 1).expect("Searched module address should be non-zero!"))
     }
 
+    pub fn resolve_table(&self, module: ModuleAddr, table_idx: usize) -> TableAddr {
+        let module_inst = &self.modules[module.val()];
+        module_inst.get_table(table_idx)
+    }
+
     pub fn resolve_mem(&self, module: ModuleAddr, mem_idx: usize) -> MemAddr {
         let module_inst = &self.modules[module.val()];
         module_inst.get_mem(mem_idx)
     }
 
+    pub fn resolve_global(&self, module: ModuleAddr, global_idx: usize) -> GlobalAddr {
+        let module_inst = &self.modules[module.val()];
+        module_inst.get_global(global_idx)
+    }
+
     pub fn resolve_func(&self, module: ModuleAddr, func_idx: usize) -> FuncAddr {
         let module_inst = &self.modules[module.val()];
         module_inst.get_func(func_idx)
@@ -79,15 +160,29 @@ impl Host {
         }
     }
 
-    /// Evaluates an expression at the module scope.
-    pub fn eval_expr(&mut self, expr: &Expr) -> Result<Value, Error> {
-        // Offset must be a constant expression
-        match expr.instructions() {
-            [Instruction::I32Const(v)] => Ok(*v),
-            _ => panic!("expr not implemented!"),
+    /// Gets the current type of an external value.
+    pub fn extern_type(&self, value: ExternVal) -> ExternType {
+        match value {
+            ExternVal::Func(addr) => ExternType::Func(self.funcs[addr.val()].typ().clone()),
+            ExternVal::Table(addr) => ExternType::Table(self.tables[addr.val()].typ()),
+            ExternVal::Mem(addr) => ExternType::Memory(self.mems[addr.val()].typ()),
+            ExternVal::Global(addr) => ExternType::Global(self.globals[addr.val()].typ().clone()),
         }
     }
 
+    /// Evaluates a constant expression at the module scope.
+    ///
+    /// The `globals` provided are the globals available to the module so far.
+    pub fn eval_expr(&self, expr: &Expr, globals: &[GlobalAddr]) -> Result<Value, Error> {
+        expr.eval_const(|global_idx| {
+            globals.get(global_idx).map(|addr| {
+                let global = &self.globals[addr.val()];
+                (global.typ().clone(), global.value())
+            })
+        })
+        .map_err(|kind| Error::Validation(kind.into()))
+    }
+
     /// Resolves a [`Location`] based on a provided [`FuncAddr`] and offset
     pub fn get_location(&self, addr: FuncAddr, offset: usize) -> Option<Location> {
         if addr.val() < self.funcs.len() {
@@ -96,7 +191,7 @@ impl Host {
 
             let func_name = match func.imp() {
                 FuncImpl::External(f) => Some(f.name().to_owned()),
-                FuncImpl::Local(_, id) => module
+                FuncImpl::Local(_, id, _) => module
                     .names()
                     .and_then(|n| n.funcs().get(*id))
                     .and_then(|n| n.func_name())
@@ -121,6 +216,9 @@ impl Host {
             .expect("New module address should be non-zero!");
 
         let mut funcs = Vec::new();
+        let mut tables = Vec::new();
+        let mut mems = Vec::new();
+        let mut globals = Vec::new();
         let mut exports = Vec::new();
         for (idx, func) in module.funcs().iter().enumerate() {
             // Allocate a func in the host
@@ -129,28 +227,51 @@ impl Host {
             let func_inst = FuncInst::external(func.typ().clone(), module_addr, func.clone());
             self.funcs.push(Arc::new(func_inst));
             funcs.push(func_addr);
-            exports.push(Export::new(
-                func.name().to_owned(),
-                MemberDesc::Function(idx),
-            ))
+            exports.push(Export::func(func.name().to_owned(), idx))
         }
 
-        // Export memories
-        for mem in module.mems() {
-            exports.push(Export::new(
-                mem.name().to_owned(),
-                MemberDesc::Memory(mem.typ().clone()),
-            ));
+        // Allocate and export tables
+        for (idx, table) in module.tables().iter().enumerate() {
+            let table_addr = TableAddr::new(self.tables.len() + 1)
+                .expect("New table address should be non-zero!");
+            self.tables
+                .push(Arc::new(TableInst::from_type(table.typ())));
+            tables.push(table_addr);
+            exports.push(Export::table(table.name().to_owned(), idx));
+        }
+
+        // Allocate and export memories
+        for (idx, mem) in module.mems().iter().enumerate() {
+            let mem_addr =
+                MemAddr::new(self.mems.len() + 1).expect("New memory address should be non-zero!");
+            self.mems.push(Arc::new(MemInst::from_type(mem.typ())?));
+            mems.push(mem_addr);
+            exports.push(Export::mem(mem.name().to_owned(), idx));
+        }
+
+        // Allocate and export globals
+        for (idx, global) in module.globals().iter().enumerate() {
+            let global_addr = GlobalAddr::new(self.globals.len() + 1)
+                .expect("New global address should be non-zero!");
+            self.globals.push(Arc::new(GlobalInst::new(
+                global.typ().clone(),
+                global.value(),
+            )));
+            globals.push(global_addr);
+            exports.push(Export::global(global.name().to_owned(), idx));
         }
 
         // Export the synthetic module
-        let exports = self.export_module(&funcs, &exports)?;
+        let exports = self.export_module(&funcs, &tables, &mems, &globals, &exports)?;
 
         // Register the module and return
         self.modules.push(Arc::new(ModuleInst::new(
             module.name().to_owned(),
-            funcs,
             Vec::new(),
+            funcs,
+            tables,
+            mems,
+            globals,
             exports,
             None,
         )));
@@ -158,131 +279,553 @@ impl Host {
     }
 
     /// Instantiates the provided [`Module`], consuming it in the process.
+    ///
+    /// The module is validated first, and rejected with [`Error::Validation`] if it is invalid.
+    /// If the module has a start function, it is invoked once the instance is registered. If
+    /// instantiation fails (including a trap in the start function), every instance allocated
+    /// by this call is removed from the host, and the table elements and memory contents that its
+    /// segments overwrote in imported tables and memories are restored.
+    ///
+    /// The returned [`Instance`] is used to look up the exports of the new instance.
     pub fn instantiate<S: Into<String>>(
         &mut self,
         name: S,
         module: Module,
-    ) -> Result<ModuleAddr, Error> {
+    ) -> Result<Instance, Error> {
+        validate(&module)?;
+
+        let lens = (
+            self.modules.len(),
+            self.funcs.len(),
+            self.tables.len(),
+            self.mems.len(),
+            self.globals.len(),
+        );
+
+        let result = self.instantiate_module(name, &module).and_then(
+            |(module_addr, overwritten)| match self.run_start(module_addr, &module) {
+                Ok(()) => Ok(module_addr),
+                Err(e) => {
+                    self.restore(overwritten);
+                    Err(e)
+                }
+            },
+        );
+
+        if result.is_err() {
+            // Roll back the partially created instance
+            let (modules_len, funcs_len, tables_len, mems_len, globals_len) = lens;
+            self.modules.truncate(modules_len);
+            self.funcs.truncate(funcs_len);
+            self.tables.truncate(tables_len);
+            self.mems.truncate(mems_len);
+            self.globals.truncate(globals_len);
+        }
+        result.map(Instance::new)
+    }
+
+    fn instantiate_module<S: Into<String>>(
+        &mut self,
+        name: S,
+        module: &Module,
+    ) -> Result<(ModuleAddr, Overwritten), Error> {
         let module_addr = ModuleAddr::new(self.modules.len() + 1)
             .expect("New module address should be non-zero!");
 
         let mut funcs = Vec::new();
+        let mut tables = Vec::new();
         let mut mems = Vec::new();
-
-        self.resolve_imports(&module, &mut funcs, &mut mems)?;
-        self.instantiate_funcs(module_addr, &module, &mut funcs);
-        self.instantiate_data(&module, &mems)?;
-
-        let exports = self.export_module(&funcs, module.exports())?;
+        let mut globals = Vec::new();
+
+        self.resolve_imports(module, &mut funcs, &mut tables, &mut mems, &mut globals)?;
+        self.instantiate_tables(module, &mut tables);
+        self.instantiate_mems(module, &mut mems)?;
+        self.instantiate_globals(module, &mut globals)?;
+        self.instantiate_funcs(module_addr, module, &mut funcs)?;
+        let exports = self.export_module(&funcs, &tables, &mems, &globals, module.exports())?;
+
+        // Every segment is checked before any is written, so that a failure can't leave some of
+        // them written to imported tables and memories.
+        let elem_offsets = self.check_elems(module, &funcs, &tables, &globals)?;
+        let data_offsets = self.check_data(module, &mems, &globals)?;
+        let overwritten = Overwritten {
+            elems: self.instantiate_elems(module, &funcs, &tables, &elem_offsets),
+            data: self.instantiate_data(module, &mems, &data_offsets),
+        };
 
         self.modules.push(Arc::new(ModuleInst::new(
             name.into(),
+            module.types().clone(),
             funcs,
+            tables,
             mems,
+            globals,
             exports,
             module.names().cloned(),
         )));
-        Ok(module_addr)
+        Ok((module_addr, overwritten))
+    }
+
+    /// Restores the table elements and memory contents overwritten by an instantiation.
+    fn restore(&mut self, overwritten: Overwritten) {
+        // In reverse, in case segments overlap
+        for (table_addr, offset, elems) in overwritten.elems.into_iter().rev() {
+            self.tables[table_addr.val()].replace(offset, &elems);
+        }
+        for (mem_addr, offset, bytes) in overwritten.data.into_iter().rev() {
+            self.mems[mem_addr.val()]
+                .view_mut()
+                .write(offset, &bytes)
+                .expect("Memories can't shrink!");
+        }
+    }
+
+    fn run_start(&mut self, module_addr: ModuleAddr, module: &Module) -> Result<(), Error> {
+        if let Some(func_idx) = module.start() {
+            let func_addr = match self.modules[module_addr.val()].funcs().get(func_idx) {
+                Some(func_addr) => *func_addr,
+                None => return Err(Error::InvalidModule),
+            };
+            Thread::new().call(self, module_addr, func_addr, Vec::new())?;
+        }
+        Ok(())
     }
 
     fn export_module(
         &mut self,
         funcs: &[FuncAddr],
-        module_exports: &Vec<Export>,
+        tables: &[TableAddr],
+        mems: &[MemAddr],
+        globals: &[GlobalAddr],
+        module_exports: &[Export],
     ) -> Result<Vec<ExportInst>, Error> {
         let mut exports = Vec::new();
         for export in module_exports {
-            match export.description() {
-                MemberDesc::Function(func_idx) => {
-                    let func_addr = funcs[*func_idx as usize];
-                    let inst = ExportInst::func(export.name(), func_addr);
-                    exports.push(inst);
-                }
-                MemberDesc::Memory(ref mem_type) => {
-                    let mem_addr = MemAddr::new(self.mems.len() + 1)
-                        .expect("New memory address should be non-zero!");
-                    self.mems.push(Arc::new(MemInst::from_type(mem_type)?));
-                    let inst = ExportInst::mem(export.name(), mem_addr);
-                    exports.push(inst);
-                }
-                _ => { /* skip */ }
-            }
+            let inst = match export.description() {
+                ExportDesc::Function(func_idx) => match funcs.get(*func_idx) {
+                    Some(func_addr) => ExportInst::func(export.name(), *func_addr),
+                    None => return Err(Error::InvalidModule),
+                },
+                ExportDesc::Table(table_idx) => match tables.get(*table_idx) {
+                    Some(table_addr) => ExportInst::table(export.name(), *table_addr),
+                    None => return Err(Error::InvalidModule),
+                },
+                ExportDesc::Memory(mem_idx) => match mems.get(*mem_idx) {
+                    Some(mem_addr) => ExportInst::mem(export.name(), *mem_addr),
+                    None => return Err(Error::InvalidModule),
+                },
+                ExportDesc::Global(global_idx) => match globals.get(*global_idx) {
+                    Some(global_addr) => ExportInst::global(export.name(), *global_addr),
+                    None => return Err(Error::InvalidModule),
+                },
+            };
+            exports.push(inst);
         }
         Ok(exports)
     }
 
+    fn instantiate_tables(&mut self, module: &Module, tables: &mut Vec<TableAddr>) {
+        for table in module.tables() {
+            let table_addr = TableAddr::new(self.tables.len() + 1)
+                .expect("New table address should be non-zero!");
+            tables.push(table_addr);
+            self.tables.push(Arc::new(TableInst::from_type(table)));
+        }
+    }
+
+    fn instantiate_mems(&mut self, module: &Module, mems: &mut Vec<MemAddr>) -> Result<(), Error> {
+        // Defined memories follow the imported ones in the memory index space
+        for mem in module.mems() {
+            let mem_addr =
+                MemAddr::new(self.mems.len() + 1).expect("New memory address should be non-zero!");
+            mems.push(mem_addr);
+            self.mems.push(Arc::new(MemInst::from_type(mem)?));
+        }
+        Ok(())
+    }
+
+    fn instantiate_globals(
+        &mut self,
+        module: &Module,
+        globals: &mut Vec<GlobalAddr>,
+    ) -> Result<(), Error> {
+        for global in module.globals() {
+            // Initializers can only refer to the imported globals, which are already in the list.
+            let value = self.eval_expr(global.init(), globals)?;
+            if value.typ() != global.typ().typ() {
+                return Err(Error::InvalidModule);
+            }
+
+            let global_addr = GlobalAddr::new(self.globals.len() + 1)
+                .expect("New global address should be non-zero!");
+            globals.push(global_addr);
+            self.globals
+                .push(Arc::new(GlobalInst::new(global.typ().clone(), value)));
+        }
+        Ok(())
+    }
+
     fn instantiate_funcs(
         &mut self,
         instance_addr: ModuleAddr,
         module: &Module,
         funcs: &mut Vec<FuncAddr>,
-    ) {
-        // Instantiate functions
-        for (code_idx, type_id) in module.funcs().iter().enumerate() {
-            // Assign an address
-            let func_addr = FuncAddr::new(self.funcs.len() + 1)
-                .expect("New function address should be non-zero!");
+    ) -> Result<(), Error> {
+        // Assign addresses first, so that calls to any function in the module can be resolved
+        let first_func = self.funcs.len() + 1;
+        for idx in 0..module.funcs().len() {
+            let func_addr =
+                FuncAddr::new(first_func + idx).expect("New function address should be non-zero!");
             funcs.push(func_addr);
+        }
 
+        // Instantiate functions
+        for (code_idx, type_id) in module.funcs().iter().enumerate() {
             // Get the function body and type
             let typ = module.types()[*type_id as usize].clone();
             let body = module.code()[code_idx].clone();
 
+            // Lower the body for the interpreter
+            let ops = interp::compile(body.body(), funcs)?;
+
             // Create the instance and register it in the host
             self.funcs.push(Arc::new(FuncInst::local(
                 typ,
                 instance_addr,
                 code_idx,
                 body,
+                ops,
             )));
         }
+        Ok(())
     }
 
     fn resolve_imports(
         &mut self,
         module: &Module,
         funcs: &mut Vec<FuncAddr>,
+        tables: &mut Vec<TableAddr>,
         mems: &mut Vec<MemAddr>,
+        globals: &mut Vec<GlobalAddr>,
     ) -> Result<(), Error> {
         for import in module.imports() {
-            if let Some(module_addr) = self.find_module(&import.module()) {
-                let export = self.resolve_import(module_addr, &import.name())?;
-                match export.value() {
-                    ExternVal::Func(func_addr) => funcs.push(func_addr.clone()),
-                    ExternVal::Mem(mem_addr) => mems.push(mem_addr.clone()),
+            let module_addr = match self.find_module(import.module()) {
+                Some(module_addr) => module_addr,
+                None => {
+                    return Err(Error::ModuleNotFound {
+                        module: import.module().to_owned(),
+                    })
                 }
-            } else {
-                return Err(Error::ModuleNotFound {
+            };
+            let value = *self.resolve_import(module_addr, import.name())?.value();
+
+            let expected = match import.description() {
+                MemberDesc::Function(type_idx) => match module.types().get(*type_idx) {
+                    Some(typ) => ExternType::Func(typ.clone()),
+                    None => return Err(Error::InvalidModule),
+                },
+                MemberDesc::Table(typ) => ExternType::Table(typ.clone()),
+                MemberDesc::Memory(typ) => ExternType::Memory(typ.clone()),
+                MemberDesc::Global(typ) => ExternType::Global(typ.clone()),
+            };
+            let actual = self.extern_type(value);
+            if !actual.matches(&expected) {
+                return Err(Error::ExportTypeMismatch {
                     module: import.module().to_owned(),
+                    name: import.name().to_owned(),
+                    expected: Box::new(expected),
+                    actual: Box::new(actual),
                 });
             }
+
+            match value {
+                ExternVal::Func(func_addr) => funcs.push(func_addr),
+                ExternVal::Table(table_addr) => tables.push(table_addr),
+                ExternVal::Mem(mem_addr) => mems.push(mem_addr),
+                ExternVal::Global(global_addr) => globals.push(global_addr),
+            }
         }
         Ok(())
     }
 
-    fn instantiate_data(&mut self, module: &Module, mems: &Vec<MemAddr>) -> Result<(), Error> {
-        for data in module.data() {
-            let offset = match self.eval_expr(data.expr())? {
+    /// Checks that the element segments fit in their tables, returning their offsets.
+    fn check_elems(
+        &self,
+        module: &Module,
+        funcs: &[FuncAddr],
+        tables: &[TableAddr],
+        globals: &[GlobalAddr],
+    ) -> Result<Vec<usize>, Error> {
+        let mut offsets = Vec::with_capacity(module.elems().len());
+        for (index, elem) in module.elems().iter().enumerate() {
+            let offset = match self.eval_expr(elem.expr(), globals)? {
                 Value::I32(i) => i as usize,
                 _ => return Err(Error::InvalidModule),
             };
-
-            // Find an initialize the memory
-            let mem_addr = mems[data.index() as usize];
-            let mem_inst = &mut self.mems[mem_addr.val()];
-            let mut mem = mem_inst.memory();
-
-            // Bounds check
-            let end = offset + data.init().len();
-            if (offset + end) > mem.len() {
+            if elem.init().iter().any(|func_idx| *func_idx >= funcs.len()) {
                 return Err(Error::InvalidModule);
             }
+            let table_inst = match tables.get(elem.index()) {
+                Some(table_addr) => &self.tables[table_addr.val()],
+                None => return Err(Error::InvalidModule),
+            };
+            match offset.checked_add(elem.init().len()) {
+                Some(end) if end <= table_inst.len() => offsets.push(offset),
+                _ => return Err(Error::ElemSegmentOutOfBounds { index }),
+            }
+        }
+        Ok(offsets)
+    }
 
-            // Safe because instatiation is single-threaded.
-            unsafe {
-                mem.data()[offset..end].copy_from_slice(data.init());
+    /// Checks that the data segments fit in their memories, returning their offsets.
+    fn check_data(
+        &self,
+        module: &Module,
+        mems: &[MemAddr],
+        globals: &[GlobalAddr],
+    ) -> Result<Vec<usize>, Error> {
+        let mut offsets = Vec::with_capacity(module.data().len());
+        for (index, data) in module.data().iter().enumerate() {
+            let offset = match self.eval_expr(data.expr(), globals)? {
+                Value::I32(i) => i as usize,
+                _ => return Err(Error::InvalidModule),
+            };
+            let mem_inst = match mems.get(data.index()) {
+                Some(mem_addr) => &self.mems[mem_addr.val()],
+                None => return Err(Error::InvalidModule),
+            };
+            match offset.checked_add(data.init().len()) {
+                Some(end) if end <= mem_inst.memory().len() => offsets.push(offset),
+                _ => return Err(Error::DataSegmentOutOfBounds { index }),
             }
         }
-        Ok(())
+        Ok(offsets)
+    }
+
+    /// Writes the element segments, which have been checked by [`Host::check_elems`], returning
+    /// the elements they replaced.
+    fn instantiate_elems(
+        &mut self,
+        module: &Module,
+        funcs: &[FuncAddr],
+        tables: &[TableAddr],
+        offsets: &[usize],
+    ) -> Vec<(TableAddr, usize, Vec<Option<FuncAddr>>)> {
+        let mut overwritten = Vec::new();
+        for (elem, offset) in module.elems().iter().zip(offsets) {
+            let elem_funcs: Vec<_> = elem.init().iter().map(|idx| Some(funcs[*idx])).collect();
+            let table_addr = tables[elem.index()];
+            let replaced = self.tables[table_addr.val()]
+                .replace(*offset, &elem_funcs)
+                .expect("Element segments should have been checked!");
+            overwritten.push((table_addr, *offset, replaced));
+        }
+        overwritten
+    }
+
+    /// Writes the data segments, which have been checked by [`Host::check_data`], returning the
+    /// bytes they replaced.
+    fn instantiate_data(
+        &mut self,
+        module: &Module,
+        mems: &[MemAddr],
+        offsets: &[usize],
+    ) -> Vec<(MemAddr, usize, Vec<u8>)> {
+        let mut overwritten = Vec::new();
+        for (data, offset) in module.data().iter().zip(offsets) {
+            let mem_addr = mems[data.index()];
+            let mut view = self.mems[mem_addr.val()].view_mut();
+            let replaced = view
+                .read(*offset, data.init().len())
+                .map(|bytes| bytes.to_vec())
+                .and_then(|replaced| view.write(*offset, data.init()).map(|_| replaced))
+                .expect("Data segments should have been checked!");
+            overwritten.push((mem_addr, *offset, replaced));
+        }
+        overwritten
+    }
+}
+
+/// The table elements and memory contents overwritten by an instantiation's segments, restored if
+/// its start function traps.
+struct Overwritten {
+    elems: Vec<(TableAddr, usize, Vec<Option<FuncAddr>>)>,
+    data: Vec<(MemAddr, usize, Vec<u8>)>,
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        builder::{FuncBuilder, ModuleBuilder},
+        hosting::{ExternVal, Host, MemAddr, ModuleAddr},
+        interp::{
+            exec::tests::{global, host, instantiate_text, invoke, link},
+            Thread,
+        },
+        module::{GlobalType, Import, MemberDesc},
+        Error, ValType, Value,
+    };
+
+    #[test]
+    pub fn global_set_updates_mutable_global() {
+        use crate::Instruction::*;
+
+        let module = ModuleBuilder::new()
+            .global(global(ValType::I64, true, I64Const(Value::I64(5))))
+            .func(
+                FuncBuilder::new()
+                    .result(ValType::I64)
+                    .export_as("test")
+                    .body(vec![
+                        GlobalGet(0),
+                        I64Const(Value::I64(1)),
+                        I64Add,
+                        GlobalSet(0),
+                        GlobalGet(0),
+                    ]),
+            )
+            .build();
+        assert_eq!(Ok(vec![Value::I64(6)]), invoke(module, "test", vec![]));
+    }
+
+    #[test]
+    pub fn imported_global_initializes_defined_global() {
+        use crate::Instruction::*;
+
+        let mut builder = ModuleBuilder::new();
+        builder.imports.push(Import::new(
+            "spectest",
+            "global_i32",
+            MemberDesc::Global(GlobalType::new(ValType::I32, false)),
+        ));
+        let module = builder
+            .global(global(ValType::I32, false, GlobalGet(0)))
+            .func(
+                FuncBuilder::new()
+                    .result(ValType::I32)
+                    .export_as("test")
+                    .body(vec![GlobalGet(1)]),
+            )
+            .build();
+        assert_eq!(Ok(vec![Value::I32(666)]), invoke(module, "test", vec![]));
+    }
+
+    #[test]
+    pub fn global_import_with_wrong_type_fails_to_link() {
+        let mut builder = ModuleBuilder::new();
+        builder.imports.push(Import::new(
+            "spectest",
+            "global_i32",
+            MemberDesc::Global(GlobalType::new(ValType::I32, true)),
+        ));
+        match host().instantiate("test", builder.build()) {
+            Err(Error::ExportTypeMismatch { .. }) => { /* expected */ }
+            Err(e) => panic!("Unexpected error: {:?}", e),
+            Ok(_) => panic!("Expected instantiation to fail"),
+        }
+    }
+
+    #[test]
+    pub fn host_data_is_typed_and_shared_with_clones() {
+        let mut host = Host::new();
+        assert_eq!(None, host.data::<u32>());
+
+        host.set_data(5u32);
+        assert_eq!(Some(&5), host.data::<u32>());
+        assert_eq!(None, host.data::<i32>());
+        *host.data_mut::<u32>().unwrap() += 1;
+
+        let clone = host.clone();
+        assert_eq!(Some(&6), clone.data::<u32>());
+        assert_eq!(None, host.data_mut::<u32>());
+
+        host.clear_data();
+        assert_eq!(None, host.data::<u32>());
+        assert_eq!(Some(&6), clone.data::<u32>());
+    }
+
+    fn exported_mem(host: &Host, module_addr: ModuleAddr, name: &str) -> MemAddr {
+        match host.resolve_import(module_addr, name).unwrap().value() {
+            ExternVal::Mem(m) => *m,
+            _ => panic!("'{}' is not a memory!", name),
+        }
+    }
+
+    #[test]
+    pub fn module_defined_memory_is_allocated_and_initialized() {
+        let (mut host, module_addr, func_addr) = instantiate_text(
+            r#"
+            (module
+              (memory (export "mem") 1 2)
+              (data (i32.const 65532) "\2a\00\00\00")
+              (func (export "test") (result i32) (i32.load (i32.const 65532))))
+            "#,
+        );
+        let mem_addr = exported_mem(&host, module_addr, "mem");
+        assert_eq!(&[mem_addr], host.get_module(module_addr).mems());
+        assert_eq!("(memory 1 2)", host.get_mem(mem_addr).typ().to_string());
+
+        let result = Thread::new().call(&mut host, module_addr, func_addr, vec![]);
+        assert_eq!(vec![Value::I32(42)], result.ok().unwrap());
+    }
+
+    #[test]
+    pub fn reexported_memory_is_the_imported_one() {
+        let mut host = host();
+        let mems = host.mems().count();
+        let text = r#"(module (import "env" "memory" (memory 1)) (export "mem" (memory 0)))"#;
+        let module_addr = host
+            .instantiate("test", crate::text::parse_module(text).unwrap())
+            .unwrap()
+            .addr();
+        let env_addr = host.find_module("env").unwrap();
+        assert_eq!(
+            exported_mem(&host, env_addr, "memory"),
+            exported_mem(&host, module_addr, "mem")
+        );
+        assert_eq!(mems, host.mems().count());
+    }
+
+    #[test]
+    pub fn data_must_fit_in_memory() {
+        let text = r#"(module (memory 1) (data (i32.const 65533) "\00\00\00\00"))"#;
+        match link(text) {
+            Err(Error::DataSegmentOutOfBounds { index: 0 }) => {}
+            Err(e) => panic!("Unexpected error: {:?}", e),
+            Ok(_) => panic!("Expected instantiation to fail"),
+        }
+    }
+
+    #[test]
+    pub fn initializers_evaluate_constant_expressions() {
+        let (mut host, module_addr, func_addr) = instantiate_text(
+            r#"
+            (module
+              (import "spectest" "global_i32" (global $i32 i32))
+              (import "spectest" "global_i64" (global $i64 i64))
+              (import "spectest" "global_f32" (global $f32 f32))
+              (import "spectest" "global_f64" (global $f64 f64))
+              (memory 1)
+              (global $a i64 (i64.sub (i64.mul (global.get $i64) (i64.const 2)) (i64.const 2)))
+              (global $b f32 (global.get $f32))
+              (global $c f64 (f64.const -1.5))
+              (data (i32.add (global.get $i32) (i32.const 4)) "\2a")
+              (func (export "test") (result i64 f32 f64 i32)
+                (global.get $a)
+                (global.get $b)
+                (global.get $c)
+                (i32.load8_u (i32.const 670))))
+            "#,
+        );
+        let result = Thread::new().call(&mut host, module_addr, func_addr, vec![]);
+        assert_eq!(
+            vec![
+                Value::I64(1330),
+                Value::F32(666.6),
+                Value::F64(-1.5),
+                Value::I32(42)
+            ],
+            result.ok().unwrap()
+        );
     }
 }
@@ -1,3 +1,86 @@
-use crate::{hosting::Host, interp::Thread, Trap, Value};
+use crate::{
+    hosting::{ExternalFunc, Host},
+    interp::Thread,
+    module::FuncType,
+    Trap, ValType, Value, WasmValues,
+};
 
-pub type HostFunc = fn(&mut Host, &mut Thread, &[Value]) -> Result<Vec<Value>, Trap>;
+/// A function implemented by the host.
+///
+/// Host functions can be plain functions or closures capturing state of their own. State shared
+/// by the whole embedding can be stored on the [`Host`] instead, see [`Host::set_data`].
+///
+/// Host functions may charge for the work they do using [`Thread::consume_fuel`], returning the
+/// resulting trap if the thread runs out of fuel.
+pub type HostFunc =
+    dyn Fn(&mut Host, &mut Thread, &[Value]) -> Result<Vec<Value>, Trap> + Send + Sync;
+
+/// The results of a host function wrapped by [`ExternalFunc::wrap`].
+///
+/// This is implemented for every [`WasmValues`], as well as for `Result<R, Trap>` where `R` is any
+/// of those, so that the function can trap.
+pub trait HostResults {
+    fn types() -> Vec<ValType>;
+    fn into_results(self) -> Result<Vec<Value>, Trap>;
+}
+
+impl<R: WasmValues> HostResults for R {
+    fn types() -> Vec<ValType> {
+        R::types()
+    }
+
+    fn into_results(self) -> Result<Vec<Value>, Trap> {
+        Ok(self.into_values())
+    }
+}
+
+impl<R: WasmValues> HostResults for Result<R, Trap> {
+    fn types() -> Vec<ValType> {
+        R::types()
+    }
+
+    fn into_results(self) -> Result<Vec<Value>, Trap> {
+        self.map(R::into_values)
+    }
+}
+
+/// A Rust function that can be wrapped into an [`ExternalFunc`] by [`ExternalFunc::wrap`].
+///
+/// This is implemented for `Fn` closures taking up to 8 [`WasmType`](crate::WasmType) parameters
+/// and returning [`HostResults`]. `Params` is the tuple of parameter types.
+pub trait IntoHostFunc<Params, Results> {
+    fn into_external(self, name: String) -> ExternalFunc;
+}
+
+macro_rules! impl_into_host_func {
+    ($($p: ident),*) => {
+        impl<F, R, $($p),*> IntoHostFunc<($($p,)*), R> for F
+        where
+            F: Fn($($p),*) -> R + Send + Sync + 'static,
+            R: HostResults,
+            ($($p,)*): WasmValues,
+        {
+            fn into_external(self, name: String) -> ExternalFunc {
+                let typ = FuncType::new(<($($p,)*) as WasmValues>::types(), R::types());
+                let imp = move |_host: &mut Host, _thread: &mut Thread, values: &[Value]| {
+                    // The interpreter has already checked the parameters against the type
+                    let mut values = values.iter().cloned();
+                    #[allow(non_snake_case)]
+                    let ($($p,)*) = <($($p,)*) as WasmValues>::from_values(&mut values)?;
+                    self($($p),*).into_results()
+                };
+                ExternalFunc::new(name, typ, imp)
+            }
+        }
+    };
+}
+
+impl_into_host_func!();
+impl_into_host_func!(A);
+impl_into_host_func!(A, B);
+impl_into_host_func!(A, B, C);
+impl_into_host_func!(A, B, C, D);
+impl_into_host_func!(A, B, C, D, E);
+impl_into_host_func!(A, B, C, D, E, G);
+impl_into_host_func!(A, B, C, D, E, G, H);
+impl_into_host_func!(A, B, C, D, E, G, H, I);
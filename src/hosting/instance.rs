@@ -0,0 +1,241 @@
+use std::{marker::PhantomData, sync::Arc};
+
+use crate::{
+    hosting::{ExternType, ExternVal, FuncAddr, GlobalInst, Host, MemInst, ModuleAddr},
+    interp::Thread,
+    module::FuncType,
+    Error, Trap, Value, WasmValues,
+};
+
+/// A handle to a module instantiated by [`Host::instantiate`], used to look up its exports.
+///
+/// Like the addresses it wraps, the handle is only meaningful for the [`Host`] that created it.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Instance {
+    addr: ModuleAddr,
+}
+
+impl Instance {
+    pub fn new(addr: ModuleAddr) -> Instance {
+        Instance { addr }
+    }
+
+    pub fn addr(&self) -> ModuleAddr {
+        self.addr
+    }
+
+    /// Gets the value exported with the specified name, if any.
+    pub fn get_export(&self, host: &Host, name: &str) -> Option<ExternVal> {
+        host.get_module(self.addr)
+            .find_export(name)
+            .map(|export| *export.value())
+    }
+
+    /// Gets the function exported with the specified name, if any.
+    pub fn get_func(&self, host: &Host, name: &str) -> Option<Func> {
+        match self.get_export(host, name) {
+            Some(ExternVal::Func(addr)) => Some(Func {
+                module: self.addr,
+                addr,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Gets the memory exported with the specified name, if any.
+    pub fn get_memory(&self, host: &Host, name: &str) -> Option<Arc<MemInst>> {
+        match self.get_export(host, name) {
+            Some(ExternVal::Mem(addr)) => Some(host.get_mem(addr)),
+            _ => None,
+        }
+    }
+
+    /// Gets the global exported with the specified name, if any.
+    pub fn get_global(&self, host: &Host, name: &str) -> Option<Arc<GlobalInst>> {
+        match self.get_export(host, name) {
+            Some(ExternVal::Global(addr)) => Some(host.get_global(addr)),
+            _ => None,
+        }
+    }
+
+    /// Gets the function exported with the specified name, checking that it takes the
+    /// parameters `P` and returns the results `R`.
+    ///
+    /// For example, `get_typed_func::<(i32, i64), f32>(&host, "name")` gets a function of type
+    /// `(func (param i32 i64) (result f32))`.
+    pub fn get_typed_func<P, R>(&self, host: &Host, name: &str) -> Result<TypedFunc<P, R>, Error>
+    where
+        P: WasmValues,
+        R: WasmValues,
+    {
+        let value = match self.get_export(host, name) {
+            Some(value) => value,
+            None => {
+                return Err(Error::ExportNotFound {
+                    module: host.get_module(self.addr).name().to_owned(),
+                    name: name.to_owned(),
+                })
+            }
+        };
+
+        let expected = ExternType::Func(FuncType::new(P::types(), R::types()));
+        let actual = host.extern_type(value);
+        match value {
+            ExternVal::Func(addr) if actual.matches(&expected) => Ok(TypedFunc {
+                func: Func {
+                    module: self.addr,
+                    addr,
+                },
+                marker: PhantomData,
+            }),
+            _ => Err(Error::ExportTypeMismatch {
+                module: host.get_module(self.addr).name().to_owned(),
+                name: name.to_owned(),
+                expected: Box::new(expected),
+                actual: Box::new(actual),
+            }),
+        }
+    }
+}
+
+/// A function exported by an [`Instance`], called with untyped values.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Func {
+    module: ModuleAddr,
+    addr: FuncAddr,
+}
+
+impl Func {
+    pub fn addr(&self) -> FuncAddr {
+        self.addr
+    }
+
+    pub fn typ(&self, host: &Host) -> FuncType {
+        host.get_func(self.addr).typ().clone()
+    }
+
+    /// Calls the function on a new [`Thread`].
+    pub fn call(&self, host: &mut Host, values: &[Value]) -> Result<Vec<Value>, Trap> {
+        self.call_on(&mut Thread::new(), host, values)
+    }
+
+    /// Calls the function on the provided [`Thread`], for example to limit the fuel it uses.
+    pub fn call_on(
+        &self,
+        thread: &mut Thread,
+        host: &mut Host,
+        values: &[Value],
+    ) -> Result<Vec<Value>, Trap> {
+        thread.call(host, self.module, self.addr, values.to_vec())
+    }
+}
+
+/// A function exported by an [`Instance`] whose type has been checked against the parameters
+/// `P` and results `R`, see [`Instance::get_typed_func`].
+pub struct TypedFunc<P, R> {
+    func: Func,
+    marker: PhantomData<fn(P) -> R>,
+}
+
+impl<P: WasmValues, R: WasmValues> TypedFunc<P, R> {
+    pub fn func(&self) -> Func {
+        self.func
+    }
+
+    /// Calls the function on a new [`Thread`].
+    pub fn call(&self, host: &mut Host, params: P) -> Result<R, Trap> {
+        self.call_on(&mut Thread::new(), host, params)
+    }
+
+    /// Calls the function on the provided [`Thread`], for example to limit the fuel it uses.
+    pub fn call_on(&self, thread: &mut Thread, host: &mut Host, params: P) -> Result<R, Trap> {
+        let results = self.func.call_on(thread, host, &params.into_values())?;
+        Ok(R::from_values(&mut results.into_iter())?)
+    }
+}
+
+impl<P, R> Clone for TypedFunc<P, R> {
+    fn clone(&self) -> Self {
+        TypedFunc {
+            func: self.func,
+            marker: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{interp::exec::tests::host, Error, Value};
+
+    #[test]
+    pub fn instances_look_up_exports() {
+        let mut host = host();
+        let text = r#"
+            (module
+              (memory (export "mem") 1)
+              (global (export "answer") i32 (i32.const 42))
+              (func (export "mix") (param i32 i64) (result f32)
+                (f32.add (f32.convert_i32_s (local.get 0)) (f32.convert_i64_s (local.get 1))))
+              (func (export "swap") (param i32 i32) (result i32 i32)
+                (local.get 1) (local.get 0)))
+        "#;
+        let instance = host
+            .instantiate("test", crate::text::parse_module(text).unwrap())
+            .unwrap();
+
+        assert_eq!(
+            Value::I32(42),
+            instance.get_global(&host, "answer").unwrap().value()
+        );
+        assert_eq!(
+            65536,
+            instance.get_memory(&host, "mem").unwrap().view().len()
+        );
+        assert!(instance.get_memory(&host, "answer").is_none());
+        assert!(instance.get_func(&host, "missing").is_none());
+
+        let mix = instance.get_func(&host, "mix").unwrap();
+        assert_eq!("(param i32 i64) (result f32)", mix.typ(&host).to_string());
+        let result = mix.call(&mut host, &[Value::from(-2), Value::I64(7)]);
+        assert_eq!(vec![Value::F32(5.0)], result.ok().unwrap());
+
+        let mix = instance
+            .get_typed_func::<(i32, i64), f32>(&host, "mix")
+            .unwrap();
+        assert_eq!(5.0, mix.call(&mut host, (-2, 7)).ok().unwrap());
+        let swap = instance
+            .get_typed_func::<(u32, u32), (u32, u32)>(&host, "swap")
+            .unwrap();
+        assert_eq!((2, 1), swap.call(&mut host, (1, 2)).ok().unwrap());
+    }
+
+    #[test]
+    pub fn typed_funcs_are_checked_up_front() {
+        let mut host = host();
+        let text = r#"(module (func (export "f") (param i32) (result i32) (local.get 0)))"#;
+        let instance = host
+            .instantiate("test", crate::text::parse_module(text).unwrap())
+            .unwrap();
+
+        match instance.get_typed_func::<i64, i32>(&host, "f") {
+            Err(Error::ExportTypeMismatch {
+                expected, actual, ..
+            }) => {
+                assert_eq!("(func (param i64) (result i32))", expected.to_string());
+                assert_eq!("(func (param i32) (result i32))", actual.to_string());
+            }
+            Err(e) => panic!("Unexpected error: {:?}", e),
+            Ok(_) => panic!("Expected a type mismatch"),
+        }
+        match instance.get_typed_func::<(), ()>(&host, "g") {
+            Err(Error::ExportNotFound { module, name }) => {
+                assert_eq!("test", module);
+                assert_eq!("g", name);
+            }
+            Err(e) => panic!("Unexpected error: {:?}", e),
+            Ok(_) => panic!("Expected the export to be missing"),
+        }
+        let f = instance.get_typed_func::<i32, i32>(&host, "f").unwrap();
+        assert_eq!(-5, f.call(&mut host, -5).ok().unwrap());
+    }
+}
@@ -1,9 +1,11 @@
-use crate::{module::MemoryType, Error, Memory, PAGE_SIZE};
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use crate::{hosting::MemoryView, module::MemoryType, Error, Memory, PAGE_SIZE};
 
 addr_type!(MemAddr);
 
 pub struct MemInst {
-    mem: Memory,
+    mem: RwLock<Memory>,
 }
 
 impl MemInst {
@@ -19,11 +21,37 @@ impl MemInst {
 
     pub fn new(min_size: usize, max_size: Option<usize>) -> Result<MemInst, Error> {
         Ok(MemInst {
-            mem: Memory::new(min_size, max_size)?,
+            mem: RwLock::new(Memory::new(min_size, max_size)?),
         })
     }
 
-    pub fn memory(&self) -> &Memory {
-        &self.mem
+    /// Gets the current [`MemoryType`] of this memory, using the current size as the minimum.
+    pub fn typ(&self) -> MemoryType {
+        let memory = self.memory();
+        MemoryType::new(memory.pages(), memory.max_size().map(|max| max / PAGE_SIZE))
+    }
+
+    pub fn memory(&self) -> RwLockReadGuard<'_, Memory> {
+        self.mem.read().expect("Memory lock was poisoned!")
+    }
+
+    /// Gets a mutable reference to the memory, used to grow it.
+    pub fn memory_mut(&self) -> RwLockWriteGuard<'_, Memory> {
+        self.mem.write().expect("Memory lock was poisoned!")
+    }
+
+    /// Gets a view providing bounds-checked read access to the contents of the memory.
+    ///
+    /// The memory is locked for reading for as long as the view is alive, other views from this
+    /// method can be held at the same time.
+    pub fn view(&self) -> MemoryView<RwLockReadGuard<'_, Memory>> {
+        MemoryView::new(self.memory())
+    }
+
+    /// Gets a view providing bounds-checked read and write access to the contents of the memory.
+    ///
+    /// The memory is locked for writing for as long as the view is alive.
+    pub fn view_mut(&self) -> MemoryView<RwLockWriteGuard<'_, Memory>> {
+        MemoryView::new(self.memory_mut())
     }
 }
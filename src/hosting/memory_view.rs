@@ -0,0 +1,248 @@
+use std::{
+    fmt,
+    ops::{Deref, DerefMut},
+    str,
+};
+
+use crate::{interp::exec::MemoryValue, Memory, Trap, TrapCause};
+
+/// An error accessing a memory through a [`MemoryView`].
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MemoryError {
+    /// The `len` bytes starting at `addr` are not all inside the memory.
+    OutOfBounds { addr: usize, len: usize },
+    /// The `len` bytes starting at `addr` are not a valid UTF-8 string.
+    InvalidUtf8 { addr: usize, len: usize },
+}
+
+impl fmt::Display for MemoryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MemoryError::OutOfBounds { addr, len } => write!(
+                f,
+                "out of bounds memory access ({} bytes at 0x{:08x})",
+                len, addr
+            ),
+            MemoryError::InvalidUtf8 { addr, len } => {
+                write!(f, "invalid UTF-8 string ({} bytes at 0x{:08x})", len, addr)
+            }
+        }
+    }
+}
+
+impl From<MemoryError> for Trap {
+    fn from(e: MemoryError) -> Trap {
+        match e {
+            MemoryError::OutOfBounds { .. } => TrapCause::OutOfBoundsMemoryAccess.into(),
+            MemoryError::InvalidUtf8 { .. } => e.to_string().into(),
+        }
+    }
+}
+
+/// Provides bounds-checked access to the contents of a memory, see [`MemInst::view`] and
+/// [`MemInst::view_mut`].
+///
+/// The view holds the memory's lock, a read lock shared with other views that only read or a
+/// write lock for views that can also write, so it should be dropped before running any more
+/// WebAssembly code.
+///
+/// [`MemInst::view`]: crate::hosting::MemInst::view
+/// [`MemInst::view_mut`]: crate::hosting::MemInst::view_mut
+pub struct MemoryView<M> {
+    mem: M,
+}
+
+macro_rules! read_accessors {
+    ($($t: ty, $read: ident;)*) => {
+        $(
+            #[doc = concat!("Reads a little-endian `", stringify!($t), "` at `addr`.")]
+            pub fn $read(&self, addr: usize) -> Result<$t, MemoryError> {
+                self.read_value(addr)
+            }
+        )*
+    };
+}
+
+macro_rules! write_accessors {
+    ($($t: ty, $write: ident;)*) => {
+        $(
+            #[doc = concat!("Writes a little-endian `", stringify!($t), "` at `addr`.")]
+            pub fn $write(&mut self, addr: usize, value: $t) -> Result<(), MemoryError> {
+                self.write_value(addr, value)
+            }
+        )*
+    };
+}
+
+impl<M: Deref<Target = Memory>> MemoryView<M> {
+    pub(crate) fn new(mem: M) -> MemoryView<M> {
+        MemoryView { mem }
+    }
+
+    /// Gets the size of the memory, in bytes.
+    pub fn len(&self) -> usize {
+        self.mem.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mem.len() == 0
+    }
+
+    /// Gets the `len` bytes starting at `addr`.
+    pub fn read(&self, addr: usize, len: usize) -> Result<&[u8], MemoryError> {
+        let end = self.end(addr, len)?;
+        Ok(&self.mem.bytes()[addr..end])
+    }
+
+    /// Reads the UTF-8 string made of the `len` bytes starting at `ptr`.
+    pub fn read_str(&self, ptr: usize, len: usize) -> Result<&str, MemoryError> {
+        str::from_utf8(self.read(ptr, len)?)
+            .map_err(|_| MemoryError::InvalidUtf8 { addr: ptr, len })
+    }
+
+    /// Reads the NUL-terminated UTF-8 string starting at `ptr`, excluding the terminator.
+    pub fn read_cstr(&self, ptr: usize) -> Result<&str, MemoryError> {
+        let rest = self.read(ptr, self.len().saturating_sub(ptr))?;
+        match rest.iter().position(|b| *b == 0) {
+            Some(len) => self.read_str(ptr, len),
+            None => Err(MemoryError::OutOfBounds {
+                addr: ptr,
+                len: rest.len() + 1,
+            }),
+        }
+    }
+
+    read_accessors! {
+        u8, read_u8;
+        i8, read_i8;
+        u16, read_u16;
+        i16, read_i16;
+        u32, read_u32;
+        i32, read_i32;
+        u64, read_u64;
+        i64, read_i64;
+        f32, read_f32;
+        f64, read_f64;
+    }
+
+    fn read_value<T: MemoryValue>(&self, addr: usize) -> Result<T, MemoryError> {
+        self.read(addr, T::SIZE).map(T::read_le)
+    }
+
+    /// Gets the end of the `len` bytes starting at `addr`, if they are all inside the memory.
+    fn end(&self, addr: usize, len: usize) -> Result<usize, MemoryError> {
+        match addr.checked_add(len) {
+            Some(end) if end <= self.mem.len() => Ok(end),
+            _ => Err(MemoryError::OutOfBounds { addr, len }),
+        }
+    }
+}
+
+impl<M: DerefMut<Target = Memory>> MemoryView<M> {
+    /// Copies `data` into the memory, starting at `addr`.
+    pub fn write(&mut self, addr: usize, data: &[u8]) -> Result<(), MemoryError> {
+        let end = self.end(addr, data.len())?;
+        self.mem.bytes_mut()[addr..end].copy_from_slice(data);
+        Ok(())
+    }
+
+    write_accessors! {
+        u8, write_u8;
+        i8, write_i8;
+        u16, write_u16;
+        i16, write_i16;
+        u32, write_u32;
+        i32, write_i32;
+        u64, write_u64;
+        i64, write_i64;
+        f32, write_f32;
+        f64, write_f64;
+    }
+
+    fn write_value<T: MemoryValue>(&mut self, addr: usize, value: T) -> Result<(), MemoryError> {
+        let end = self.end(addr, T::SIZE)?;
+        value.write_le(&mut self.mem.bytes_mut()[addr..end]);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{hosting::MemInst, PAGE_SIZE};
+
+    #[test]
+    pub fn typed_accessors_are_little_endian() {
+        let mem = MemInst::new(PAGE_SIZE, None).unwrap();
+        let mut view = mem.view_mut();
+        view.write_u32(8, 0x0403_0201).unwrap();
+        assert_eq!(&[1, 2, 3, 4], view.read(8, 4).unwrap());
+        assert_eq!(0x0201, view.read_u16(8).unwrap());
+
+        view.write_i64(16, -2).unwrap();
+        assert_eq!(u64::MAX - 1, view.read_u64(16).unwrap());
+        view.write_f64(PAGE_SIZE - 8, 1.5).unwrap();
+        assert_eq!(1.5, view.read_f64(PAGE_SIZE - 8).unwrap());
+    }
+
+    #[test]
+    pub fn accesses_outside_memory_are_errors() {
+        let mem = MemInst::new(PAGE_SIZE, None).unwrap();
+        let mut view = mem.view_mut();
+        assert_eq!(
+            Err(MemoryError::OutOfBounds {
+                addr: PAGE_SIZE - 3,
+                len: 4
+            }),
+            view.read_u32(PAGE_SIZE - 3)
+        );
+        assert_eq!(
+            Err(MemoryError::OutOfBounds {
+                addr: usize::MAX,
+                len: 2
+            }),
+            view.write(usize::MAX, &[1, 2])
+        );
+        assert!(view.read(PAGE_SIZE, 0).is_ok());
+
+        let trap: Trap = MemoryError::OutOfBounds { addr: 0, len: 1 }.into();
+        assert!(*trap.cause() == TrapCause::OutOfBoundsMemoryAccess);
+    }
+
+    #[test]
+    pub fn reads_strings() {
+        let mem = MemInst::new(PAGE_SIZE, None).unwrap();
+        let mut view = mem.view_mut();
+        view.write(4, b"hello\0world").unwrap();
+        assert_eq!(Ok("hello"), view.read_str(4, 5));
+        assert_eq!(Ok("hello"), view.read_cstr(4));
+        assert_eq!(Ok(""), view.read_cstr(9));
+
+        view.write(PAGE_SIZE - 2, b"\xff!").unwrap();
+        assert_eq!(
+            Err(MemoryError::InvalidUtf8 {
+                addr: PAGE_SIZE - 2,
+                len: 2
+            }),
+            view.read_str(PAGE_SIZE - 2, 2)
+        );
+        assert_eq!(
+            Err(MemoryError::OutOfBounds {
+                addr: PAGE_SIZE - 1,
+                len: 2
+            }),
+            view.read_cstr(PAGE_SIZE - 1)
+        );
+    }
+
+    #[test]
+    pub fn read_views_can_be_held_together() {
+        let mem = MemInst::new(PAGE_SIZE, None).unwrap();
+        mem.view_mut().write(0, b"shared").unwrap();
+        let view = mem.view();
+        let other = mem.view();
+        assert_eq!(Ok("shared"), view.read_str(0, 6));
+        assert_eq!(view.read(0, 6), other.read(0, 6));
+        assert_eq!(PAGE_SIZE, mem.memory().len());
+    }
+}
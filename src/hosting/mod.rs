@@ -1,8 +1,8 @@
 macro_rules! addr_type {
     ($name: ident) => {
-        #[derive(Clone, Copy, PartialEq)]
+        #[derive(Clone, Copy, PartialEq, Debug)]
         pub struct $name(::std::num::NonZeroUsize);
-        
+
         impl $name {
             pub fn new(id: usize) -> Option<$name> {
                 match ::std::num::NonZeroUsize::new(id) {
@@ -10,12 +10,12 @@ macro_rules! addr_type {
                     None => None,
                 }
             }
-        
+
             pub fn val(&self) -> usize {
                 self.0.get() - 1
             }
         }
-        
+
         impl ::std::fmt::Display for $name {
             fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
                 write!(f, concat!("[", stringify!($name), "]0x{:04X}"), self.0)
@@ -37,17 +37,29 @@ macro_rules! addr_type {
 }
 
 mod export_inst;
+mod extern_type;
+mod external;
 mod func_inst;
+mod global_inst;
 mod host;
+mod host_func;
+mod instance;
 mod mem_inst;
+mod memory_view;
 mod module_inst;
-mod external;
-mod host_func;
+mod table_inst;
 
 pub use self::export_inst::{ExportInst, ExternVal};
+pub use self::extern_type::ExternType;
+pub use self::external::{
+    ExternalFunc, ExternalGlobal, ExternalMemory, ExternalModule, ExternalTable,
+};
 pub use self::func_inst::{FuncAddr, FuncImpl, FuncInst};
+pub use self::global_inst::{GlobalAddr, GlobalInst};
 pub use self::host::Host;
+pub use self::host_func::{HostFunc, HostResults, IntoHostFunc};
+pub use self::instance::{Func, Instance, TypedFunc};
 pub use self::mem_inst::{MemAddr, MemInst};
+pub use self::memory_view::{MemoryError, MemoryView};
 pub use self::module_inst::{ModuleAddr, ModuleInst};
-pub use self::external::{ExternalModule, ExternalFunc, ExternalMemory};
-pub use self::host_func::HostFunc;
+pub use self::table_inst::{TableAddr, TableInst};
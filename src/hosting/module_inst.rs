@@ -1,6 +1,6 @@
 use crate::{
-    hosting::{ExportInst, FuncAddr, MemAddr},
-    module::ModuleNames,
+    hosting::{ExportInst, FuncAddr, GlobalAddr, MemAddr, TableAddr},
+    module::{FuncType, ModuleNames},
 };
 
 addr_type!(ModuleAddr);
@@ -8,24 +8,34 @@ addr_type!(ModuleAddr);
 pub struct ModuleInst {
     // TODO: Consider making names Cow<'static, str>
     name: String,
+    types: Vec<FuncType>,
     funcs: Vec<FuncAddr>,
+    tables: Vec<TableAddr>,
     mems: Vec<MemAddr>,
+    globals: Vec<GlobalAddr>,
     exports: Vec<ExportInst>,
     names: Option<ModuleNames>,
 }
 
 impl ModuleInst {
+    #[allow(clippy::too_many_arguments)]
     pub fn new<S: Into<String>>(
         name: S,
+        types: Vec<FuncType>,
         funcs: Vec<FuncAddr>,
+        tables: Vec<TableAddr>,
         mems: Vec<MemAddr>,
+        globals: Vec<GlobalAddr>,
         exports: Vec<ExportInst>,
         names: Option<ModuleNames>,
     ) -> ModuleInst {
         ModuleInst {
             name: name.into(),
+            types,
             funcs,
+            tables,
             mems,
+            globals,
             exports,
             names,
         }
@@ -35,14 +45,26 @@ impl ModuleInst {
         &self.name
     }
 
+    pub fn types(&self) -> &[FuncType] {
+        &self.types
+    }
+
     pub fn funcs(&self) -> &[FuncAddr] {
         &self.funcs
     }
 
+    pub fn tables(&self) -> &[TableAddr] {
+        &self.tables
+    }
+
     pub fn mems(&self) -> &[MemAddr] {
         &self.mems
     }
 
+    pub fn globals(&self) -> &[GlobalAddr] {
+        &self.globals
+    }
+
     pub fn exports(&self) -> &[ExportInst] {
         &self.exports
     }
@@ -51,10 +73,18 @@ impl ModuleInst {
         self.names.as_ref()
     }
 
+    pub fn get_table(&self, table_idx: usize) -> TableAddr {
+        self.tables[table_idx]
+    }
+
     pub fn get_mem(&self, mem_idx: usize) -> MemAddr {
         self.mems[mem_idx]
     }
 
+    pub fn get_global(&self, global_idx: usize) -> GlobalAddr {
+        self.globals[global_idx]
+    }
+
     pub fn get_func(&self, func_idx: usize) -> FuncAddr {
         self.funcs[func_idx]
     }
@@ -0,0 +1,89 @@
+use std::sync::RwLock;
+
+use crate::{
+    hosting::FuncAddr,
+    module::{ElemType, TableType},
+};
+
+addr_type!(TableAddr);
+
+pub struct TableInst {
+    elem_type: ElemType,
+    max: Option<usize>,
+    elements: RwLock<Vec<Option<FuncAddr>>>,
+}
+
+impl TableInst {
+    pub fn from_type(table_type: &TableType) -> TableInst {
+        TableInst {
+            elem_type: table_type.elem_type(),
+            max: table_type.max(),
+            elements: RwLock::new(vec![None; table_type.min()]),
+        }
+    }
+
+    /// Gets the current [`TableType`] of this table, using the current size as the minimum.
+    pub fn typ(&self) -> TableType {
+        TableType::new(self.elem_type, self.len(), self.max)
+    }
+
+    pub fn len(&self) -> usize {
+        self.elements
+            .read()
+            .expect("Table lock was poisoned!")
+            .len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Gets the element at the specified index.
+    ///
+    /// ## Returns
+    /// `None` if the index is out of bounds, `Some(None)` if the element is uninitialized.
+    pub fn get(&self, idx: usize) -> Option<Option<FuncAddr>> {
+        self.elements
+            .read()
+            .expect("Table lock was poisoned!")
+            .get(idx)
+            .cloned()
+    }
+
+    /// Initializes the elements starting at `offset` with the provided function addresses.
+    ///
+    /// ## Returns
+    /// `false`, without modifying the table, if the range is out of bounds.
+    pub fn init(&self, offset: usize, funcs: &[FuncAddr]) -> bool {
+        let mut elements = self.elements.write().expect("Table lock was poisoned!");
+        match offset.checked_add(funcs.len()) {
+            Some(end) if end <= elements.len() => {
+                for (element, func) in elements[offset..end].iter_mut().zip(funcs) {
+                    *element = Some(*func);
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Replaces the elements starting at `offset`, returning the elements that were replaced.
+    ///
+    /// ## Returns
+    /// `None`, without modifying the table, if the range is out of bounds.
+    pub fn replace(
+        &self,
+        offset: usize,
+        elems: &[Option<FuncAddr>],
+    ) -> Option<Vec<Option<FuncAddr>>> {
+        let mut elements = self.elements.write().expect("Table lock was poisoned!");
+        match offset.checked_add(elems.len()) {
+            Some(end) if end <= elements.len() => {
+                let replaced = elements[offset..end].to_vec();
+                elements[offset..end].copy_from_slice(elems);
+                Some(replaced)
+            }
+            _ => None,
+        }
+    }
+}
@@ -1,6 +1,6 @@
 use std::{fmt, io};
 
-use byteorder::{LittleEndian, ReadBytesExt};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 
 use crate::{utils, Error, Value};
 
@@ -8,11 +8,40 @@ use crate::{utils, Error, Value};
 pub struct BranchTable(Vec<u32>, u32);
 
 impl BranchTable {
+    pub fn new(targets: Vec<u32>, default: u32) -> BranchTable {
+        BranchTable(targets, default)
+    }
+
     pub fn read<R: io::Read>(reader: &mut R) -> Result<BranchTable, Error> {
         let branches = utils::read_vec(reader, utils::read_leb128_u32)?;
         let else_case = utils::read_leb128_u32(reader)?;
         Ok(BranchTable(branches, else_case))
     }
+
+    pub fn write<W: io::Write>(&self, writer: &mut W) -> Result<(), Error> {
+        utils::write_vec(writer, &self.0, |w, t| utils::write_leb128_u32(w, *t))?;
+        utils::write_leb128_u32(writer, self.1)
+    }
+
+    /// Gets the label depths of the indexed branch targets.
+    pub fn targets(&self) -> &[u32] {
+        &self.0
+    }
+
+    /// Gets the label depth to branch to when the operand is out of range of the table.
+    pub fn default(&self) -> u32 {
+        self.1
+    }
+
+    /// Gets the label depth to branch to for the provided operand.
+    ///
+    /// Operands that are out of range of the table select the default target.
+    pub fn target(&self, idx: u32) -> u32 {
+        match self.0.get(idx as usize) {
+            Some(t) => *t,
+            None => self.1,
+        }
+    }
 }
 
 impl fmt::Display for BranchTable {
@@ -49,6 +78,17 @@ impl Instruction {
             insts.push(inst);
         }
     }
+
+    /// Writes a sequence of instructions, followed by the `end` instruction that terminates it.
+    pub fn write_sequence<W: io::Write>(
+        insts: &[Instruction],
+        writer: &mut W,
+    ) -> Result<(), Error> {
+        for inst in insts {
+            inst.write(writer)?;
+        }
+        Instruction::End.write(writer)
+    }
 }
 
 #[inline]
@@ -77,3 +117,40 @@ fn read_f64<R: io::Read>(reader: &mut R) -> Result<Value, Error> {
     let bits = reader.read_u64::<LittleEndian>()?;
     Ok(Value::F64(f64::from_bits(bits)))
 }
+
+#[inline]
+fn write_idx<W: io::Write>(writer: &mut W, idx: u32) -> Result<(), Error> {
+    utils::write_leb128_u32(writer, idx)
+}
+
+#[inline]
+fn write_i32<W: io::Write>(writer: &mut W, value: &Value) -> Result<(), Error> {
+    match value {
+        Value::I32(x) => utils::write_leb128_s(writer, *x as i32 as i64),
+        _ => Err(Error::InvalidModule),
+    }
+}
+
+#[inline]
+fn write_i64<W: io::Write>(writer: &mut W, value: &Value) -> Result<(), Error> {
+    match value {
+        Value::I64(x) => utils::write_leb128_s(writer, *x as i64),
+        _ => Err(Error::InvalidModule),
+    }
+}
+
+#[inline]
+fn write_f32<W: io::Write>(writer: &mut W, value: &Value) -> Result<(), Error> {
+    match value {
+        Value::F32(x) => Ok(writer.write_u32::<LittleEndian>(x.to_bits())?),
+        _ => Err(Error::InvalidModule),
+    }
+}
+
+#[inline]
+fn write_f64<W: io::Write>(writer: &mut W, value: &Value) -> Result<(), Error> {
+    match value {
+        Value::F64(x) => Ok(writer.write_u64::<LittleEndian>(x.to_bits())?),
+        _ => Err(Error::InvalidModule),
+    }
+}
@@ -0,0 +1,203 @@
+use crate::{
+    interp::{Label, Op, Thread},
+    Trap, TrapCause,
+};
+
+pub fn exec(thread: &mut Thread, op: &Op) -> Result<(), Trap> {
+    match *op {
+        Op::Block {
+            arity,
+            continuation,
+        } => enter(thread, Label::new(arity, height(thread), continuation)),
+        Op::Loop { start } => {
+            // Branching to a loop re-executes the 'loop' instruction itself, which
+            // re-enters the label. In WASM v1, loops never take values on a branch.
+            enter(thread, Label::new(0, height(thread), start));
+        }
+        Op::If {
+            arity,
+            else_start,
+            continuation,
+        } => {
+            let cond = thread.stack_mut().pop_as::<u32>()?;
+            if cond != 0 {
+                enter(thread, Label::new(arity, height(thread), continuation));
+            } else if let Some(else_start) = else_start {
+                enter(thread, Label::new(arity, height(thread), continuation));
+                thread.stack_mut().current_mut().jump(else_start);
+            } else {
+                thread.stack_mut().current_mut().jump(continuation);
+            }
+        }
+        Op::Else => {
+            // We only hit an 'else' when we finish executing the 'then' branch,
+            // so just exit the 'if' block.
+            thread.stack_mut().current_mut().branch(0)?;
+        }
+        Op::End => {
+            if thread.stack_mut().current_mut().pop_label().is_none() {
+                return Err(TrapCause::from("'end' without a matching block").into());
+            }
+        }
+        Op::Br(depth) => thread.stack_mut().current_mut().branch(depth as usize)?,
+        Op::BrIf(depth) => {
+            if thread.stack_mut().pop_as::<u32>()? != 0 {
+                thread.stack_mut().current_mut().branch(depth as usize)?;
+            }
+        }
+        Op::BrTable(ref table) => {
+            let idx = thread.stack_mut().pop_as::<u32>()?;
+            thread
+                .stack_mut()
+                .current_mut()
+                .branch(table.target(idx) as usize)?;
+        }
+        Op::Return => {
+            // The outermost label of a function is the function body itself.
+            let depth = thread.stack().current().label_depth();
+            if depth == 0 {
+                return Err(TrapCause::from("'return' outside of a function").into());
+            }
+            thread.stack_mut().current_mut().branch(depth - 1)?;
+        }
+        _ => unreachable!("Not a control op: {:?}", op),
+    }
+
+    Ok(())
+}
+
+fn height(thread: &Thread) -> usize {
+    thread.stack().current().height()
+}
+
+fn enter(thread: &mut Thread, label: Label) {
+    thread.stack_mut().current_mut().push_label(label)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        builder::FuncBuilder, interp::exec::tests::call, BranchTable, Instruction::*, ValType,
+        Value,
+    };
+
+    fn select_func() -> FuncBuilder {
+        // (func (param i32) (result i32)
+        //   (block (result i32)
+        //     (block
+        //       (block
+        //         (br_table 0 1 1 (local.get 0)))
+        //       (br 1 (i32.const 10)))
+        //     (i32.const 20)))
+        FuncBuilder::new()
+            .param(ValType::I32)
+            .result(ValType::I32)
+            .body(vec![
+                Block(ValType::I32),
+                Block(ValType::Nil),
+                Block(ValType::Nil),
+                LocalGet(0),
+                BrTable(BranchTable::new(vec![0, 1], 1)),
+                End,
+                I32Const(Value::I32(10)),
+                Br(1),
+                End,
+                I32Const(Value::I32(20)),
+                End,
+            ])
+    }
+
+    #[test]
+    pub fn br_table_selects_target() {
+        assert_eq!(
+            Ok(vec![Value::I32(10)]),
+            call(select_func(), vec![Value::I32(0)])
+        );
+        assert_eq!(
+            Ok(vec![Value::I32(20)]),
+            call(select_func(), vec![Value::I32(1)])
+        );
+    }
+
+    #[test]
+    pub fn br_table_uses_default_target_when_out_of_range() {
+        assert_eq!(
+            Ok(vec![Value::I32(20)]),
+            call(select_func(), vec![Value::I32(5)])
+        );
+    }
+
+    #[test]
+    pub fn br_unwinds_operand_stack() {
+        let func = FuncBuilder::new().result(ValType::I32).body(vec![
+            Block(ValType::I32),
+            I32Const(Value::I32(1)),
+            I32Const(Value::I32(2)),
+            I32Const(Value::I32(3)),
+            Br(0),
+            End,
+        ]);
+        assert_eq!(Ok(vec![Value::I32(3)]), call(func, vec![]));
+    }
+
+    #[test]
+    pub fn if_else_selects_branch() {
+        let func = |cond| {
+            FuncBuilder::new().result(ValType::I32).body(vec![
+                I32Const(Value::I32(cond)),
+                If(ValType::I32),
+                I32Const(Value::I32(1)),
+                Else,
+                I32Const(Value::I32(2)),
+                End,
+            ])
+        };
+        assert_eq!(Ok(vec![Value::I32(1)]), call(func(1), vec![]));
+        assert_eq!(Ok(vec![Value::I32(2)]), call(func(0), vec![]));
+    }
+
+    #[test]
+    pub fn if_without_else_skips_body() {
+        let func = FuncBuilder::new().result(ValType::I32).body(vec![
+            I32Const(Value::I32(0)),
+            If(ValType::Nil),
+            Unreachable,
+            End,
+            I32Const(Value::I32(42)),
+        ]);
+        assert_eq!(Ok(vec![Value::I32(42)]), call(func, vec![]));
+    }
+
+    #[test]
+    pub fn br_from_loop_exits_enclosing_block() {
+        let func = FuncBuilder::new()
+            .param(ValType::I32)
+            .result(ValType::I32)
+            .body(vec![
+                Block(ValType::I32),
+                Loop(ValType::I32),
+                LocalGet(0),
+                I32Const(Value::I32(1)),
+                I32Sub,
+                Br(1),
+                End,
+                End,
+            ]);
+        assert_eq!(Ok(vec![Value::I32(4)]), call(func, vec![Value::I32(5)]));
+    }
+
+    #[test]
+    pub fn return_exits_nested_blocks() {
+        let func = FuncBuilder::new().result(ValType::I32).body(vec![
+            Block(ValType::Nil),
+            Loop(ValType::Nil),
+            I32Const(Value::I32(7)),
+            I32Const(Value::I32(8)),
+            Return,
+            End,
+            End,
+            Unreachable,
+        ]);
+        assert_eq!(Ok(vec![Value::I32(8)]), call(func, vec![]));
+    }
+}
@@ -0,0 +1,274 @@
+use byteorder::{ByteOrder, LittleEndian};
+
+use crate::{hosting::Host, interp::Thread, FromValue, Instruction, Trap, TrapCause, Value};
+
+pub fn exec(thread: &mut Thread, host: &mut Host, inst: &Instruction) -> Result<(), Trap> {
+    use crate::Instruction::*;
+
+    match *inst {
+        I32Load(_, offset) => load::<u32, u32>(thread, host, offset),
+        I64Load(_, offset) => load::<u64, u64>(thread, host, offset),
+        F32Load(_, offset) => load::<f32, f32>(thread, host, offset),
+        F64Load(_, offset) => load::<f64, f64>(thread, host, offset),
+        I32Load8S(_, offset) => load::<i32, i8>(thread, host, offset),
+        I32Load8U(_, offset) => load::<u32, u8>(thread, host, offset),
+        I32Load16S(_, offset) => load::<i32, i16>(thread, host, offset),
+        I32Load16U(_, offset) => load::<u32, u16>(thread, host, offset),
+        I64Load8S(_, offset) => load::<i64, i8>(thread, host, offset),
+        I64Load8U(_, offset) => load::<u64, u8>(thread, host, offset),
+        I64Load16S(_, offset) => load::<i64, i16>(thread, host, offset),
+        I64Load16U(_, offset) => load::<u64, u16>(thread, host, offset),
+        I64Load32S(_, offset) => load::<i64, i32>(thread, host, offset),
+        I64Load32U(_, offset) => load::<u64, u32>(thread, host, offset),
+
+        I32Store(_, offset) => store::<u32, u32>(thread, host, offset),
+        I64Store(_, offset) => store::<u64, u64>(thread, host, offset),
+        F32Store(_, offset) => store::<f32, f32>(thread, host, offset),
+        F64Store(_, offset) => store::<f64, f64>(thread, host, offset),
+        I32Store8(_, offset) => store::<u32, u8>(thread, host, offset),
+        I32Store16(_, offset) => store::<u32, u16>(thread, host, offset),
+        I64Store8(_, offset) => store::<u64, u8>(thread, host, offset),
+        I64Store16(_, offset) => store::<u64, u16>(thread, host, offset),
+        I64Store32(_, offset) => store::<u64, u32>(thread, host, offset),
+
+        MemorySize(_) => size(thread, host),
+        MemoryGrow(_) => grow(thread, host),
+
+        _ => unreachable!("Not a memory instruction: {}", inst),
+    }
+}
+
+/// A value that can be read from, or written to, linear memory.
+pub(crate) trait MemoryValue: Sized {
+    const SIZE: usize;
+
+    fn read_le(buf: &[u8]) -> Self;
+    fn write_le(self, buf: &mut [u8]);
+}
+
+macro_rules! impl_memory_value {
+    ($t: ty, $size: expr, $read: ident, $write: ident) => {
+        impl MemoryValue for $t {
+            const SIZE: usize = $size;
+
+            fn read_le(buf: &[u8]) -> $t {
+                LittleEndian::$read(buf)
+            }
+
+            fn write_le(self, buf: &mut [u8]) {
+                LittleEndian::$write(buf, self)
+            }
+        }
+    };
+}
+
+impl MemoryValue for u8 {
+    const SIZE: usize = 1;
+
+    fn read_le(buf: &[u8]) -> u8 {
+        buf[0]
+    }
+
+    fn write_le(self, buf: &mut [u8]) {
+        buf[0] = self
+    }
+}
+
+impl MemoryValue for i8 {
+    const SIZE: usize = 1;
+
+    fn read_le(buf: &[u8]) -> i8 {
+        buf[0] as i8
+    }
+
+    fn write_le(self, buf: &mut [u8]) {
+        buf[0] = self as u8
+    }
+}
+
+impl_memory_value!(u16, 2, read_u16, write_u16);
+impl_memory_value!(i16, 2, read_i16, write_i16);
+impl_memory_value!(u32, 4, read_u32, write_u32);
+impl_memory_value!(i32, 4, read_i32, write_i32);
+impl_memory_value!(u64, 8, read_u64, write_u64);
+impl_memory_value!(i64, 8, read_i64, write_i64);
+impl_memory_value!(f32, 4, read_f32, write_f32);
+impl_memory_value!(f64, 8, read_f64, write_f64);
+
+/// Wraps a value into a (possibly) narrower type, discarding the high-order bits.
+pub(crate) trait WrapInto<T> {
+    fn wrap_into(self) -> T;
+}
+
+macro_rules! impl_wrap {
+    ($from: ty, $($to: ty),*) => {
+        $(
+            impl WrapInto<$to> for $from {
+                fn wrap_into(self) -> $to {
+                    self as $to
+                }
+            }
+        )*
+    };
+}
+
+impl_wrap!(u32, u8, u16, u32);
+impl_wrap!(u64, u8, u16, u32, u64);
+impl_wrap!(f32, f32);
+impl_wrap!(f64, f64);
+
+/// Computes the range of memory accessed by an instruction, trapping if it is out of bounds.
+pub(crate) fn effective_range(
+    addr: u32,
+    offset: u32,
+    size: usize,
+    len: usize,
+) -> Result<(usize, usize), TrapCause> {
+    // Compute in u64 so the effective address can't overflow
+    let start = addr as u64 + offset as u64;
+    let end = start + size as u64;
+    if end > len as u64 {
+        Err(TrapCause::OutOfBoundsMemoryAccess)
+    } else {
+        Ok((start as usize, end as usize))
+    }
+}
+
+fn load<T, M>(thread: &mut Thread, host: &mut Host, offset: u32) -> Result<(), Trap>
+where
+    M: MemoryValue,
+    T: From<M>,
+    Value: From<T>,
+{
+    let addr = thread.stack_mut().pop_as::<u32>()?;
+
+    let module = thread.stack().current().frame().module();
+    let mem_inst = host.get_mem(host.resolve_mem(module, 0));
+    let mem = mem_inst.memory();
+    let (start, end) = effective_range(addr, offset, M::SIZE, mem.len())?;
+    let val = M::read_le(&mem.bytes()[start..end]);
+    thread.stack_mut().push(T::from(val));
+    Ok(())
+}
+
+fn store<T, M>(thread: &mut Thread, host: &mut Host, offset: u32) -> Result<(), Trap>
+where
+    T: FromValue + WrapInto<M>,
+    M: MemoryValue,
+{
+    let (addr, val) = thread.stack_mut().pop_pair_as::<u32, T>()?;
+
+    let module = thread.stack().current().frame().module();
+    let mem_inst = host.get_mem(host.resolve_mem(module, 0));
+    let mut mem = mem_inst.memory_mut();
+    let (start, end) = effective_range(addr, offset, M::SIZE, mem.len())?;
+    val.wrap_into().write_le(&mut mem.bytes_mut()[start..end]);
+    Ok(())
+}
+
+fn size(thread: &mut Thread, host: &mut Host) -> Result<(), Trap> {
+    let module = thread.stack().current().frame().module();
+    let pages = host.get_mem(host.resolve_mem(module, 0)).memory().pages();
+    thread.stack_mut().push(pages as u32);
+    Ok(())
+}
+
+fn grow(thread: &mut Thread, host: &mut Host) -> Result<(), Trap> {
+    let delta = thread.stack_mut().pop_as::<u32>()?;
+
+    let module = thread.stack().current().frame().module();
+    let mem_inst = host.get_mem(host.resolve_mem(module, 0));
+    let result = match mem_inst.memory_mut().grow(delta as usize) {
+        Some(old_pages) => old_pages as i32,
+        None => -1,
+    };
+    thread.stack_mut().push(result);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        builder::FuncBuilder, interp::exec::tests::call_with_memory, Instruction::*, TrapCause,
+        ValType, Value,
+    };
+
+    #[test]
+    pub fn store_then_load_round_trips() {
+        let func = FuncBuilder::new().result(ValType::F64).body(vec![
+            I32Const(Value::I32(8)),
+            F64Const(Value::F64(1.5)),
+            F64Store(3, 0),
+            I32Const(Value::I32(0)),
+            F64Load(3, 8),
+        ]);
+        assert_eq!(Ok(vec![Value::F64(1.5)]), call_with_memory(func, vec![]));
+    }
+
+    #[test]
+    pub fn narrow_loads_extend_value() {
+        let func = |load| {
+            FuncBuilder::new().result(ValType::I64).body(vec![
+                I32Const(Value::I32(0)),
+                I32Const(Value::I32(0xFFFF_FF80)),
+                I32Store(2, 0),
+                I32Const(Value::I32(0)),
+                load,
+            ])
+        };
+        assert_eq!(
+            Ok(vec![Value::I64(0xFFFF_FFFF_FFFF_FF80)]),
+            call_with_memory(func(I64Load8S(0, 0)), vec![])
+        );
+        assert_eq!(
+            Ok(vec![Value::I64(0x80)]),
+            call_with_memory(func(I64Load8U(0, 0)), vec![])
+        );
+    }
+
+    #[test]
+    pub fn narrow_stores_wrap_value() {
+        let func = FuncBuilder::new().result(ValType::I32).body(vec![
+            I32Const(Value::I32(0)),
+            I64Const(Value::I64(0x1234_5678_9ABC)),
+            I64Store16(1, 0),
+            I32Const(Value::I32(0)),
+            I32Load(2, 0),
+        ]);
+        assert_eq!(Ok(vec![Value::I32(0x9ABC)]), call_with_memory(func, vec![]));
+    }
+
+    #[test]
+    pub fn memory_size_returns_pages() {
+        let func = FuncBuilder::new()
+            .result(ValType::I32)
+            .body(vec![MemorySize(0)]);
+        assert_eq!(Ok(vec![Value::I32(256)]), call_with_memory(func, vec![]));
+    }
+
+    #[test]
+    pub fn memory_grow_returns_previous_size_or_failure() {
+        // The 'env' memory is already at its maximum size
+        let func = |delta| {
+            FuncBuilder::new()
+                .result(ValType::I32)
+                .body(vec![I32Const(Value::I32(delta)), MemoryGrow(0)])
+        };
+        assert_eq!(Ok(vec![Value::I32(256)]), call_with_memory(func(0), vec![]));
+        assert_eq!(
+            Ok(vec![Value::I32(0xFFFF_FFFF)]),
+            call_with_memory(func(1), vec![])
+        );
+    }
+
+    #[test]
+    pub fn access_past_end_of_memory_traps() {
+        let func = FuncBuilder::new().result(ValType::I32).body(vec![
+            I32Const(Value::I32(0xFFFF_FFFF)),
+            I32Load(2, 0xFFFF_FFFF),
+        ]);
+        let trap = call_with_memory(func, vec![]).unwrap_err();
+        assert!(*trap.cause() == TrapCause::OutOfBoundsMemoryAccess);
+        assert_eq!("out of bounds memory access", trap.cause().message());
+    }
+}
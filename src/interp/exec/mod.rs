@@ -1,25 +1,90 @@
-use crate::{hosting::Host, interp::Thread, Instruction, Trap};
+use crate::{
+    hosting::Host,
+    interp::{Fused, FusedOp, Op, Thread},
+    Instruction, Trap, TrapCause, Value,
+};
 
+mod control;
+mod memops;
 mod numops;
 
-pub fn execute(thread: &mut Thread, host: &mut Host, inst: Instruction) -> Result<(), Trap> {
+pub(crate) use self::memops::{effective_range, MemoryValue, WrapInto};
+
+pub fn execute(thread: &mut Thread, host: &mut Host, op: &Op) -> Result<(), Trap> {
+    match *op {
+        Op::Inst(ref inst) => execute_inst(thread, host, inst)?,
+        Op::Call(func) => thread.enter_func(host, func)?,
+        Op::CallIndirect(type_idx) => {
+            let module_addr = thread.stack().current().frame().module();
+            let table = host.get_table(host.resolve_table(module_addr, 0));
+            let elem_idx = thread.stack_mut().pop_as::<u32>()?;
+            let func = match table.get(elem_idx as usize) {
+                Some(Some(func)) => func,
+                Some(None) => return Err(TrapCause::UninitializedElement.into()),
+                None => return Err(TrapCause::UndefinedElement.into()),
+            };
+
+            // Check the callee's signature against the expected type
+            let module_inst = host.get_module(module_addr);
+            match module_inst.types().get(type_idx as usize) {
+                Some(typ) if typ == host.get_func(func).typ() => {}
+                _ => return Err(TrapCause::IndirectCallTypeMismatch.into()),
+            }
+
+            thread.enter_func(host, func)?;
+        }
+        Op::Fused(ref fused) => execute_fused(thread, fused)?,
+        _ => control::exec(thread, op)?,
+    };
+
+    Ok(())
+}
+
+fn execute_fused(thread: &mut Thread, fused: &Fused) -> Result<(), Trap> {
+    // Skip the rest of the sequence, the program counter is already past the first instruction
+    let next = thread.stack().current().pc() + fused.count() - 1;
+    thread.stack_mut().current_mut().jump(next);
+
+    match *fused.op() {
+        FusedOp::LocalCopy { src, dst } => {
+            let val = match thread.stack().current().local(src as usize) {
+                Some(l) => l,
+                None => return Err(format!("No such local: {}", src).into()),
+            };
+            set_local(thread, dst as usize, val)?;
+        }
+        FusedOp::I32AddConst(value) => {
+            let val = thread.stack_mut().pop_as::<u32>()?;
+            thread.push(Value::I32(val.wrapping_add(value)));
+        }
+        FusedOp::BrIfEqz(depth) => {
+            if thread.stack_mut().pop_as::<u32>()? == 0 {
+                thread.stack_mut().current_mut().branch(depth as usize)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn execute_inst(thread: &mut Thread, host: &mut Host, inst: &Instruction) -> Result<(), Trap> {
     use crate::Instruction::*;
 
-    match inst {
+    match *inst {
+        Unreachable => return Err(TrapCause::Unreachable.into()),
+        Nop => {}
+        Drop => {
+            thread.pop()?;
+        }
+        Select => {
+            let cond = thread.stack_mut().pop_as::<u32>()?;
+            let val2 = thread.pop()?;
+            let val1 = thread.pop()?;
+            thread.push(if cond != 0 { val1 } else { val2 });
+        }
         I32Const(v) => thread.push(v),
         I64Const(v) => thread.push(v),
         F32Const(v) => thread.push(v),
         F64Const(v) => thread.push(v),
-        Call(func_idx) => {
-            let module_addr = thread.stack().current().frame().module();
-            let func = host.resolve_func(module_addr, func_idx as usize);
-            let values = thread.invoke(host, func)?;
-
-            // Push the result values on to the stack
-            for value in values {
-                thread.push(value)
-            }
-        }
         LocalGet(local_idx) => {
             let val = match thread.stack().current().local(local_idx as usize) {
                 Some(l) => l,
@@ -27,8 +92,594 @@ pub fn execute(thread: &mut Thread, host: &mut Host, inst: Instruction) -> Resul
             };
             thread.push(val);
         }
+        LocalSet(local_idx) => {
+            let val = thread.pop()?;
+            set_local(thread, local_idx as usize, val)?;
+        }
+        LocalTee(local_idx) => {
+            let val = thread.pop()?;
+            set_local(thread, local_idx as usize, val)?;
+            thread.push(val);
+        }
+        GlobalGet(global_idx) => {
+            let module_addr = thread.stack().current().frame().module();
+            let global = host.get_global(host.resolve_global(module_addr, global_idx as usize));
+            thread.push(global.value());
+        }
+        GlobalSet(global_idx) => {
+            let module_addr = thread.stack().current().frame().module();
+            let global = host.get_global(host.resolve_global(module_addr, global_idx as usize));
+            let val = thread.pop()?;
+            global.set(val)?;
+        }
+        I32Load(..) | I64Load(..) | F32Load(..) | F64Load(..) | I32Load8S(..) | I32Load8U(..)
+        | I32Load16S(..) | I32Load16U(..) | I64Load8S(..) | I64Load8U(..) | I64Load16S(..)
+        | I64Load16U(..) | I64Load32S(..) | I64Load32U(..) | I32Store(..) | I64Store(..)
+        | F32Store(..) | F64Store(..) | I32Store8(..) | I32Store16(..) | I64Store8(..)
+        | I64Store16(..) | I64Store32(..) | MemorySize(_) | MemoryGrow(_) => {
+            memops::exec(thread, host, inst)?
+        }
         _ => numops::exec(thread, inst)?,
     };
 
     Ok(())
 }
+
+fn set_local(thread: &mut Thread, local_idx: usize, val: Value) -> Result<(), Trap> {
+    let local = match thread.stack_mut().current_mut().local_mut(local_idx) {
+        Some(l) => l,
+        None => return Err(format!("No such local: {}", local_idx).into()),
+    };
+
+    // Locals are initialized with a value of their declared type, so it can be used to type-check
+    if local.typ() != val.typ() {
+        return Err(TrapCause::TypeMismatch {
+            expected: local.typ(),
+            actual: val.typ(),
+        }
+        .into());
+    }
+    *local = val;
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::{
+        builder::{FuncBuilder, ModuleBuilder},
+        hosting::{
+            ExternVal, ExternalFunc, ExternalGlobal, ExternalMemory, ExternalModule, ExternalTable,
+            FuncAddr, Host, ModuleAddr,
+        },
+        interp::Thread,
+        module::{
+            ElemItem, ElemType, Expr, FuncType, Global, GlobalType, Import, MemberDesc, MemoryType,
+            Module, TableType, ValidationErrorKind,
+        },
+        runtime, Error, Instruction, Trap, TrapCause, ValType, Value,
+    };
+
+    /// Creates a host with the 'env' and 'spectest' modules available for import.
+    pub fn host() -> Host {
+        let mut host = Host::new();
+        host.external(runtime::Env::new()).unwrap();
+        host.external(runtime::SpecTest::new()).unwrap();
+        host
+    }
+
+    /// Parses a module from the text format and instantiates it.
+    pub fn link(text: &str) -> Result<(), Error> {
+        host()
+            .instantiate("test", crate::text::parse_module(text).unwrap())
+            .map(|_| ())
+    }
+
+    /// Instantiates the provided module and calls the export with the specified name.
+    pub fn invoke(module: Module, name: &str, args: Vec<Value>) -> Result<Vec<Value>, Trap> {
+        let mut host = host();
+        let instance = host.instantiate("test", module).unwrap();
+        let func = match instance.get_func(&host, name) {
+            Some(func) => func,
+            None => panic!("'{}' is not a function!", name),
+        };
+        func.call(&mut host, &args)
+    }
+
+    /// Builds a module containing only the provided function, exported as 'test', and calls it.
+    pub fn call(func: FuncBuilder, args: Vec<Value>) -> Result<Vec<Value>, Trap> {
+        let module = ModuleBuilder::new().func(func.export_as("test")).build();
+        invoke(module, "test", args)
+    }
+
+    /// Like [`call`], but the module also imports the 'env' module's memory.
+    pub fn call_with_memory(func: FuncBuilder, args: Vec<Value>) -> Result<Vec<Value>, Trap> {
+        let mut builder = ModuleBuilder::new();
+        builder.imports.push(Import::new(
+            "env",
+            "memory",
+            MemberDesc::Memory(MemoryType::new(1, None)),
+        ));
+        let module = builder.func(func.export_as("test")).build();
+        invoke(module, "test", args)
+    }
+
+    /// Asserts that instantiating the provided module fails validation with the specified error.
+    pub fn assert_invalid(module: Module, expected: ValidationErrorKind) {
+        match host().instantiate("test", module) {
+            Err(Error::Validation(e)) => assert_eq!(expected, *e.kind()),
+            Err(e) => panic!("Unexpected error: {:?}", e),
+            Ok(_) => panic!("Expected validation to fail"),
+        }
+    }
+
+    pub fn global(typ: ValType, mutable: bool, init: Instruction) -> Global {
+        Global::new(GlobalType::new(typ, mutable), Expr::new(vec![init]))
+    }
+
+    #[test]
+    pub fn global_set_on_immutable_global_is_invalid() {
+        use crate::Instruction::*;
+
+        let module = ModuleBuilder::new()
+            .global(global(ValType::I32, false, I32Const(Value::I32(5))))
+            .func(
+                FuncBuilder::new()
+                    .export_as("test")
+                    .body(vec![I32Const(Value::I32(1)), GlobalSet(0)]),
+            )
+            .build();
+        assert_invalid(module, ValidationErrorKind::ImmutableGlobal);
+    }
+
+    /// Builds a module with a 3-element table holding `() -> i32` and `(i32) -> i32` functions
+    /// in the first two slots, and an exported 'test' function that calls `() -> i32` indirectly.
+    fn indirect_module() -> Module {
+        use crate::Instruction::*;
+
+        ModuleBuilder::new()
+            .table(TableType::new(ElemType::AnyFunc, 3, None))
+            .elem(ElemItem::new(
+                0,
+                Expr::new(vec![I32Const(Value::I32(0))]),
+                vec![0, 1],
+            ))
+            .func(
+                FuncBuilder::new()
+                    .result(ValType::I32)
+                    .body(vec![I32Const(Value::I32(42))]),
+            )
+            .func(
+                FuncBuilder::new()
+                    .param(ValType::I32)
+                    .result(ValType::I32)
+                    .body(vec![LocalGet(0)]),
+            )
+            .func(
+                FuncBuilder::new()
+                    .param(ValType::I32)
+                    .result(ValType::I32)
+                    .export_as("test")
+                    .body(vec![LocalGet(0), CallIndirect(0, 0)]),
+            )
+            .build()
+    }
+
+    #[test]
+    pub fn call_indirect_dispatches_through_table() {
+        assert_eq!(
+            Ok(vec![Value::I32(42)]),
+            invoke(indirect_module(), "test", vec![Value::I32(0)])
+        );
+    }
+
+    #[test]
+    pub fn call_indirect_traps_on_signature_mismatch() {
+        let trap = invoke(indirect_module(), "test", vec![Value::I32(1)]).unwrap_err();
+        assert!(*trap.cause() == TrapCause::IndirectCallTypeMismatch);
+    }
+
+    #[test]
+    pub fn call_indirect_traps_on_uninitialized_element() {
+        let trap = invoke(indirect_module(), "test", vec![Value::I32(2)]).unwrap_err();
+        assert!(*trap.cause() == TrapCause::UninitializedElement);
+    }
+
+    #[test]
+    pub fn call_indirect_traps_on_undefined_element() {
+        let trap = invoke(indirect_module(), "test", vec![Value::I32(3)]).unwrap_err();
+        assert!(*trap.cause() == TrapCause::UndefinedElement);
+    }
+
+    #[test]
+    pub fn out_of_bounds_elem_segment_fails_to_instantiate() {
+        let module = ModuleBuilder::new()
+            .table(TableType::new(ElemType::AnyFunc, 1, None))
+            .elem(ElemItem::new(
+                0,
+                Expr::new(vec![Instruction::I32Const(Value::I32(1))]),
+                vec![0],
+            ))
+            .func(FuncBuilder::new())
+            .build();
+        assert!(host().instantiate("test", module).is_err());
+    }
+
+    #[test]
+    pub fn local_set_updates_local() {
+        use crate::Instruction::*;
+
+        let func = FuncBuilder::new()
+            .param(ValType::I32)
+            .result(ValType::I32)
+            .body(vec![I32Const(Value::I32(7)), LocalSet(0), LocalGet(0)]);
+        assert_eq!(Ok(vec![Value::I32(7)]), call(func, vec![Value::I32(1)]));
+    }
+
+    #[test]
+    pub fn local_tee_updates_local_and_keeps_value() {
+        use crate::Instruction::*;
+
+        let func = FuncBuilder::new()
+            .result(ValType::I64)
+            .locals(vec![ValType::I64])
+            .body(vec![
+                I64Const(Value::I64(3)),
+                LocalTee(0),
+                LocalGet(0),
+                I64Add,
+            ]);
+        assert_eq!(Ok(vec![Value::I64(6)]), call(func, vec![]));
+    }
+
+    #[test]
+    pub fn local_set_with_wrong_type_is_invalid() {
+        use crate::Instruction::*;
+
+        let module = ModuleBuilder::new()
+            .func(
+                FuncBuilder::new()
+                    .locals(vec![ValType::I32])
+                    .body(vec![F32Const(Value::F32(1.0)), LocalSet(0)]),
+            )
+            .build();
+        assert_invalid(module, ValidationErrorKind::TypeMismatch);
+    }
+
+    #[test]
+    pub fn loop_counts_down_with_local() {
+        use crate::Instruction::*;
+
+        // Sums 1..=n by counting the parameter down to zero
+        let func = FuncBuilder::new()
+            .param(ValType::I32)
+            .result(ValType::I32)
+            .locals(vec![ValType::I32])
+            .body(vec![
+                Block(ValType::Nil),
+                Loop(ValType::Nil),
+                LocalGet(0),
+                I32Eqz,
+                BrIf(1),
+                LocalGet(1),
+                LocalGet(0),
+                I32Add,
+                LocalSet(1),
+                LocalGet(0),
+                I32Const(Value::I32(1)),
+                I32Sub,
+                LocalSet(0),
+                Br(0),
+                End,
+                End,
+                LocalGet(1),
+            ]);
+        assert_eq!(Ok(vec![Value::I32(15)]), call(func, vec![Value::I32(5)]));
+    }
+
+    #[test]
+    pub fn unreachable_traps() {
+        let func = FuncBuilder::new().body(vec![Instruction::Unreachable]);
+        let trap = call(func, vec![]).unwrap_err();
+        assert!(*trap.cause() == TrapCause::Unreachable);
+        assert_eq!("unreachable", trap.to_string());
+    }
+
+    #[test]
+    pub fn nop_and_drop() {
+        use crate::Instruction::*;
+
+        let func = FuncBuilder::new().result(ValType::I32).body(vec![
+            I32Const(Value::I32(1)),
+            Nop,
+            I32Const(Value::I32(2)),
+            Drop,
+        ]);
+        assert_eq!(Ok(vec![Value::I32(1)]), call(func, vec![]));
+    }
+
+    #[test]
+    pub fn select_picks_operand_by_condition() {
+        use crate::Instruction::*;
+
+        let select = |cond| {
+            let func = FuncBuilder::new().result(ValType::I64).body(vec![
+                I64Const(Value::I64(10)),
+                I64Const(Value::I64(20)),
+                I32Const(Value::I32(cond)),
+                Select,
+            ]);
+            call(func, vec![])
+        };
+        assert_eq!(Ok(vec![Value::I64(10)]), select(1));
+        assert_eq!(Ok(vec![Value::I64(20)]), select(0));
+    }
+
+    #[test]
+    pub fn start_function_runs_on_instantiation() {
+        use crate::Instruction::*;
+
+        let module = ModuleBuilder::new()
+            .global(global(ValType::I32, true, I32Const(Value::I32(0))))
+            .func(FuncBuilder::new().body(vec![I32Const(Value::I32(42)), GlobalSet(0)]))
+            .func(
+                FuncBuilder::new()
+                    .result(ValType::I32)
+                    .export_as("test")
+                    .body(vec![GlobalGet(0)]),
+            )
+            .start(0)
+            .build();
+        assert_eq!(Ok(vec![Value::I32(42)]), invoke(module, "test", vec![]));
+    }
+
+    #[test]
+    pub fn start_function_trap_rolls_back_instance() {
+        let module = ModuleBuilder::new()
+            .global(global(
+                ValType::I32,
+                true,
+                Instruction::I32Const(Value::I32(0)),
+            ))
+            .func(FuncBuilder::new().body(vec![Instruction::Unreachable]))
+            .start(0)
+            .build();
+
+        let mut host = host();
+        let modules = host.modules().count();
+        let funcs = host.funcs().count();
+        let globals = host.globals().count();
+        match host.instantiate("test", module) {
+            Err(Error::Trap(trap)) => assert!(*trap.cause() == TrapCause::Unreachable),
+            Err(e) => panic!("Unexpected error: {:?}", e),
+            Ok(_) => panic!("Expected instantiation to fail"),
+        }
+        assert_eq!(modules, host.modules().count());
+        assert_eq!(funcs, host.funcs().count());
+        assert_eq!(globals, host.globals().count());
+        assert!(host.find_module("test").is_none());
+    }
+
+    #[test]
+    pub fn start_function_trap_restores_imported_table_and_memory() {
+        let mut host = host();
+        let lib = host
+            .instantiate(
+                "lib",
+                crate::text::parse_module(
+                    r#"(module
+                        (type (func (result i32)))
+                        (table (export "tab") 2 funcref)
+                        (memory (export "mem") 1)
+                        (func $one (result i32) (i32.const 1))
+                        (elem (i32.const 0) $one $one)
+                        (data (i32.const 0) "ab")
+                        (func (export "call") (param i32) (result i32)
+                            (call_indirect (type 0) (local.get 0))))"#,
+                )
+                .unwrap(),
+            )
+            .unwrap();
+        host.register("lib", lib.addr());
+
+        let module = crate::text::parse_module(
+            r#"(module
+                (import "lib" "tab" (table 2 funcref))
+                (import "lib" "mem" (memory 1))
+                (func $two (result i32) (i32.const 2))
+                (func $start unreachable)
+                (elem (i32.const 1) $two)
+                (data (i32.const 1) "z")
+                (start $start))"#,
+        )
+        .unwrap();
+        match host.instantiate("test", module) {
+            Err(Error::Trap(trap)) => assert!(*trap.cause() == TrapCause::Unreachable),
+            Err(e) => panic!("Unexpected error: {:?}", e),
+            Ok(_) => panic!("Expected instantiation to fail"),
+        }
+
+        let call = lib.get_func(&host, "call").unwrap();
+        assert_eq!(
+            vec![Value::I32(1)],
+            call.call(&mut host, &[Value::I32(1)]).ok().unwrap()
+        );
+        let mem = lib.get_memory(&host, "mem").unwrap();
+        assert_eq!(b"ab", mem.view().read(0, 2).unwrap());
+    }
+
+    #[test]
+    pub fn failed_instantiation_leaves_imported_table_untouched() {
+        let mut host = host();
+        let table = host
+            .instantiate(
+                "Mt",
+                crate::text::parse_module(
+                    r#"(module
+                        (type (func (result i32)))
+                        (table (export "tab") 10 funcref)
+                        (func (export "call") (param i32) (result i32)
+                            (call_indirect (type 0) (local.get 0))))"#,
+                )
+                .unwrap(),
+            )
+            .unwrap();
+        host.register("Mt", table.addr());
+
+        let funcs = host.funcs().count();
+        let module = crate::text::parse_module(
+            r#"(module
+                (import "Mt" "tab" (table 10 funcref))
+                (memory 1)
+                (func $f (result i32) (i32.const 0))
+                (elem (i32.const 7) $f)
+                (data (i32.const 0x10000) "d"))"#,
+        )
+        .unwrap();
+        match host.instantiate("test", module) {
+            Err(Error::DataSegmentOutOfBounds { index: 0 }) => {}
+            Err(e) => panic!("Unexpected error: {:?}", e),
+            Ok(_) => panic!("Expected instantiation to fail"),
+        }
+        assert_eq!(funcs, host.funcs().count());
+
+        let call = table.get_func(&host, "call").unwrap();
+        match call.call(&mut host, &[Value::I32(7)]) {
+            Err(trap) => assert!(*trap.cause() == TrapCause::UninitializedElement),
+            Ok(_) => panic!("Expected the table element to be uninitialized"),
+        }
+    }
+
+    #[test]
+    pub fn registered_names_resolve_imports() {
+        let lib = ModuleBuilder::new()
+            .func(
+                FuncBuilder::new()
+                    .result(ValType::I32)
+                    .export_as("answer")
+                    .body(vec![Instruction::I32Const(Value::I32(42))]),
+            )
+            .build();
+        let app = || {
+            ModuleBuilder::new()
+                .func(
+                    FuncBuilder::new()
+                        .result(ValType::I32)
+                        .import_from("lib", "answer"),
+                )
+                .func(
+                    FuncBuilder::new()
+                        .result(ValType::I32)
+                        .export_as("test")
+                        .body(vec![Instruction::Call(0)]),
+                )
+                .build()
+        };
+
+        let mut host = host();
+        let lib_addr = host.instantiate("lib_v1", lib).unwrap().addr();
+        match host.instantiate("app", app()) {
+            Err(Error::ModuleNotFound { module }) => assert_eq!("lib", module),
+            Err(e) => panic!("Unexpected error: {:?}", e),
+            Ok(_) => panic!("Expected instantiation to fail"),
+        }
+
+        host.register("lib", lib_addr);
+        assert!(host.find_module("lib") == Some(lib_addr));
+        let app_addr = host.instantiate("app", app()).unwrap().addr();
+        let func_addr = match host.resolve_import(app_addr, "test").unwrap().value() {
+            ExternVal::Func(f) => *f,
+            _ => panic!("'test' is not a function!"),
+        };
+        assert_eq!(
+            Ok(vec![Value::I32(42)]),
+            Thread::new().call(&mut host, app_addr, func_addr, vec![])
+        );
+    }
+
+    /// An external module which only contains functions.
+    pub struct Funcs {
+        pub name: &'static str,
+        pub funcs: Vec<std::sync::Arc<ExternalFunc>>,
+    }
+
+    impl ExternalModule for Funcs {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn funcs(&self) -> &[std::sync::Arc<ExternalFunc>] {
+            &self.funcs
+        }
+
+        fn tables(&self) -> &[ExternalTable] {
+            &[]
+        }
+
+        fn mems(&self) -> &[ExternalMemory] {
+            &[]
+        }
+
+        fn globals(&self) -> &[ExternalGlobal] {
+            &[]
+        }
+    }
+
+    /// A function for the 'metered' module, which consumes 10 fuel.
+    fn work(_host: &mut Host, thread: &mut Thread, _values: &[Value]) -> Result<Vec<Value>, Trap> {
+        thread.consume_fuel(10)?;
+        Ok(vec![])
+    }
+
+    /// Instantiates a module from the text format, returning the host and the export named 'test'.
+    pub fn instantiate_text(text: &str) -> (Host, ModuleAddr, FuncAddr) {
+        let mut host = host();
+        host.external(Funcs {
+            name: "metered",
+            funcs: vec![std::sync::Arc::new(ExternalFunc::new(
+                "work",
+                FuncType::empty(),
+                work,
+            ))],
+        })
+        .unwrap();
+        let module_addr = host
+            .instantiate("test", crate::text::parse_module(text).unwrap())
+            .unwrap()
+            .addr();
+        let func_addr = match host.resolve_import(module_addr, "test").unwrap().value() {
+            ExternVal::Func(f) => *f,
+            _ => panic!("'test' is not a function!"),
+        };
+        (host, module_addr, func_addr)
+    }
+
+    #[test]
+    pub fn fused_sequences_run_with_and_without_fuel() {
+        let (mut host, module_addr, func_addr) = instantiate_text(
+            r#"
+            (func (export "test") (param i32) (result i32) (local i32 i32)
+                (block
+                    (loop
+                        (br_if 1 (i32.eqz (local.get 0)))
+                        (local.set 1 (i32.add (local.get 1) (local.get 0)))
+                        (local.set 0 (i32.add (local.get 0) (i32.const -1)))
+                        (br 0)))
+                (local.set 2 (local.get 1))
+                (local.get 2))"#,
+        );
+        let mut thread = Thread::new();
+        assert_eq!(
+            Ok(vec![Value::I32(55)]),
+            thread.call(&mut host, module_addr, func_addr, vec![Value::I32(10)])
+        );
+
+        // Metered threads run each instruction separately: 2 to enter the block and loop, 13 for
+        // each of the 10 iterations (including re-entering the loop), 3 to exit and 3 after it
+        thread.set_fuel(1000);
+        assert_eq!(
+            Ok(vec![Value::I32(55)]),
+            thread.call(&mut host, module_addr, func_addr, vec![Value::I32(10)])
+        );
+        assert_eq!(Some(1000 - 138), thread.fuel());
+    }
+}
@@ -2,10 +2,10 @@ use std::{cmp, ops};
 
 use crate::{interp::Thread, value, FromValue, Instruction, Trap, Value};
 
-pub fn exec(thread: &mut Thread, inst: Instruction) -> Result<(), Trap> {
+pub fn exec(thread: &mut Thread, inst: &Instruction) -> Result<(), Trap> {
     use crate::Instruction::*;
 
-    match inst {
+    match *inst {
         I32Eqz => eqz::<u32>(thread),
         I32Eq => eq::<u32>(thread),
         I32Ne => ne::<u32>(thread),
@@ -138,7 +138,7 @@ pub fn exec(thread: &mut Thread, inst: Instruction) -> Result<(), Trap> {
         F32ReinterpretI32 => reinterpret::<f32, u32>(thread),
         F64ReinterpretI64 => reinterpret::<f64, u64>(thread),
 
-        x => return Err(format!("Instruction not implemented: {}", x).into()),
+        _ => return Err(format!("Instruction not implemented: {}", inst).into()),
     }
 }
 
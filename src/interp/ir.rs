@@ -0,0 +1,275 @@
+use crate::{hosting::FuncAddr, BranchTable, Error, Instruction, ValType, Value};
+
+/// An operation in the interpreter's internal representation of code.
+///
+/// Code is lowered from [`Instruction`]s when a module is instantiated, with one [`Op`] per
+/// instruction so that offsets (and fuel costs) still line up with the original code. Control
+/// instructions have the offsets they jump to precomputed, calls have their function address
+/// resolved, and common sequences of instructions are fused into a single [`Op`].
+#[derive(Clone, PartialEq, Debug)]
+pub enum Op {
+    /// An instruction that doesn't affect control flow, which is executed as is.
+    Inst(Instruction),
+    /// Enters a block, whose label continues at `continuation` (just past the matching 'end').
+    Block {
+        arity: usize,
+        continuation: usize,
+    },
+    /// Enters a loop, whose label continues at `start` (the 'loop' itself).
+    Loop {
+        start: usize,
+    },
+    /// Enters the 'then' block if the condition is non-zero, otherwise continues at `else_start`
+    /// (just past the matching 'else') if there is one, or at `continuation` if not.
+    If {
+        arity: usize,
+        else_start: Option<usize>,
+        continuation: usize,
+    },
+    Else,
+    End,
+    Br(u32),
+    BrIf(u32),
+    BrTable(BranchTable),
+    Return,
+    Call(FuncAddr),
+    /// Calls a function from table 0, which must have the type with the specified index.
+    CallIndirect(u32),
+    Fused(Box<Fused>),
+}
+
+/// A sequence of instructions fused into a single operation.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Fused {
+    op: FusedOp,
+    len: usize,
+    first: Op,
+}
+
+impl Fused {
+    /// Gets the operation performed by the whole sequence.
+    pub fn op(&self) -> &FusedOp {
+        &self.op
+    }
+
+    /// Gets the number of instructions in the sequence.
+    pub fn count(&self) -> usize {
+        self.len
+    }
+
+    /// Gets the operation for the first instruction in the sequence alone.
+    ///
+    /// The remaining instructions keep their own operations, so the sequence can also be run one
+    /// instruction at a time (which is done when the thread meters fuel).
+    pub fn first(&self) -> &Op {
+        &self.first
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum FusedOp {
+    /// `local.get src`, `local.set dst`
+    LocalCopy { src: u32, dst: u32 },
+    /// `i32.const c`, `i32.add`
+    I32AddConst(u32),
+    /// `i32.eqz`, `br_if depth`
+    BrIfEqz(u32),
+}
+
+/// Lowers a sequence of instructions into [`Op`]s.
+///
+/// `funcs` is the function index space of the module the code belongs to, used to resolve calls.
+pub fn compile(code: &[Instruction], funcs: &[FuncAddr]) -> Result<Vec<Op>, Error> {
+    let ends = match_blocks(code)?;
+
+    let mut ops = Vec::with_capacity(code.len());
+    for (offset, inst) in code.iter().enumerate() {
+        let op = match *inst {
+            Instruction::Block(typ) => Op::Block {
+                arity: arity(typ),
+                continuation: ends[offset].1 + 1,
+            },
+            Instruction::Loop(_) => Op::Loop { start: offset },
+            Instruction::If(typ) => Op::If {
+                arity: arity(typ),
+                else_start: ends[offset].0.map(|e| e + 1),
+                continuation: ends[offset].1 + 1,
+            },
+            Instruction::Else => Op::Else,
+            Instruction::End => Op::End,
+            Instruction::Br(depth) => Op::Br(depth),
+            Instruction::BrIf(depth) => Op::BrIf(depth),
+            Instruction::BrTable(ref table) => Op::BrTable(table.clone()),
+            Instruction::Return => Op::Return,
+            Instruction::Call(func_idx) => match funcs.get(func_idx as usize) {
+                Some(func) => Op::Call(*func),
+                None => return Err(Error::InvalidModule),
+            },
+            Instruction::CallIndirect(type_idx, _) => Op::CallIndirect(type_idx),
+            ref inst => Op::Inst(inst.clone()),
+        };
+        ops.push(op);
+    }
+
+    fuse(code, &mut ops);
+    Ok(ops)
+}
+
+fn arity(typ: ValType) -> usize {
+    match typ {
+        ValType::Nil => 0,
+        _ => 1,
+    }
+}
+
+/// Finds the 'else' (if any) and 'end' matching each block, indexed by the offset of the block.
+fn match_blocks(code: &[Instruction]) -> Result<Vec<(Option<usize>, usize)>, Error> {
+    let mut ends = vec![(None, 0); code.len()];
+    let mut open = Vec::new();
+    for (offset, inst) in code.iter().enumerate() {
+        if inst.is_block() {
+            open.push(offset);
+        } else if *inst == Instruction::Else {
+            match open.last() {
+                Some(&start) => ends[start].0 = Some(offset),
+                None => return Err(Error::InvalidModule),
+            }
+        } else if *inst == Instruction::End {
+            match open.pop() {
+                Some(start) => ends[start].1 = offset,
+                None => return Err(Error::InvalidModule),
+            }
+        }
+    }
+
+    if open.is_empty() {
+        Ok(ends)
+    } else {
+        Err(Error::InvalidModule)
+    }
+}
+
+/// Replaces the first [`Op`] of each common sequence of instructions with a [`Fused`] op.
+///
+/// Only the last instruction of a sequence may be a control instruction. Every branch target is
+/// either a 'loop' or immediately follows a control instruction, so no branch can land in the
+/// middle of a sequence.
+fn fuse(code: &[Instruction], ops: &mut [Op]) {
+    use crate::Instruction::*;
+
+    let mut offset = 0;
+    while offset < code.len() {
+        let fused = match code[offset..] {
+            [LocalGet(src), LocalSet(dst), ..] => Some((FusedOp::LocalCopy { src, dst }, 2)),
+            [I32Const(Value::I32(value)), I32Add, ..] => Some((FusedOp::I32AddConst(value), 2)),
+            [I32Eqz, BrIf(depth), ..] => Some((FusedOp::BrIfEqz(depth), 2)),
+            _ => None,
+        };
+
+        match fused {
+            Some((op, len)) => {
+                let first = ops[offset].clone();
+                ops[offset] = Op::Fused(Box::new(Fused { op, len, first }));
+                offset += len;
+            }
+            None => offset += 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn func(val: usize) -> FuncAddr {
+        FuncAddr::new(val).unwrap()
+    }
+
+    #[test]
+    pub fn resolves_block_targets() {
+        use crate::Instruction::*;
+
+        let code = vec![
+            Block(ValType::I32),
+            I32Const(Value::I32(1)),
+            If(ValType::I32),
+            Loop(ValType::Nil),
+            End,
+            I32Const(Value::I32(2)),
+            Else,
+            I32Const(Value::I32(3)),
+            End,
+            End,
+        ];
+        let ops = compile(&code, &[]).unwrap();
+        assert_eq!(
+            Op::Block {
+                arity: 1,
+                continuation: 10
+            },
+            ops[0]
+        );
+        assert_eq!(
+            Op::If {
+                arity: 1,
+                else_start: Some(7),
+                continuation: 9
+            },
+            ops[2]
+        );
+        assert_eq!(Op::Loop { start: 3 }, ops[3]);
+    }
+
+    #[test]
+    pub fn resolves_call_targets() {
+        let code = vec![Instruction::Call(1), Instruction::Call(0)];
+        let ops = compile(&code, &[func(4), func(7)]).unwrap();
+        assert_eq!(vec![Op::Call(func(7)), Op::Call(func(4))], ops);
+        assert!(compile(&code, &[func(4)]).is_err());
+    }
+
+    #[test]
+    pub fn fuses_common_sequences() {
+        use crate::Instruction::*;
+
+        let code = vec![
+            LocalGet(0),
+            LocalSet(1),
+            I32Const(Value::I32(5)),
+            I32Add,
+            Loop(ValType::Nil),
+            I32Eqz,
+            BrIf(0),
+            End,
+        ];
+        let ops = compile(&code, &[]).unwrap();
+        let fused: Vec<_> = ops
+            .iter()
+            .filter_map(|op| match op {
+                Op::Fused(fused) => Some((fused.op().clone(), fused.first().clone())),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                (FusedOp::LocalCopy { src: 0, dst: 1 }, Op::Inst(LocalGet(0))),
+                (FusedOp::I32AddConst(5), Op::Inst(I32Const(Value::I32(5)))),
+                (FusedOp::BrIfEqz(0), Op::Inst(I32Eqz)),
+            ],
+            fused
+        );
+
+        // The remaining instructions of each sequence are still there
+        assert_eq!(Op::Inst(LocalSet(1)), ops[1]);
+        assert_eq!(Op::BrIf(0), ops[6]);
+    }
+
+    #[test]
+    pub fn rejects_unbalanced_blocks() {
+        use crate::Instruction::*;
+
+        assert!(compile(&[Block(ValType::Nil)], &[]).is_err());
+        assert!(compile(&[End], &[]).is_err());
+        assert!(compile(&[Else], &[]).is_err());
+    }
+}
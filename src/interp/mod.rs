@@ -1,6 +1,13 @@
-mod exec;
+pub(crate) mod exec;
+mod ir;
+mod slots;
 mod stack;
 mod thread;
 
-pub use self::stack::{ExecutionContext, ExecutionStack, StackFrame, StackTrace};
-pub use self::thread::Thread;
+pub use self::ir::{compile, Fused, FusedOp, Op};
+pub use self::slots::Engine;
+pub(crate) use self::slots::{SlotCode, SlotStack};
+pub use self::stack::{
+    Code, ExecutionContext, ExecutionStack, Label, StackFrame, StackTrace, DEFAULT_MAX_DEPTH,
+};
+pub use self::thread::{default_fuel_cost, Thread};
@@ -0,0 +1,1237 @@
+use std::{cmp, ops, sync::Arc};
+
+use crate::{
+    hosting::{FuncAddr, FuncImpl, FuncInst, Host, ModuleAddr},
+    interp::{
+        exec::{effective_range, MemoryValue, WrapInto},
+        Op, StackFrame, StackTrace, Thread,
+    },
+    value, Error, Instruction, Trap, TrapCause, ValType, Value,
+};
+
+/// The engine a [`Thread`] uses to run WebAssembly functions.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Engine {
+    /// Operands are [`Value`]s on the operand stack of each
+    /// [`ExecutionContext`](crate::interp::ExecutionContext), and their types are checked as
+    /// they are popped. This is the default.
+    Stack,
+    /// Operands are untyped 64-bit slots on a single stack shared by all frames.
+    ///
+    /// Each function is compiled the first time it runs into operations that address the slots
+    /// of its frame directly. The module was validated when it was instantiated, so the types of
+    /// operands are known and are not checked again. An invocation that runs out of fuel on this
+    /// engine traps, and can't be resumed.
+    Slots,
+}
+
+/// A value that can be stored in an untyped slot.
+trait Slot: Sized {
+    fn from_slot(slot: u64) -> Self;
+    fn into_slot(self) -> u64;
+}
+
+macro_rules! impl_int_slot {
+    ($t: ty, $bits: ty) => {
+        impl Slot for $t {
+            fn from_slot(slot: u64) -> $t {
+                slot as $bits as $t
+            }
+
+            fn into_slot(self) -> u64 {
+                self as $bits as u64
+            }
+        }
+    };
+}
+
+impl_int_slot!(u32, u32);
+impl_int_slot!(i32, u32);
+impl_int_slot!(u64, u64);
+impl_int_slot!(i64, u64);
+
+impl Slot for f32 {
+    fn from_slot(slot: u64) -> f32 {
+        f32::from_bits(slot as u32)
+    }
+
+    fn into_slot(self) -> u64 {
+        self.to_bits() as u64
+    }
+}
+
+impl Slot for f64 {
+    fn from_slot(slot: u64) -> f64 {
+        f64::from_bits(slot)
+    }
+
+    fn into_slot(self) -> u64 {
+        self.to_bits()
+    }
+}
+
+impl Slot for bool {
+    fn from_slot(slot: u64) -> bool {
+        slot != 0
+    }
+
+    fn into_slot(self) -> u64 {
+        self as u64
+    }
+}
+
+fn value_to_slot(value: Value) -> u64 {
+    match value {
+        Value::Nil => 0,
+        Value::I32(x) => x.into_slot(),
+        Value::I64(x) => x.into_slot(),
+        Value::F32(x) => x.into_slot(),
+        Value::F64(x) => x.into_slot(),
+    }
+}
+
+fn slot_to_value(slot: u64, typ: ValType) -> Value {
+    match typ {
+        ValType::Nil => Value::Nil,
+        ValType::I32 => Value::I32(u32::from_slot(slot)),
+        ValType::I64 => Value::I64(u64::from_slot(slot)),
+        ValType::F32 => Value::F32(f32::from_slot(slot)),
+        ValType::F64 => Value::F64(f64::from_slot(slot)),
+    }
+}
+
+/// Where a branch continues, and the values it keeps.
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct Target {
+    pc: usize,
+    /// The height of the label's operand stack, as an offset from the start of the frame.
+    height: usize,
+    arity: usize,
+}
+
+type UnaryFn = fn(u64) -> Result<u64, TrapCause>;
+type BinaryFn = fn(u64, u64) -> Result<u64, TrapCause>;
+
+/// An operation of code compiled for the slot engine.
+///
+/// Like [`Op`], there is one operation per instruction. Blocks have no runtime representation,
+/// branches carry the stack height of their label instead.
+enum SlotOp {
+    Nop,
+    Unreachable,
+    /// Continues at the specified offset.
+    Jump(usize),
+    /// Pops a condition, and continues at the specified offset if it is zero.
+    JumpIfZero(usize),
+    Br(Target),
+    BrIf(Target),
+    /// Branches to the target selected by the operand, the last target is the default.
+    BrTable(Box<[Target]>),
+    Call(FuncAddr),
+    CallIndirect(u32),
+    Drop,
+    Select,
+    Const(u64),
+    LocalGet(usize),
+    LocalSet(usize),
+    LocalTee(usize),
+    GlobalGet(u32),
+    GlobalSet(u32),
+    Load {
+        offset: u32,
+        size: usize,
+        read: fn(&[u8]) -> u64,
+    },
+    Store {
+        offset: u32,
+        size: usize,
+        write: fn(u64, &mut [u8]),
+    },
+    MemorySize,
+    MemoryGrow,
+    Unary(UnaryFn),
+    Binary(BinaryFn),
+}
+
+/// A function body compiled for the slot engine.
+pub struct SlotCode {
+    ops: Vec<SlotOp>,
+    /// The number of locals declared by the body, which follow the parameters in the frame.
+    locals: usize,
+}
+
+/// A block being compiled.
+struct CtrlFrame {
+    label: Target,
+    /// The number of values the block leaves on the stack when it ends.
+    results: usize,
+    /// Whether the whole block is unreachable, because it is nested in unreachable code.
+    dead: bool,
+    /// Whether the rest of the block is unreachable, its stack height is unknown in that case.
+    unreachable: bool,
+}
+
+fn arity(typ: ValType) -> usize {
+    match typ {
+        ValType::Nil => 0,
+        _ => 1,
+    }
+}
+
+/// Compiles a validated function body for the slot engine.
+///
+/// The height of the operand stack is known statically at each instruction of valid code, so
+/// locals and branch targets can be resolved to offsets from the start of the frame.
+fn compile(host: &Host, func_inst: &FuncInst) -> Result<SlotCode, Error> {
+    use crate::Instruction::*;
+
+    let (body, ir) = match func_inst.imp() {
+        FuncImpl::Local(body, _, ir) => (body, ir),
+        FuncImpl::External(_) => return Err(Error::InvalidModule),
+    };
+    let code = body.body();
+    let typ = func_inst.typ();
+    let module = host.get_module(func_inst.module());
+
+    // The function body is a block, branching to it continues past the end and returns
+    let frame_size = typ.params().len() + body.locals().len();
+    let mut height = frame_size;
+    let mut blocks = vec![CtrlFrame {
+        label: Target {
+            pc: code.len(),
+            height,
+            arity: typ.results().len(),
+        },
+        results: typ.results().len(),
+        dead: false,
+        unreachable: false,
+    }];
+
+    let mut ops = Vec::with_capacity(code.len());
+    for (inst, op) in code.iter().zip(ir) {
+        let op = match *op {
+            Op::Fused(ref fused) => fused.first(),
+            ref op => op,
+        };
+
+        let (pops, pushes, slot_op) = match (inst, op) {
+            (Unreachable, _) => (0, 0, SlotOp::Unreachable),
+            (Nop, _) => (0, 0, SlotOp::Nop),
+            (Block(typ), &Op::Block { continuation, .. }) => {
+                blocks.push(enter(
+                    &blocks,
+                    continuation,
+                    height,
+                    arity(*typ),
+                    arity(*typ),
+                ));
+                (0, 0, SlotOp::Nop)
+            }
+            (Loop(typ), &Op::Loop { start }) => {
+                blocks.push(enter(&blocks, start, height, 0, arity(*typ)));
+                (0, 0, SlotOp::Nop)
+            }
+            (
+                If(typ),
+                &Op::If {
+                    else_start,
+                    continuation,
+                    ..
+                },
+            ) => {
+                height = height.saturating_sub(1);
+                blocks.push(enter(
+                    &blocks,
+                    continuation,
+                    height,
+                    arity(*typ),
+                    arity(*typ),
+                ));
+                let target = else_start.unwrap_or(continuation);
+                (0, 0, SlotOp::JumpIfZero(target))
+            }
+            (Else, _) => {
+                // The 'then' branch is done, so skip the 'else' branch
+                let block = match blocks.last_mut() {
+                    Some(block) => block,
+                    None => return Err(Error::InvalidModule),
+                };
+                height = block.label.height;
+                block.unreachable = block.dead;
+                (0, 0, SlotOp::Jump(block.label.pc))
+            }
+            (End, _) => {
+                // The function's block has no 'end', it ends with the body
+                match blocks.pop() {
+                    Some(ref block) if !blocks.is_empty() => {
+                        height = block.label.height + block.results
+                    }
+                    _ => return Err(Error::InvalidModule),
+                }
+                (0, 0, SlotOp::Nop)
+            }
+            (Br(depth), _) => (0, 0, SlotOp::Br(label(&blocks, *depth)?)),
+            (BrIf(depth), _) => (1, 0, SlotOp::BrIf(label(&blocks, *depth)?)),
+            (BrTable(table), _) => {
+                let mut targets = Vec::with_capacity(table.targets().len() + 1);
+                for depth in table.targets().iter().cloned().chain(Some(table.default())) {
+                    targets.push(label(&blocks, depth)?);
+                }
+                (0, 0, SlotOp::BrTable(targets.into()))
+            }
+            (Return, _) => (0, 0, SlotOp::Br(blocks[0].label)),
+            (Call(_), &Op::Call(func)) => {
+                let callee = host.get_func(func);
+                let typ = callee.typ();
+                (typ.params().len(), typ.results().len(), SlotOp::Call(func))
+            }
+            (CallIndirect(type_idx, _), _) => match module.types().get(*type_idx as usize) {
+                Some(typ) => (
+                    typ.params().len() + 1,
+                    typ.results().len(),
+                    SlotOp::CallIndirect(*type_idx),
+                ),
+                None => return Err(Error::InvalidModule),
+            },
+            (Drop, _) => (1, 0, SlotOp::Drop),
+            (Select, _) => (3, 1, SlotOp::Select),
+            (I32Const(v), _) | (I64Const(v), _) | (F32Const(v), _) | (F64Const(v), _) => {
+                (0, 1, SlotOp::Const(value_to_slot(*v)))
+            }
+            (LocalGet(idx), _) => (0, 1, SlotOp::LocalGet(*idx as usize)),
+            (LocalSet(idx), _) => (1, 0, SlotOp::LocalSet(*idx as usize)),
+            (LocalTee(idx), _) => (1, 1, SlotOp::LocalTee(*idx as usize)),
+            (GlobalGet(idx), _) => (0, 1, SlotOp::GlobalGet(*idx)),
+            (GlobalSet(idx), _) => (1, 0, SlotOp::GlobalSet(*idx)),
+            (MemorySize(_), _) => (0, 1, SlotOp::MemorySize),
+            (MemoryGrow(_), _) => (1, 1, SlotOp::MemoryGrow),
+            (inst, _) => match memory(inst).or_else(|| numeric(inst)) {
+                Some(slot_op) => {
+                    let (pops, pushes) = match slot_op {
+                        SlotOp::Load { .. } => (1, 1),
+                        SlotOp::Store { .. } => (2, 0),
+                        SlotOp::Binary(_) => (2, 1),
+                        _ => (1, 1),
+                    };
+                    (pops, pushes, slot_op)
+                }
+                None => return Err(Error::InvalidModule),
+            },
+        };
+
+        height = height.saturating_sub(pops) + pushes;
+        match *inst {
+            Unreachable | Br(_) | BrTable(_) | Return => {
+                if let Some(block) = blocks.last_mut() {
+                    height = block.label.height;
+                    block.unreachable = true;
+                }
+            }
+            _ => {}
+        }
+        ops.push(slot_op);
+    }
+
+    if blocks.len() == 1 {
+        Ok(SlotCode {
+            ops,
+            locals: body.locals().len(),
+        })
+    } else {
+        Err(Error::InvalidModule)
+    }
+}
+
+/// Gets the label of the block at the specified depth, counting out from the innermost block.
+fn label(blocks: &[CtrlFrame], depth: u32) -> Result<Target, Error> {
+    match blocks.len().checked_sub(depth as usize + 1) {
+        Some(idx) => Ok(blocks[idx].label),
+        None => Err(Error::InvalidModule),
+    }
+}
+
+/// Creates a block nested in the innermost block of `blocks`.
+fn enter(
+    blocks: &[CtrlFrame],
+    pc: usize,
+    height: usize,
+    arity: usize,
+    results: usize,
+) -> CtrlFrame {
+    let dead = blocks.last().is_some_and(|b| b.unreachable);
+    CtrlFrame {
+        label: Target { pc, height, arity },
+        results,
+        dead,
+        unreachable: dead,
+    }
+}
+
+fn memory(inst: &Instruction) -> Option<SlotOp> {
+    use crate::Instruction::*;
+
+    let op = match *inst {
+        I32Load(_, offset) => load_op::<u32, u32>(offset),
+        I64Load(_, offset) => load_op::<u64, u64>(offset),
+        F32Load(_, offset) => load_op::<f32, f32>(offset),
+        F64Load(_, offset) => load_op::<f64, f64>(offset),
+        I32Load8S(_, offset) => load_op::<i32, i8>(offset),
+        I32Load8U(_, offset) => load_op::<u32, u8>(offset),
+        I32Load16S(_, offset) => load_op::<i32, i16>(offset),
+        I32Load16U(_, offset) => load_op::<u32, u16>(offset),
+        I64Load8S(_, offset) => load_op::<i64, i8>(offset),
+        I64Load8U(_, offset) => load_op::<u64, u8>(offset),
+        I64Load16S(_, offset) => load_op::<i64, i16>(offset),
+        I64Load16U(_, offset) => load_op::<u64, u16>(offset),
+        I64Load32S(_, offset) => load_op::<i64, i32>(offset),
+        I64Load32U(_, offset) => load_op::<u64, u32>(offset),
+
+        I32Store(_, offset) => store_op::<u32, u32>(offset),
+        I64Store(_, offset) => store_op::<u64, u64>(offset),
+        F32Store(_, offset) => store_op::<f32, f32>(offset),
+        F64Store(_, offset) => store_op::<f64, f64>(offset),
+        I32Store8(_, offset) => store_op::<u32, u8>(offset),
+        I32Store16(_, offset) => store_op::<u32, u16>(offset),
+        I64Store8(_, offset) => store_op::<u64, u8>(offset),
+        I64Store16(_, offset) => store_op::<u64, u16>(offset),
+        I64Store32(_, offset) => store_op::<u64, u32>(offset),
+
+        _ => return None,
+    };
+    Some(op)
+}
+
+fn load_op<T, M>(offset: u32) -> SlotOp
+where
+    M: MemoryValue,
+    T: Slot + From<M>,
+{
+    SlotOp::Load {
+        offset,
+        size: M::SIZE,
+        read: load::<T, M>,
+    }
+}
+
+fn load<T, M>(buf: &[u8]) -> u64
+where
+    M: MemoryValue,
+    T: Slot + From<M>,
+{
+    T::from(M::read_le(buf)).into_slot()
+}
+
+fn store_op<T, M>(offset: u32) -> SlotOp
+where
+    T: Slot + WrapInto<M>,
+    M: MemoryValue,
+{
+    SlotOp::Store {
+        offset,
+        size: M::SIZE,
+        write: store::<T, M>,
+    }
+}
+
+fn store<T, M>(slot: u64, buf: &mut [u8])
+where
+    T: Slot + WrapInto<M>,
+    M: MemoryValue,
+{
+    T::from_slot(slot).wrap_into().write_le(buf)
+}
+
+fn numeric(inst: &Instruction) -> Option<SlotOp> {
+    use self::SlotOp::{Binary, Unary};
+    use crate::Instruction::*;
+
+    let op = match *inst {
+        I32Eqz => Unary(eqz::<u32>),
+        I32Eq => Binary(eq::<u32>),
+        I32Ne => Binary(ne::<u32>),
+        I32LtS => Binary(lt::<i32>),
+        I32LtU => Binary(lt::<u32>),
+        I32GtS => Binary(gt::<i32>),
+        I32GtU => Binary(gt::<u32>),
+        I32LeS => Binary(le::<i32>),
+        I32LeU => Binary(le::<u32>),
+        I32GeS => Binary(ge::<i32>),
+        I32GeU => Binary(ge::<u32>),
+
+        I64Eqz => Unary(eqz::<u64>),
+        I64Eq => Binary(eq::<u64>),
+        I64Ne => Binary(ne::<u64>),
+        I64LtS => Binary(lt::<i64>),
+        I64LtU => Binary(lt::<u64>),
+        I64GtS => Binary(gt::<i64>),
+        I64GtU => Binary(gt::<u64>),
+        I64LeS => Binary(le::<i64>),
+        I64LeU => Binary(le::<u64>),
+        I64GeS => Binary(ge::<i64>),
+        I64GeU => Binary(ge::<u64>),
+
+        F32Eq => Binary(eq::<f32>),
+        F32Ne => Binary(ne::<f32>),
+        F32Lt => Binary(lt::<f32>),
+        F32Gt => Binary(gt::<f32>),
+        F32Le => Binary(le::<f32>),
+        F32Ge => Binary(ge::<f32>),
+
+        F64Eq => Binary(eq::<f64>),
+        F64Ne => Binary(ne::<f64>),
+        F64Lt => Binary(lt::<f64>),
+        F64Gt => Binary(gt::<f64>),
+        F64Le => Binary(le::<f64>),
+        F64Ge => Binary(ge::<f64>),
+
+        I32Clz => Unary(clz::<u32>),
+        I32Ctz => Unary(ctz::<u32>),
+        I32Popcnt => Unary(popcnt::<u32>),
+        I32Add => Binary(add::<u32>),
+        I32Sub => Binary(sub::<u32>),
+        I32Mul => Binary(mul::<u32>),
+        I32DivS => Binary(div::<i32>),
+        I32DivU => Binary(div::<u32>),
+        I32RemS => Binary(rem::<i32>),
+        I32RemU => Binary(rem::<u32>),
+        I32And => Binary(and::<u32>),
+        I32Or => Binary(or::<u32>),
+        I32Xor => Binary(xor::<u32>),
+        I32Shl => Binary(shl::<u32>),
+        I32ShrS => Binary(shr::<i32>),
+        I32ShrU => Binary(shr::<u32>),
+        I32Rotl => Binary(rotl::<u32>),
+        I32Rotr => Binary(rotr::<u32>),
+
+        I64Clz => Unary(clz::<u64>),
+        I64Ctz => Unary(ctz::<u64>),
+        I64Popcnt => Unary(popcnt::<u64>),
+        I64Add => Binary(add::<u64>),
+        I64Sub => Binary(sub::<u64>),
+        I64Mul => Binary(mul::<u64>),
+        I64DivS => Binary(div::<i64>),
+        I64DivU => Binary(div::<u64>),
+        I64RemS => Binary(rem::<i64>),
+        I64RemU => Binary(rem::<u64>),
+        I64And => Binary(and::<u64>),
+        I64Or => Binary(or::<u64>),
+        I64Xor => Binary(xor::<u64>),
+        I64Shl => Binary(shl::<u64>),
+        I64ShrS => Binary(shr::<i64>),
+        I64ShrU => Binary(shr::<u64>),
+        I64Rotl => Binary(rotl::<u64>),
+        I64Rotr => Binary(rotr::<u64>),
+
+        F32Abs => Unary(abs::<f32>),
+        F32Neg => Unary(neg::<f32>),
+        F32Ceil => Unary(ceil::<f32>),
+        F32Floor => Unary(floor::<f32>),
+        F32Trunc => Unary(trunc::<f32>),
+        F32Nearest => Unary(nearest::<f32>),
+        F32Sqrt => Unary(sqrt::<f32>),
+        F32Add => Binary(add::<f32>),
+        F32Sub => Binary(sub::<f32>),
+        F32Mul => Binary(mul::<f32>),
+        F32Div => Binary(div::<f32>),
+        F32Min => Binary(min::<f32>),
+        F32Max => Binary(max::<f32>),
+        F32Copysign => Binary(copysign::<f32>),
+
+        F64Abs => Unary(abs::<f64>),
+        F64Neg => Unary(neg::<f64>),
+        F64Ceil => Unary(ceil::<f64>),
+        F64Floor => Unary(floor::<f64>),
+        F64Trunc => Unary(trunc::<f64>),
+        F64Nearest => Unary(nearest::<f64>),
+        F64Sqrt => Unary(sqrt::<f64>),
+        F64Add => Binary(add::<f64>),
+        F64Sub => Binary(sub::<f64>),
+        F64Mul => Binary(mul::<f64>),
+        F64Div => Binary(div::<f64>),
+        F64Min => Binary(min::<f64>),
+        F64Max => Binary(max::<f64>),
+        F64Copysign => Binary(copysign::<f64>),
+
+        I32WrapI64 => Unary(convert::<u32, u64>),
+        I32TruncF32S => Unary(convert::<i32, f32>),
+        I32TruncF32U => Unary(convert::<u32, f32>),
+        I32TruncF64S => Unary(convert::<i32, f64>),
+        I32TruncF64U => Unary(convert::<u32, f64>),
+        I64ExtendI32S => Unary(convert::<i64, i32>),
+        I64ExtendI32U => Unary(convert::<u64, u32>),
+        I64TruncF32S => Unary(convert::<i64, f32>),
+        I64TruncF32U => Unary(convert::<u64, f32>),
+        I64TruncF64S => Unary(convert::<i64, f64>),
+        I64TruncF64U => Unary(convert::<u64, f64>),
+        F32ConvertI32S => Unary(convert::<f32, i32>),
+        F32ConvertI32U => Unary(convert::<f32, u32>),
+        F32ConvertI64S => Unary(convert::<f32, i64>),
+        F32ConvertI64U => Unary(convert::<f32, u64>),
+        F32DemoteF64 => Unary(convert::<f32, f64>),
+        F64ConvertI32S => Unary(convert::<f64, i32>),
+        F64ConvertI32U => Unary(convert::<f64, u32>),
+        F64ConvertI64S => Unary(convert::<f64, i64>),
+        F64ConvertI64U => Unary(convert::<f64, u64>),
+        F64PromoteF32 => Unary(convert::<f64, f32>),
+        I32ReinterpretF32 => Unary(reinterpret::<u32, f32>),
+        I64ReinterpretF64 => Unary(reinterpret::<u64, f64>),
+        F32ReinterpretI32 => Unary(reinterpret::<f32, u32>),
+        F64ReinterpretI64 => Unary(reinterpret::<f64, u64>),
+
+        _ => return None,
+    };
+    Some(op)
+}
+
+macro_rules! slot_binop {
+    ($try: ident, $name: ident, $($path_elem: ident)::*) => {
+        slot_binop!($try, $name, $name, $($path_elem)::*);
+    };
+    (try, $name: ident, $method: ident, $($path_elem: ident)::*) => {
+        fn $name<T>(left: u64, right: u64) -> Result<u64, TrapCause>
+        where
+            T: Slot + $($path_elem)::*,
+            <T as $($path_elem)::*>::Output: Slot,
+        {
+            Ok(T::from_slot(left).$method(T::from_slot(right))?.into_slot())
+        }
+    };
+    (notry, $name: ident, $method: ident, $($path_elem: ident)::*) => {
+        fn $name<T>(left: u64, right: u64) -> Result<u64, TrapCause>
+        where
+            T: Slot + $($path_elem)::*,
+            <T as $($path_elem)::*>::Output: Slot,
+        {
+            Ok(T::from_slot(left).$method(T::from_slot(right)).into_slot())
+        }
+    };
+}
+
+macro_rules! slot_unop {
+    ($name: ident, $($path_elem: ident)::*) => {
+        fn $name<T>(val: u64) -> Result<u64, TrapCause>
+        where
+            T: Slot + $($path_elem)::*,
+            <T as $($path_elem)::*>::Output: Slot,
+        {
+            Ok(T::from_slot(val).$name().into_slot())
+        }
+    };
+}
+
+slot_unop!(clz, value::ops::IntegerOps);
+slot_unop!(ctz, value::ops::IntegerOps);
+slot_unop!(popcnt, value::ops::IntegerOps);
+slot_binop!(notry, add, value::ops::ArithmeticOps);
+slot_binop!(notry, sub, value::ops::ArithmeticOps);
+slot_binop!(notry, mul, value::ops::ArithmeticOps);
+slot_binop!(try, div, value::ops::ArithmeticOps);
+slot_binop!(try, rem, value::ops::IntegerOps);
+slot_binop!(notry, and, bitand, ops::BitAnd);
+slot_binop!(notry, or, bitor, ops::BitOr);
+slot_binop!(notry, xor, bitxor, ops::BitXor);
+slot_binop!(notry, shl, value::ops::IntegerOps);
+slot_binop!(notry, shr, value::ops::IntegerOps);
+slot_binop!(notry, rotl, value::ops::IntegerOps);
+slot_binop!(notry, rotr, value::ops::IntegerOps);
+slot_binop!(notry, copysign, value::ops::FloatOps);
+slot_binop!(notry, max, value::ops::FloatOps);
+slot_binop!(notry, min, value::ops::FloatOps);
+slot_unop!(sqrt, value::ops::FloatOps);
+slot_unop!(nearest, value::ops::FloatOps);
+slot_unop!(trunc, value::ops::FloatOps);
+slot_unop!(floor, value::ops::FloatOps);
+slot_unop!(ceil, value::ops::FloatOps);
+slot_unop!(neg, value::ops::FloatOps);
+slot_unop!(abs, value::ops::FloatOps);
+
+fn eqz<T>(val: u64) -> Result<u64, TrapCause>
+where
+    T: Slot + cmp::Eq + From<u8>,
+{
+    Ok((T::from_slot(val) == 0.into()).into_slot())
+}
+
+fn eq<T>(left: u64, right: u64) -> Result<u64, TrapCause>
+where
+    T: Slot + cmp::PartialEq,
+{
+    Ok((T::from_slot(left) == T::from_slot(right)).into_slot())
+}
+
+fn ne<T>(left: u64, right: u64) -> Result<u64, TrapCause>
+where
+    T: Slot + cmp::PartialEq,
+{
+    Ok((T::from_slot(left) != T::from_slot(right)).into_slot())
+}
+
+macro_rules! slot_ord {
+    ($name: ident, $($true_ord: ident),*) => {
+        fn $name<T>(left: u64, right: u64) -> Result<u64, TrapCause>
+        where
+            T: Slot + cmp::PartialOrd,
+        {
+            let res = match T::from_slot(left).partial_cmp(&T::from_slot(right)) {
+                $(
+                    Some(cmp::Ordering::$true_ord) => true,
+                )*
+                _ => false
+            };
+            Ok(res.into_slot())
+        }
+    };
+}
+
+slot_ord!(lt, Less);
+slot_ord!(gt, Greater);
+slot_ord!(le, Less, Equal);
+slot_ord!(ge, Greater, Equal);
+
+fn convert<T, U>(val: u64) -> Result<u64, TrapCause>
+where
+    T: Slot,
+    U: Slot + value::ops::ConvertInto<T>,
+{
+    Ok(U::from_slot(val).convert_into()?.into_slot())
+}
+
+fn reinterpret<T, U>(val: u64) -> Result<u64, TrapCause>
+where
+    T: Slot,
+    U: Slot + value::ops::ReinterpretInto<T>,
+{
+    Ok(U::from_slot(val).reinterpret_into().into_slot())
+}
+
+/// Gets the slot code for a local function, compiling it the first time.
+fn slot_code(host: &Host, func_inst: &FuncInst) -> Result<Arc<SlotCode>, TrapCause> {
+    if let Some(code) = func_inst.slot_code().get() {
+        return Ok(code.clone());
+    }
+
+    let code = match compile(host, func_inst) {
+        Ok(code) => Arc::new(code),
+        Err(e) => return Err(format!("Invalid function body: {:?}", e).into()),
+    };
+    Ok(func_inst.slot_code().get_or_init(|| code).clone())
+}
+
+struct SlotFrame {
+    func: Arc<FuncInst>,
+    addr: FuncAddr,
+    code: Arc<SlotCode>,
+    /// The offset of the frame's first slot, which holds the first parameter.
+    base: usize,
+    /// The offset of the next operation, saved while the frame calls another function.
+    pc: usize,
+}
+
+/// The stack of the slot engine, holding the locals and operands of every frame.
+pub struct SlotStack {
+    slots: Vec<u64>,
+    frames: Vec<SlotFrame>,
+}
+
+/// Returns early with a [`Trap`] capturing the stack if the expression is an error.
+macro_rules! check {
+    ($stack: ident, $thread: ident, $e: expr) => {
+        match $e {
+            Ok(v) => v,
+            Err(e) => return Err($stack.throw($thread, e)),
+        }
+    };
+}
+
+impl SlotStack {
+    pub fn new() -> SlotStack {
+        SlotStack {
+            slots: Vec::new(),
+            frames: Vec::new(),
+        }
+    }
+
+    /// Runs a local function, popping its parameters off the current frame of the thread.
+    pub fn invoke(
+        &mut self,
+        thread: &mut Thread,
+        host: &mut Host,
+        func_inst: &Arc<FuncInst>,
+        func: FuncAddr,
+    ) -> Result<Vec<Value>, Trap> {
+        let (slots, frames) = (self.slots.len(), self.frames.len());
+
+        // Pop parameters, the last parameter is on top of the stack
+        let params = func_inst.typ().params();
+        let mut args = Vec::with_capacity(params.len());
+        for param in params.iter().rev() {
+            let val = thread.pop()?;
+            if val.typ() != *param {
+                return Err(self.throw(
+                    thread,
+                    format!("Type mismatch. Expected: {}, Actual: {}", param, val.typ()),
+                ));
+            }
+            args.push(value_to_slot(val));
+        }
+        self.slots.extend(args.into_iter().rev());
+
+        let result = self
+            .enter(thread, host, func_inst.clone(), func)
+            .and_then(|()| self.run(thread, host, frames))
+            .map(|()| {
+                let results = func_inst.typ().results();
+                let start = self.slots.len() - results.len();
+                self.slots[start..]
+                    .iter()
+                    .zip(results)
+                    .map(|(slot, typ)| slot_to_value(*slot, *typ))
+                    .collect()
+            });
+
+        self.slots.truncate(slots);
+        thread.exit_slot_frames(self.frames.len() - frames);
+        self.frames.truncate(frames);
+        result
+    }
+
+    /// Enters a new frame for a local function, whose parameters are on top of the stack.
+    fn enter(
+        &mut self,
+        thread: &mut Thread,
+        host: &Host,
+        func_inst: Arc<FuncInst>,
+        func: FuncAddr,
+    ) -> Result<(), Trap> {
+        if thread.depth() >= thread.stack().max_depth() {
+            return Err(self.throw(thread, TrapCause::CallStackExhausted));
+        }
+        let code = check!(self, thread, slot_code(host, &func_inst));
+
+        // Locals are zero, which is the representation of zero for every type
+        let base = self.slots.len() - func_inst.typ().params().len();
+        let len = self.slots.len() + code.locals;
+        self.slots.resize(len, 0);
+        self.frames.push(SlotFrame {
+            func: func_inst,
+            addr: func,
+            code,
+            base,
+            pc: 0,
+        });
+        thread.enter_slot_frame();
+        Ok(())
+    }
+
+    /// Calls a function, whose parameters are on top of the stack.
+    ///
+    /// Local functions enter a new frame, external functions run immediately and their results
+    /// are pushed on to the stack.
+    fn call(&mut self, thread: &mut Thread, host: &mut Host, func: FuncAddr) -> Result<(), Trap> {
+        let func_inst = host.get_func(func);
+        let external = match func_inst.imp() {
+            FuncImpl::Local(..) => return self.enter(thread, host, func_inst.clone(), func),
+            FuncImpl::External(external) => external,
+        };
+
+        let typ = func_inst.typ();
+        let start = self.slots.len() - typ.params().len();
+        let args: Vec<_> = self.slots[start..]
+            .iter()
+            .zip(typ.params())
+            .map(|(slot, typ)| slot_to_value(*slot, *typ))
+            .collect();
+        self.slots.truncate(start);
+
+        let results = check!(self, thread, external.call(host, thread, &args));
+        if !results
+            .iter()
+            .map(Value::typ)
+            .eq(typ.results().iter().cloned())
+        {
+            return Err(self.throw(
+                thread,
+                format!(
+                    "Type mismatch. Function '{}' returned unexpected results.",
+                    external.name()
+                ),
+            ));
+        }
+        self.slots.extend(results.into_iter().map(value_to_slot));
+        Ok(())
+    }
+
+    /// Runs operations until the frame above `entry` frames returns.
+    fn run(&mut self, thread: &mut Thread, host: &mut Host, entry: usize) -> Result<(), Trap> {
+        let (mut code, mut base, mut pc, mut module) = self.current();
+        loop {
+            if pc == code.ops.len() {
+                // Return, moving the results to the start of the frame
+                let frame = self.frames.pop().expect("Frame stack should not be empty!");
+                thread.exit_slot_frames(1);
+                let arity = frame.func.typ().results().len();
+                let top = self.slots.len();
+                self.slots.copy_within(top - arity..top, frame.base);
+                self.slots.truncate(frame.base + arity);
+
+                if self.frames.len() == entry {
+                    return Ok(());
+                }
+                let (c, b, p, m) = self.current();
+                code = c;
+                base = b;
+                pc = p;
+                module = m;
+                continue;
+            }
+
+            if thread.fuel().is_some() {
+                let cost = match self.frames[self.frames.len() - 1].func.imp() {
+                    FuncImpl::Local(body, ..) => thread.fuel_cost()(&body.body()[pc]),
+                    FuncImpl::External(_) => unreachable!("not a local function"),
+                };
+                check!(self, thread, thread.consume_fuel(cost));
+            }
+
+            let depth = self.frames.len();
+            let op = &code.ops[pc];
+            pc += 1;
+            match *op {
+                SlotOp::Nop => {}
+                SlotOp::Unreachable => return Err(self.throw(thread, TrapCause::Unreachable)),
+                SlotOp::Jump(target) => pc = target,
+                SlotOp::JumpIfZero(target) => {
+                    if self.pop() == 0 {
+                        pc = target;
+                    }
+                }
+                SlotOp::Br(target) => pc = self.branch(base, target),
+                SlotOp::BrIf(target) => {
+                    if self.pop() != 0 {
+                        pc = self.branch(base, target);
+                    }
+                }
+                SlotOp::BrTable(ref targets) => {
+                    let idx = cmp::min(self.pop() as u32 as usize, targets.len() - 1);
+                    pc = self.branch(base, targets[idx]);
+                }
+                SlotOp::Call(func) => {
+                    self.frames[depth - 1].pc = pc;
+                    self.call(thread, host, func)?;
+                }
+                SlotOp::CallIndirect(type_idx) => {
+                    self.frames[depth - 1].pc = pc;
+                    let elem_idx = self.pop() as u32;
+                    let table = host.get_table(host.resolve_table(module, 0));
+                    let func = match table.get(elem_idx as usize) {
+                        Some(Some(func)) => func,
+                        Some(None) => {
+                            return Err(self.throw(thread, TrapCause::UninitializedElement))
+                        }
+                        None => return Err(self.throw(thread, TrapCause::UndefinedElement)),
+                    };
+
+                    // Check the callee's signature against the expected type
+                    let module_inst = host.get_module(module);
+                    match module_inst.types().get(type_idx as usize) {
+                        Some(typ) if typ == host.get_func(func).typ() => {}
+                        _ => return Err(self.throw(thread, TrapCause::IndirectCallTypeMismatch)),
+                    }
+                    self.call(thread, host, func)?;
+                }
+                SlotOp::Drop => {
+                    self.pop();
+                }
+                SlotOp::Select => {
+                    let cond = self.pop();
+                    let val2 = self.pop();
+                    if cond == 0 {
+                        *self.top() = val2;
+                    }
+                }
+                SlotOp::Const(val) => self.slots.push(val),
+                SlotOp::LocalGet(idx) => {
+                    let val = self.slots[base + idx];
+                    self.slots.push(val);
+                }
+                SlotOp::LocalSet(idx) => {
+                    let val = self.pop();
+                    self.slots[base + idx] = val;
+                }
+                SlotOp::LocalTee(idx) => {
+                    let val = *self.top();
+                    self.slots[base + idx] = val;
+                }
+                SlotOp::GlobalGet(idx) => {
+                    let global = host.get_global(host.resolve_global(module, idx as usize));
+                    self.slots.push(value_to_slot(global.value()));
+                }
+                SlotOp::GlobalSet(idx) => {
+                    let global = host.get_global(host.resolve_global(module, idx as usize));
+                    let val = slot_to_value(self.pop(), global.typ().typ());
+                    check!(self, thread, global.set(val));
+                }
+                SlotOp::Load { offset, size, read } => {
+                    let addr = self.pop() as u32;
+                    let mem_inst = host.get_mem(host.resolve_mem(module, 0));
+                    let mem = mem_inst.memory();
+                    let (start, end) =
+                        check!(self, thread, effective_range(addr, offset, size, mem.len()));
+                    let val = read(&mem.bytes()[start..end]);
+                    self.slots.push(val);
+                }
+                SlotOp::Store {
+                    offset,
+                    size,
+                    write,
+                } => {
+                    let val = self.pop();
+                    let addr = self.pop() as u32;
+                    let mem_inst = host.get_mem(host.resolve_mem(module, 0));
+                    let mut mem = mem_inst.memory_mut();
+                    let (start, end) =
+                        check!(self, thread, effective_range(addr, offset, size, mem.len()));
+                    write(val, &mut mem.bytes_mut()[start..end]);
+                }
+                SlotOp::MemorySize => {
+                    let pages = host.get_mem(host.resolve_mem(module, 0)).memory().pages();
+                    self.slots.push(pages as u32 as u64);
+                }
+                SlotOp::MemoryGrow => {
+                    let delta = self.pop() as u32;
+                    let mem_inst = host.get_mem(host.resolve_mem(module, 0));
+                    let result = match mem_inst.memory_mut().grow(delta as usize) {
+                        Some(old_pages) => old_pages as u32,
+                        None => 0xFFFF_FFFF,
+                    };
+                    self.slots.push(result as u64);
+                }
+                SlotOp::Unary(f) => {
+                    let val = check!(self, thread, f(*self.top()));
+                    *self.top() = val;
+                }
+                SlotOp::Binary(f) => {
+                    let right = self.pop();
+                    let val = check!(self, thread, f(*self.top(), right));
+                    *self.top() = val;
+                }
+            }
+
+            // Calls enter a new frame, so continue with its code
+            if self.frames.len() != depth {
+                let (c, b, p, m) = self.current();
+                code = c;
+                base = b;
+                pc = p;
+                module = m;
+            }
+        }
+    }
+
+    /// Gets the code, base, program counter and module of the current frame.
+    fn current(&self) -> (Arc<SlotCode>, usize, usize, ModuleAddr) {
+        let frame = self
+            .frames
+            .last()
+            .expect("Frame stack should not be empty!");
+        (
+            frame.code.clone(),
+            frame.base,
+            frame.pc,
+            frame.func.module(),
+        )
+    }
+
+    /// Branches to a target, keeping its values on top of the stack, and returns where to continue.
+    fn branch(&mut self, base: usize, target: Target) -> usize {
+        let top = self.slots.len();
+        let height = base + target.height;
+        if height + target.arity != top {
+            self.slots.copy_within(top - target.arity..top, height);
+            self.slots.truncate(height + target.arity);
+        }
+        target.pc
+    }
+
+    // Validation guarantees the operands are there, so popping them can't fail
+
+    fn pop(&mut self) -> u64 {
+        self.slots.pop().expect("Slot stack should not be empty!")
+    }
+
+    fn top(&mut self) -> &mut u64 {
+        self.slots
+            .last_mut()
+            .expect("Slot stack should not be empty!")
+    }
+
+    /// Creates a new [`Trap`], capturing the frames of this stack on top of the thread's.
+    fn throw<T: Into<Trap>>(&self, thread: &Thread, trap: T) -> Trap {
+        let mut frames: Vec<_> = self
+            .frames
+            .iter()
+            .rev()
+            .map(|f| StackFrame::new(f.func.module(), Some(f.addr)))
+            .collect();
+        frames.extend(thread.stack().trace().frames().iter().cloned());
+
+        let mut trap = trap.into();
+        trap.try_set_stack(StackTrace::new(frames));
+        trap
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interp::exec::tests::instantiate_text;
+
+    /// Calls the export named 'test' of a new instance on each engine, asserting that they agree.
+    fn call_on_both_engines(text: &str, args: Vec<Value>) -> Result<Vec<Value>, String> {
+        let mut results = Vec::new();
+        for engine in &[Engine::Stack, Engine::Slots] {
+            let (mut host, module_addr, func_addr) = instantiate_text(text);
+            let mut thread = Thread::new();
+            thread.set_engine(*engine);
+            let result = thread.call(&mut host, module_addr, func_addr, args.clone());
+            results.push(result.map_err(|trap| trap.cause().to_string()));
+            assert_eq!(0, thread.stack().depth());
+        }
+        assert_eq!(results[0], results[1]);
+        results.pop().unwrap()
+    }
+
+    #[test]
+    pub fn slot_engine_runs_control_flow() {
+        let text = r#"
+            (func (export "test") (param i32) (result i64) (local i64)
+                (block $done
+                    (block $two
+                        (block $one
+                            (br_table $one $two $done (local.get 0)))
+                        ;; Sum 1..10 in a loop
+                        (local.set 0 (i32.const 10))
+                        (loop $next
+                            (local.set 1 (i64.add (local.get 1) (i64.extend_i32_u (local.get 0))))
+                            (local.tee 0 (i32.sub (local.get 0) (i32.const 1)))
+                            (br_if $next))
+                        (return (local.get 1)))
+                    ;; Branch out of a block, keeping only the top value
+                    (return (block (result i64)
+                        (i64.const 1)
+                        (drop)
+                        (i64.const 2)
+                        (i64.const 3)
+                        (br 0)
+                        (i64.const 4))))
+                (select
+                    (if (result i64) (i32.eq (local.get 0) (i32.const 2))
+                        (then (i64.const 20))
+                        (else (i64.const 30)))
+                    (i64.const 40)
+                    (i32.lt_u (local.get 0) (i32.const 3))))"#;
+        let call = |n| call_on_both_engines(text, vec![Value::I32(n)]);
+        assert_eq!(Ok(vec![Value::I64(55)]), call(0));
+        assert_eq!(Ok(vec![Value::I64(3)]), call(1));
+        assert_eq!(Ok(vec![Value::I64(20)]), call(2));
+        assert_eq!(Ok(vec![Value::I64(40)]), call(3));
+    }
+
+    #[test]
+    pub fn slot_engine_calls_functions_and_accesses_state() {
+        let text = r#"
+            (import "env" "memory" (memory 1))
+            (type $binop (func (param f64 f64) (result f64)))
+            (table 2 funcref)
+            (elem (i32.const 0) $sub $mul)
+            (global $calls (mut i32) (i32.const 0))
+            (func $sub (type $binop) (f64.sub (local.get 0) (local.get 1)))
+            (func $mul (type $binop) (f64.mul (local.get 0) (local.get 1)))
+            (func $apply (param i32 f64 f64) (result f64)
+                (global.set $calls (i32.add (global.get $calls) (i32.const 1)))
+                (call_indirect (type $binop) (local.get 1) (local.get 2) (local.get 0)))
+            (func (export "test") (param i32) (result f64)
+                (f64.store offset=8 (i32.const 0) (f64.const 1.5))
+                (call $apply
+                    (local.get 0)
+                    (call $apply (i32.const 1) (f64.load offset=8 (i32.const 0)) (f64.const 4))
+                    (f64.convert_i32_s (global.get $calls))))"#;
+        assert_eq!(
+            Ok(vec![Value::F64(5.0)]),
+            call_on_both_engines(text, vec![Value::I32(0)])
+        );
+        assert_eq!(
+            Ok(vec![Value::F64(6.0)]),
+            call_on_both_engines(text, vec![Value::I32(1)])
+        );
+        assert_eq!(
+            Err("undefined element".to_string()),
+            call_on_both_engines(text, vec![Value::I32(2)])
+        );
+    }
+
+    #[test]
+    pub fn slot_engine_traps_like_stack_engine() {
+        let text = r#"
+            (import "env" "memory" (memory 1))
+            (func (export "test") (param i32) (result i32)
+                (if (i32.eqz (local.get 0)) (then (unreachable)))
+                (drop (i32.load (i32.const 0x1000000)))
+                (i32.div_s (i32.const 1) (local.get 0)))"#;
+        assert_eq!(
+            Err("unreachable".to_string()),
+            call_on_both_engines(text, vec![Value::I32(0)])
+        );
+        assert_eq!(
+            Err("out of bounds memory access".to_string()),
+            call_on_both_engines(text, vec![Value::I32(1)])
+        );
+    }
+
+    #[test]
+    pub fn slot_engine_meters_fuel_and_limits_depth() {
+        let (mut host, module_addr, func_addr) = instantiate_text(
+            r#"
+            (import "metered" "work" (func $work))
+            (func $count (export "test") (param i32) (result i32)
+                (call $work)
+                (if (result i32) (local.get 0)
+                    (then (i32.add
+                        (call $count (i32.sub (local.get 0) (i32.const 1)))
+                        (i32.const 1)))
+                    (else (i32.const 0))))"#,
+        );
+        let mut thread = Thread::new();
+        thread.set_engine(Engine::Slots);
+        assert_eq!(
+            Ok(vec![Value::I32(10000)]),
+            thread.call(&mut host, module_addr, func_addr, vec![Value::I32(10000)])
+        );
+
+        // Both engines charge the same fuel, but the slot engine can't resume
+        let mut fuel = Vec::new();
+        for engine in &[Engine::Stack, Engine::Slots] {
+            thread.set_engine(*engine);
+            thread.set_fuel(1000);
+            thread
+                .call(&mut host, module_addr, func_addr, vec![Value::I32(3)])
+                .unwrap();
+            fuel.push(thread.fuel());
+        }
+        assert_eq!(fuel[0], fuel[1]);
+        thread.set_fuel(5);
+        let trap = thread
+            .call(&mut host, module_addr, func_addr, vec![Value::I32(3)])
+            .unwrap_err();
+        assert!(*trap.cause() == TrapCause::OutOfFuel);
+        assert!(!thread.is_suspended());
+
+        thread.remove_fuel_limit();
+        thread.stack_mut().set_max_depth(10);
+        let trap = thread
+            .call(&mut host, module_addr, func_addr, vec![Value::I32(9)])
+            .unwrap_err();
+        assert!(*trap.cause() == TrapCause::CallStackExhausted);
+        assert_eq!(10, trap.trace().unwrap().frames().len());
+    }
+}
@@ -1,8 +1,9 @@
-use std::fmt;
+use std::{fmt, sync::Arc};
 
 use crate::{
-    hosting::{FuncAddr, ModuleAddr},
-    FromValue, TrapCause, Value,
+    hosting::{FuncAddr, FuncImpl, FuncInst, ModuleAddr},
+    interp::Op,
+    FromValue, Instruction, TrapCause, Value,
 };
 
 #[derive(Clone, PartialEq)]
@@ -54,23 +55,104 @@ impl fmt::Debug for StackFrame {
     }
 }
 
+/// Represents a structured control label (a `block`, `loop`, `if` or function body).
+#[derive(Clone, Copy, PartialEq)]
+pub struct Label {
+    arity: usize,
+    height: usize,
+    continuation: usize,
+}
+
+impl Label {
+    /// Creates a new label.
+    ///
+    /// * `arity` is the number of values carried by a branch to this label.
+    /// * `height` is the height of the operand stack when the label was entered.
+    /// * `continuation` is the offset of the instruction to execute after branching to this label.
+    pub fn new(arity: usize, height: usize, continuation: usize) -> Label {
+        Label {
+            arity,
+            height,
+            continuation,
+        }
+    }
+
+    pub fn arity(&self) -> usize {
+        self.arity
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn continuation(&self) -> usize {
+        self.continuation
+    }
+}
+
+/// The code run by an [`ExecutionContext`].
+#[derive(Clone)]
+pub enum Code {
+    /// The context runs no code, it only holds values (such as the arguments a host passes to a function).
+    None,
+    /// The context runs the body of a local function.
+    Func(Arc<FuncInst>),
+    /// The context runs a standalone expression (such as a global initializer), given as the
+    /// instructions along with the [`Op`]s they were lowered to.
+    Expr(Arc<[Instruction]>, Arc<[Op]>),
+}
+
+impl Code {
+    /// Gets the instructions to run.
+    pub fn instructions(&self) -> &[Instruction] {
+        match self {
+            Code::Func(func) => match func.imp() {
+                FuncImpl::Local(body, _, _) => body.body(),
+                FuncImpl::External(_) => &[],
+            },
+            Code::Expr(instructions, _) => instructions,
+            Code::None => &[],
+        }
+    }
+
+    /// Gets the [`Op`]s the instructions were lowered to, there is one for each instruction.
+    pub fn ops(&self) -> &[Op] {
+        match self {
+            Code::Func(func) => match func.imp() {
+                FuncImpl::Local(_, _, ops) => ops,
+                FuncImpl::External(_) => &[],
+            },
+            Code::Expr(_, ops) => ops,
+            Code::None => &[],
+        }
+    }
+}
+
 /// Represents the context under which a function executes.
 ///
 /// The execution context contains the following items:
 /// * The operand stack for the invocation.
 /// * The values of the locals currently in scope.
+/// * The stack of control labels currently in scope.
+/// * The code being run, and the offset of the next instruction to execute.
 /// * A [`StackFrame`] representing the current location in the program.
 pub struct ExecutionContext {
     values: Vec<Value>,
     locals: Vec<Value>,
+    labels: Vec<Label>,
+    code: Code,
+    pc: usize,
     frame: StackFrame,
 }
 
 impl ExecutionContext {
-    /// Creates a new execution context with the specified [`ExecutionContext`] and a list of local values.
-    pub fn new(frame: StackFrame, locals: Vec<Value>) -> ExecutionContext {
+    /// Creates a new execution context with the specified [`StackFrame`], code to run and list of local values.
+    pub fn new(frame: StackFrame, code: Code, locals: Vec<Value>) -> ExecutionContext {
         ExecutionContext {
             values: Vec::new(),
+            labels: Vec::new(),
+            code,
+            pc: 0,
             frame,
             locals,
         }
@@ -81,6 +163,11 @@ impl ExecutionContext {
         &self.frame
     }
 
+    /// Gets the [`Code`] run by this execution context.
+    pub fn code(&self) -> &Code {
+        &self.code
+    }
+
     /// Pushes a new value on to the operand stack for this execution context.
     pub fn push(&mut self, value: Value) {
         // Don't push nils, just drop them.
@@ -99,6 +186,11 @@ impl ExecutionContext {
         self.values.is_empty()
     }
 
+    /// Gets the number of values on the operand stack for this execution context.
+    pub fn height(&self) -> usize {
+        self.values.len()
+    }
+
     /// Gets the value of the local with the specified index.
     pub fn local(&self, idx: usize) -> Option<Value> {
         if idx < self.locals.len() {
@@ -107,13 +199,104 @@ impl ExecutionContext {
             None
         }
     }
+
+    /// Gets a mutable reference to the local with the specified index.
+    pub fn local_mut(&mut self, idx: usize) -> Option<&mut Value> {
+        self.locals.get_mut(idx)
+    }
+
+    /// Gets the offset of the next instruction to be executed.
+    pub fn pc(&self) -> usize {
+        self.pc
+    }
+
+    /// Moves the program counter to the instruction following the current one.
+    pub fn advance(&mut self) {
+        self.pc += 1;
+    }
+
+    /// Sets the offset of the next instruction to be executed.
+    pub fn jump(&mut self, pc: usize) {
+        self.pc = pc;
+    }
+
+    /// Pushes a new [`Label`] on to the control stack.
+    pub fn push_label(&mut self, label: Label) {
+        self.labels.push(label)
+    }
+
+    /// Pops the innermost [`Label`] off the control stack.
+    pub fn pop_label(&mut self) -> Option<Label> {
+        self.labels.pop()
+    }
+
+    /// Gets the number of labels currently in scope.
+    pub fn label_depth(&self) -> usize {
+        self.labels.len()
+    }
+
+    /// Branches to the label at the specified depth (`0` being the innermost label).
+    ///
+    /// The top `arity` values of the operand stack are preserved, all other values pushed since
+    /// the label was entered are discarded, the label (and all labels nested within it) are
+    /// popped off the control stack and the program counter is moved to the label's continuation.
+    pub fn branch(&mut self, depth: usize) -> Result<(), TrapCause> {
+        if depth >= self.labels.len() {
+            return Err(format!("Invalid branch depth: {}", depth).into());
+        }
+        let idx = self.labels.len() - 1 - depth;
+        let label = self.labels[idx];
+
+        // Unwind the operand stack to the label height, keeping the branch values
+        if self.values.len() < label.height + label.arity {
+            return Err(TrapCause::StackUnderflow);
+        }
+        let keep_from = self.values.len() - label.arity;
+        self.values.drain(label.height..keep_from);
+
+        self.labels.truncate(idx);
+        self.pc = label.continuation;
+        Ok(())
+    }
 }
 
-pub struct ExecutionStack(Vec<ExecutionContext>);
+/// The maximum number of [`ExecutionContext`]s an [`ExecutionStack`] holds unless configured otherwise.
+pub const DEFAULT_MAX_DEPTH: usize = 16 * 1024;
+
+pub struct ExecutionStack {
+    contexts: Vec<ExecutionContext>,
+    max_depth: usize,
+}
 
 impl ExecutionStack {
     pub fn new() -> ExecutionStack {
-        ExecutionStack(Vec::new())
+        ExecutionStack::with_max_depth(DEFAULT_MAX_DEPTH)
+    }
+
+    /// Creates a new stack that can hold at most `max_depth` [`ExecutionContext`]s.
+    pub fn with_max_depth(max_depth: usize) -> ExecutionStack {
+        ExecutionStack {
+            contexts: Vec::new(),
+            max_depth,
+        }
+    }
+
+    /// Gets the number of [`ExecutionContext`]s currently on the stack.
+    pub fn depth(&self) -> usize {
+        self.contexts.len()
+    }
+
+    /// Gets the maximum number of [`ExecutionContext`]s the stack can hold.
+    pub fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    /// Sets the maximum number of [`ExecutionContext`]s the stack can hold.
+    ///
+    /// Lowering the maximum below the current depth does not affect contexts already on the
+    /// stack, but no new contexts can be entered until enough have exited.
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.max_depth = max_depth;
     }
 
     /// Gets a reference to the active [`ExecutionContext`]
@@ -121,7 +304,7 @@ impl ExecutionStack {
     /// # Panics
     /// Panics if there is no current [`ExecutionContext`] on the stack
     pub fn current(&self) -> &ExecutionContext {
-        self.0.last().unwrap()
+        self.contexts.last().unwrap()
     }
 
     /// Gets a mutable reference to the active [`ExecutionContext`].
@@ -129,13 +312,28 @@ impl ExecutionStack {
     /// # Panics
     /// Panics if there is no current [`ExecutionContext`] on the stack
     pub fn current_mut(&mut self) -> &mut ExecutionContext {
-        self.0.last_mut().unwrap()
+        self.contexts.last_mut().unwrap()
     }
 
     /// Pushes a new [`ExecutionContext`] on to the stack
-    pub fn enter(&mut self, module: ModuleAddr, func: Option<FuncAddr>, locals: Vec<Value>) {
-        self.0
-            .push(ExecutionContext::new(StackFrame::new(module, func), locals))
+    ///
+    /// Returns [`TrapCause::CallStackExhausted`] if the stack is already at its maximum depth.
+    pub fn enter(
+        &mut self,
+        module: ModuleAddr,
+        func: Option<FuncAddr>,
+        code: Code,
+        locals: Vec<Value>,
+    ) -> Result<(), TrapCause> {
+        if self.contexts.len() >= self.max_depth {
+            return Err(TrapCause::CallStackExhausted);
+        }
+        self.contexts.push(ExecutionContext::new(
+            StackFrame::new(module, func),
+            code,
+            locals,
+        ));
+        Ok(())
     }
 
     /// Pops the current [`ExecutionContext`] (and all values associated with it) off the stack
@@ -143,17 +341,27 @@ impl ExecutionStack {
     /// # Panics
     /// Panics if there is no current [`ExecutionContext`] on the stack
     pub fn exit(&mut self) {
-        if self.0.len() == 0 {
+        if self.contexts.is_empty() {
             panic!("There is no current frame to exit!");
         } else {
-            self.0.pop();
+            self.contexts.pop();
         }
     }
 
+    /// Pops [`ExecutionContext`]s off the stack until there are no more than `depth` left.
+    pub fn unwind(&mut self, depth: usize) {
+        self.contexts.truncate(depth);
+    }
+
     /// Creates a [`StackTrace`] representing the current position in the stack.
     pub fn trace(&self) -> StackTrace {
         // Iterate up the stack from bottom to top, cloning the stack frames
-        let frames = self.0.iter().rev().map(|c| c.frame().clone()).collect();
+        let frames = self
+            .contexts
+            .iter()
+            .rev()
+            .map(|c| c.frame().clone())
+            .collect();
         StackTrace(frames)
     }
 
@@ -1,29 +1,175 @@
+use std::{mem, sync::Arc};
+
 use crate::{
-    hosting::{FuncAddr, FuncImpl, Host, ModuleAddr},
-    interp::{exec, ExecutionStack},
+    hosting::{FuncAddr, FuncImpl, FuncInst, Host, ModuleAddr},
+    interp::{self, exec, Code, Engine, ExecutionStack, Label, Op, SlotStack},
     module::Expr,
     Instruction, Trap, TrapCause, ValType, Value,
 };
 
+/// The fuel cost function used by default, which charges one unit of fuel per instruction.
+pub fn default_fuel_cost(_inst: &Instruction) -> u64 {
+    1
+}
+
+/// An invocation of a local function, which runs until the function's frame returns.
+#[derive(Clone, Copy)]
+struct Invocation {
+    /// The depth of the stack before the invocation started, the stack is unwound to it when the invocation ends.
+    base: usize,
+    /// The depth of the stack when the invoked function's frame is the current frame.
+    frame: usize,
+}
+
+/// The reason the run loop stopped before an invocation completed.
+enum Stop {
+    /// There was not enough fuel to run the next instruction, execution can resume from that instruction.
+    OutOfFuel(Trap),
+    Trap(Trap),
+}
+
+/// Runs WebAssembly code.
+///
+/// Calls between WebAssembly functions don't recurse on the native stack, each function runs in
+/// an [`ExecutionContext`](crate::interp::ExecutionContext) on the thread's [`ExecutionStack`] and
+/// a single loop runs the instructions of whichever context is current. Host functions can call
+/// back into the thread, which starts a nested loop.
 pub struct Thread {
     stack: ExecutionStack,
+    /// The fuel remaining, or `None` if execution is not metered.
+    fuel: Option<u64>,
+    fuel_cost: fn(&Instruction) -> u64,
+    /// The number of invocations currently running on this thread.
+    running: usize,
+    /// The invocation that ran out of fuel, if any.
+    suspended: Option<Invocation>,
+    engine: Engine,
+    /// The stack used by the slot engine, which is moved out of the thread while it runs.
+    slots: SlotStack,
+    /// The number of frames on the slot engine's stacks, including those moved out of the thread
+    /// by invocations that are still running.
+    slot_frames: usize,
 }
 
 impl Thread {
     pub fn new() -> Thread {
         Thread {
             stack: ExecutionStack::new(),
+            fuel: None,
+            fuel_cost: default_fuel_cost,
+            running: 0,
+            suspended: None,
+            engine: Engine::Stack,
+            slots: SlotStack::new(),
+            slot_frames: 0,
+        }
+    }
+
+    /// Gets the fuel remaining, or `None` if execution is not metered.
+    pub fn fuel(&self) -> Option<u64> {
+        self.fuel
+    }
+
+    /// Sets the fuel remaining, enabling metering if it was not already enabled.
+    ///
+    /// When metering is enabled, each instruction consumes fuel before it executes (see
+    /// [`Thread::set_fuel_cost`]), and execution traps with [`TrapCause::OutOfFuel`] if there is
+    /// not enough fuel left. The invocation is then suspended, and can continue from the
+    /// instruction that ran out of fuel using [`Thread::resume`] once more fuel is added.
+    pub fn set_fuel(&mut self, fuel: u64) {
+        self.fuel = Some(fuel);
+    }
+
+    /// Adds fuel, enabling metering (with no fuel other than that added) if it was not already enabled.
+    pub fn add_fuel(&mut self, fuel: u64) {
+        self.fuel = Some(self.fuel.unwrap_or(0).saturating_add(fuel));
+    }
+
+    /// Disables metering, allowing execution to run without limit.
+    pub fn remove_fuel_limit(&mut self) {
+        self.fuel = None;
+    }
+
+    /// Sets the function used to determine how much fuel each instruction consumes.
+    pub fn set_fuel_cost(&mut self, fuel_cost: fn(&Instruction) -> u64) {
+        self.fuel_cost = fuel_cost;
+    }
+
+    /// Gets the function used to determine how much fuel each instruction consumes.
+    pub fn fuel_cost(&self) -> fn(&Instruction) -> u64 {
+        self.fuel_cost
+    }
+
+    /// Consumes the specified amount of fuel, if execution is metered.
+    ///
+    /// This is used by the interpreter for each instruction, and may also be used by host
+    /// functions to charge for the work they do. If there is not enough fuel left, no fuel is
+    /// consumed and [`TrapCause::OutOfFuel`] is returned.
+    pub fn consume_fuel(&mut self, amount: u64) -> Result<(), TrapCause> {
+        match self.fuel {
+            Some(fuel) if fuel < amount => Err(TrapCause::OutOfFuel),
+            Some(fuel) => {
+                self.fuel = Some(fuel - amount);
+                Ok(())
+            }
+            None => Ok(()),
         }
     }
 
+    /// Gets the engine used to run local functions.
+    pub fn engine(&self) -> Engine {
+        self.engine
+    }
+
+    /// Sets the engine used to run local functions invoked from now on.
+    ///
+    /// Expressions evaluated by [`Thread::eval`] always run on [`Engine::Stack`].
+    pub fn set_engine(&mut self, engine: Engine) {
+        self.engine = engine;
+    }
+
     pub fn stack(&self) -> &ExecutionStack {
         &self.stack
     }
 
+    /// Gets the number of frames on the thread, counting the frames of both engines.
+    ///
+    /// Entering a function traps with [`TrapCause::CallStackExhausted`] once this reaches the
+    /// stack's [maximum depth](ExecutionStack::max_depth), including when a host function calls
+    /// back into the thread.
+    pub fn depth(&self) -> usize {
+        self.stack.depth() + self.slot_frames
+    }
+
+    /// Records that the slot engine entered a frame.
+    pub(crate) fn enter_slot_frame(&mut self) {
+        self.slot_frames += 1;
+    }
+
+    /// Records that the slot engine exited `count` frames.
+    pub(crate) fn exit_slot_frames(&mut self, count: usize) {
+        self.slot_frames -= count;
+    }
+
     pub fn stack_mut(&mut self) -> &mut ExecutionStack {
         &mut self.stack
     }
 
+    /// Gets a boolean indicating if an invocation ran out of fuel and can be resumed.
+    pub fn is_suspended(&self) -> bool {
+        self.suspended.is_some()
+    }
+
+    /// Resumes the invocation that was suspended when the thread ran out of fuel.
+    ///
+    /// Returns the results of the function originally invoked, once it completes.
+    pub fn resume(&mut self, host: &mut Host) -> Result<Vec<Value>, Trap> {
+        match self.suspended.take() {
+            Some(invocation) => self.run_invocation(host, invocation),
+            None => Err(self.throw("There is no suspended invocation to resume")),
+        }
+    }
+
     /// Evaluates the expression specified by [`expr`] in the context of the provided module
     pub fn eval(
         &mut self,
@@ -31,25 +177,34 @@ impl Thread {
         expr: &Expr,
         host: &mut Host,
     ) -> Result<Value, Trap> {
+        self.discard_suspended();
+        let base = self.stack.depth();
+
         // Push a stack frame
-        self.stack.enter(module, None, Vec::new());
+        let ops = match interp::compile(expr.instructions(), &[]) {
+            Ok(ops) => ops,
+            Err(e) => return Err(self.throw(format!("Invalid expression: {:?}", e))),
+        };
+        let code = Code::Expr(expr.instructions().into(), ops.into());
+        self.stack
+            .enter(module, None, code, Vec::new())
+            .map_err(|e| self.throw(e))?;
 
         // Evaluate the expression
-        let val = match self.run(host, expr.instructions()) {
-            Ok(()) => self.pop()?,
-            Err(e) => {
-                self.stack.exit();
-                return Err(e);
-            }
-        };
+        self.running += 1;
+        let res = self.run(host, base + 1);
+        self.running -= 1;
 
-        let result = if !self.stack.current().is_empty() {
-            Err(self.throw(TrapCause::StackNotEmpty))
-        } else {
-            Ok(val)
+        let result = match res {
+            Ok(()) => match self.stack.current_mut().pop() {
+                Some(val) if self.stack.current().is_empty() => Ok(val),
+                Some(_) => Err(self.throw(TrapCause::StackNotEmpty)),
+                None => Err(self.throw(TrapCause::StackUnderflow)),
+            },
+            Err(Stop::OutOfFuel(trap)) | Err(Stop::Trap(trap)) => Err(trap),
         };
 
-        self.stack.exit();
+        self.stack.unwind(base);
         result
     }
 
@@ -63,106 +218,285 @@ impl Thread {
         host: &mut Host,
         module: ModuleAddr,
         func: FuncAddr,
-        mut values: Vec<Value>,
+        values: Vec<Value>,
     ) -> Result<Vec<Value>, Trap> {
-        self.stack_mut().enter(module, None, Vec::new());
+        self.discard_suspended();
+        let base = self.stack.depth();
+
+        self.stack
+            .enter(module, None, Code::None, Vec::new())
+            .map_err(|e| self.throw(e))?;
 
         // Push the values on to the stack
-        for value in values.drain(..).rev() {
+        for value in values {
             self.push(value);
         }
 
-        let res = self.invoke(host, func);
-
-        self.stack_mut().exit();
-
-        res
+        self.start(host, func, base)
     }
 
     /// Runs the function specified by [`func`] in the context of this thread.
+    ///
+    /// The function's parameters are popped off the operand stack of the current frame.
     pub fn invoke(&mut self, host: &mut Host, func: FuncAddr) -> Result<Vec<Value>, Trap> {
-        // Resolve the function
+        self.discard_suspended();
+        let base = self.stack.depth();
+        self.start(host, func, base)
+    }
+
+    /// Calls the specified function from WebAssembly code running in the current frame.
+    ///
+    /// Local functions enter a new frame, which the run loop picks up. External functions run
+    /// immediately and their results are pushed on to the operand stack of the current frame.
+    pub(crate) fn enter_func(&mut self, host: &mut Host, func: FuncAddr) -> Result<(), Trap> {
         let func_inst = host.get_func(func);
         match func_inst.imp() {
-            FuncImpl::External(synth_fn) => synth_fn.invoke(host, self).map_err(|e| self.throw(e)),
-            FuncImpl::Local(code, _) => {
-                // Pop parameters
-                let mut locals =
-                    Vec::with_capacity(func_inst.typ().params().len() + code.locals().len());
-                for param in func_inst.typ().params() {
-                    if let Some(val) = self.stack.current_mut().pop() {
-                        if val.typ() != *param {
-                            return Err(self.throw(format!(
-                                "Type mismatch. Expected: {}, Actual: {}",
-                                param,
-                                val.typ()
-                            )));
-                        }
-                        locals.push(val);
-                    } else {
-                        return Err(self.throw("Stack underflow!"));
-                    }
+            FuncImpl::External(_) => {
+                for value in self.invoke_external(host, &func_inst)? {
+                    self.push(value);
                 }
+                Ok(())
+            }
+            FuncImpl::Local(..) => self.enter_local(&func_inst, func),
+        }
+    }
 
-                // Initialize locals
-                for local in code.locals() {
-                    let v = match local {
-                        ValType::Nil => unreachable!(),
-                        ValType::I32 => Value::I32(0),
-                        ValType::I64 => Value::I64(0),
-                        ValType::F32 => Value::F32(0.0),
-                        ValType::F64 => Value::F64(0.0),
-                    };
-                    locals.push(v);
-                }
+    /// Runs an external function, popping its parameters off the current frame and checking its
+    /// results against its type.
+    fn invoke_external(
+        &mut self,
+        host: &mut Host,
+        func_inst: &Arc<FuncInst>,
+    ) -> Result<Vec<Value>, Trap> {
+        let external = match func_inst.imp() {
+            FuncImpl::External(external) => external,
+            FuncImpl::Local(..) => unreachable!("not an external function"),
+        };
+
+        let results = external.invoke(host, self)?;
+        if !results
+            .iter()
+            .map(Value::typ)
+            .eq(func_inst.typ().results().iter().cloned())
+        {
+            return Err(format!(
+                "Type mismatch. Function '{}' returned unexpected results.",
+                external.name()
+            )
+            .into());
+        }
+        Ok(results)
+    }
 
-                self.stack
-                    .enter(func_inst.module().clone(), Some(func), locals);
-                if let Err(e) = self.run(host, code.body()) {
-                    self.stack.exit();
-                    return Err(e);
+    /// Starts an invocation of the specified function, unwinding the stack to `base` once it ends.
+    fn start(&mut self, host: &mut Host, func: FuncAddr, base: usize) -> Result<Vec<Value>, Trap> {
+        let func_inst = host.get_func(func);
+        let result = match func_inst.imp() {
+            FuncImpl::External(_) => self
+                .invoke_external(host, &func_inst)
+                .map_err(|e| self.throw(e)),
+            FuncImpl::Local(..) if self.engine == Engine::Slots => {
+                self.invoke_slots(host, &func_inst, func)
+            }
+            FuncImpl::Local(..) => match self.enter_local(&func_inst, func) {
+                Ok(()) => {
+                    let frame = self.stack.depth();
+                    return self.run_invocation(host, Invocation { base, frame });
                 }
+                Err(e) => Err(e),
+            },
+        };
+        self.stack.unwind(base);
+        result
+    }
+
+    /// Runs a local function on the slot engine, popping its parameters off the current frame.
+    fn invoke_slots(
+        &mut self,
+        host: &mut Host,
+        func_inst: &Arc<FuncInst>,
+        func: FuncAddr,
+    ) -> Result<Vec<Value>, Trap> {
+        // Host functions called by the function may call back into the thread, which then uses a new stack
+        let mut slots = mem::replace(&mut self.slots, SlotStack::new());
+        self.running += 1;
+        let result = slots.invoke(self, host, func_inst, func);
+        self.running -= 1;
+        self.slots = slots;
+        result
+    }
 
-                // Pop the result
-                // In WASM v1, there is only zero or one result.
-                let mut results = Vec::with_capacity(func_inst.typ().results().len());
-                for result in func_inst.typ().results() {
-                    if let Some(val) = self.stack.current_mut().pop() {
-                        if val.typ() != *result {
-                            return Err(self.throw(format!(
-                                "Type mismatch. Expected: {}, Actual: {}",
-                                result,
-                                val.typ()
-                            )));
-                        }
-                        results.push(val);
-                    } else {
-                        return Err(self.throw("Stack underflow!"));
+    /// Runs an invocation until it completes or traps.
+    ///
+    /// If the outermost invocation on the thread runs out of fuel, it is suspended (leaving its
+    /// frames on the stack) so that it can be resumed. Otherwise, the stack is unwound.
+    fn run_invocation(
+        &mut self,
+        host: &mut Host,
+        invocation: Invocation,
+    ) -> Result<Vec<Value>, Trap> {
+        self.running += 1;
+        let res = self.run(host, invocation.frame);
+        self.running -= 1;
+
+        let result = match res {
+            Ok(()) => self.exit_local(),
+            Err(Stop::OutOfFuel(trap)) if self.running == 0 => {
+                self.suspended = Some(invocation);
+                return Err(trap);
+            }
+            Err(Stop::OutOfFuel(trap)) | Err(Stop::Trap(trap)) => Err(trap),
+        };
+        self.stack.unwind(invocation.base);
+        result
+    }
+
+    /// Discards the suspended invocation, if any, unless invocations are still running.
+    fn discard_suspended(&mut self) {
+        if self.running == 0 {
+            if let Some(invocation) = self.suspended.take() {
+                self.stack.unwind(invocation.base);
+            }
+        }
+    }
+
+    /// Runs instructions until the frame at the specified depth runs out of instructions.
+    ///
+    /// When any other frame runs out of instructions, its function returns to the caller.
+    fn run(&mut self, host: &mut Host, frame: usize) -> Result<(), Stop> {
+        let mut code = self.stack.current().code().clone();
+        loop {
+            let pc = self.stack.current().pc();
+            let op = match code.ops().get(pc) {
+                Some(op) => op,
+                None if self.stack.depth() <= frame => return Ok(()),
+                None => {
+                    // Return from the current function, pushing the results on to the caller's stack
+                    for value in self.exit_local().map_err(Stop::Trap)? {
+                        self.push(value);
                     }
+                    code = self.stack.current().code().clone();
+                    continue;
                 }
+            };
 
-                // Validate that the stack is empty
-                let result = if !self.stack.current().is_empty() {
-                    Err(self.throw(TrapCause::StackNotEmpty))
-                } else {
-                    Ok(results)
-                };
+            // Fuel is charged for each instruction, so fused sequences run one instruction at a time
+            let op = match *op {
+                Op::Fused(ref fused) if self.fuel.is_some() => fused.first(),
+                ref op => op,
+            };
+            if self.fuel.is_some() {
+                let cost = (self.fuel_cost)(&code.instructions()[pc]);
+                if let Err(e) = self.consume_fuel(cost) {
+                    return Err(Stop::OutOfFuel(self.throw(e)));
+                }
+            }
 
-                // Exit the stack frame
-                self.stack.exit();
+            // Advance before executing so control instructions can redirect the program counter
+            self.stack.current_mut().advance();
+            let depth = self.stack.depth();
+            self.execute(host, op).map_err(Stop::Trap)?;
 
-                result
+            // Calls enter a new frame, so continue with its code
+            if self.stack.depth() != depth {
+                code = self.stack.current().code().clone();
             }
         }
     }
 
-    pub fn run(&mut self, host: &mut Host, code: &[Instruction]) -> Result<(), Trap> {
-        for inst in code {
-            self.execute(host, inst.clone())?;
+    /// Enters a new frame for a local function, popping its parameters off the current frame.
+    fn enter_local(&mut self, func_inst: &Arc<FuncInst>, func: FuncAddr) -> Result<(), Trap> {
+        let body = match func_inst.imp() {
+            FuncImpl::Local(body, _, _) => body,
+            FuncImpl::External(_) => unreachable!("not a local function"),
+        };
+
+        // Pop parameters, the last parameter is on top of the stack
+        let params = func_inst.typ().params();
+        let mut locals = Vec::with_capacity(params.len() + body.locals().len());
+        for param in params.iter().rev() {
+            let val = self.pop()?;
+            if val.typ() != *param {
+                return Err(self.throw(format!(
+                    "Type mismatch. Expected: {}, Actual: {}",
+                    param,
+                    val.typ()
+                )));
+            }
+            locals.push(val);
+        }
+        locals.reverse();
+        if self.depth() >= self.stack.max_depth() {
+            return Err(self.throw(TrapCause::CallStackExhausted));
+        }
+
+        // Initialize locals
+        for local in body.locals() {
+            let v = match local {
+                ValType::Nil => unreachable!(),
+                ValType::I32 => Value::I32(0),
+                ValType::I64 => Value::I64(0),
+                ValType::F32 => Value::F32(0.0),
+                ValType::F64 => Value::F64(0.0),
+            };
+            locals.push(v);
         }
+
+        self.stack
+            .enter(
+                func_inst.module(),
+                Some(func),
+                Code::Func(func_inst.clone()),
+                locals,
+            )
+            .map_err(|e| self.throw(e))?;
+
+        // The function body is an implicit block, branching to it returns from the function.
+        self.stack.current_mut().push_label(Label::new(
+            func_inst.typ().results().len(),
+            0,
+            body.body().len(),
+        ));
         Ok(())
     }
 
+    /// Pops the results of the local function in the current frame, then exits the frame.
+    fn exit_local(&mut self) -> Result<Vec<Value>, Trap> {
+        let result = self.pop_results();
+        self.stack.exit();
+        result
+    }
+
+    /// Pops the results of the local function in the current frame, checking their types and
+    /// that nothing else is left on the stack.
+    fn pop_results(&mut self) -> Result<Vec<Value>, Trap> {
+        let func_inst = match self.stack.current().code() {
+            Code::Func(func_inst) => func_inst.clone(),
+            _ => unreachable!("not a local function frame"),
+        };
+
+        // Pop the results, the last result is on top of the stack
+        let mut results = Vec::with_capacity(func_inst.typ().results().len());
+        for result in func_inst.typ().results().iter().rev() {
+            let val = self.pop()?;
+            if val.typ() != *result {
+                return Err(self.throw(format!(
+                    "Type mismatch. Expected: {}, Actual: {}",
+                    result,
+                    val.typ()
+                )));
+            }
+            results.push(val);
+        }
+        results.reverse();
+
+        // Validate that the stack is empty
+        if !self.stack.current().is_empty() {
+            return Err(self.throw(TrapCause::StackNotEmpty));
+        }
+        Ok(results)
+    }
+
     /// Tries to pop a value off the stack for the current frame, traps if there is no current value.
     pub fn pop(&mut self) -> Result<Value, Trap> {
         match self.stack.current_mut().pop() {
@@ -175,8 +509,8 @@ impl Thread {
         self.stack.current_mut().push(v)
     }
 
-    fn execute(&mut self, host: &mut Host, inst: Instruction) -> Result<(), Trap> {
-        exec::execute(self, host, inst).map_err(|e| self.throw(e))
+    fn execute(&mut self, host: &mut Host, op: &Op) -> Result<(), Trap> {
+        exec::execute(self, host, op).map_err(|e| self.throw(e))
     }
 
     /// Creates a new [`Trap`], capturing the current stack frame.
@@ -186,3 +520,294 @@ impl Thread {
         trap
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        hosting::{ExternVal, ExternalFunc},
+        interp::exec::tests::{host, instantiate_text, Funcs},
+        module::FuncType,
+    };
+
+    const COUNTER: &str = r#"
+        (func (export "test") (param i32) (result i32)
+            (local.get 0)
+            (i32.const 1)
+            (i32.add))"#;
+
+    #[test]
+    pub fn host_function_results_are_checked() {
+        for engine in &[Engine::Stack, Engine::Slots] {
+            let mut host = host();
+            host.external(Funcs {
+                name: "bad",
+                funcs: vec![Arc::new(ExternalFunc::new(
+                    "f",
+                    FuncType::new(vec![], vec![ValType::I32]),
+                    |_host: &mut Host, _thread: &mut Thread, _values: &[Value]| {
+                        Ok(vec![Value::I64(1)])
+                    },
+                ))],
+            })
+            .unwrap();
+            let text = r#"
+                (import "bad" "f" (func $f (result i32)))
+                (export "f" (func $f))
+                (func (export "test") (result i32) (call $f))"#;
+            let instance = host
+                .instantiate("test", crate::text::parse_module(text).unwrap())
+                .unwrap();
+
+            for name in &["test", "f"] {
+                let func = instance.get_func(&host, name).unwrap();
+                let mut thread = Thread::new();
+                thread.set_engine(*engine);
+                let trap = func.call_on(&mut thread, &mut host, &[]).unwrap_err();
+                assert_eq!(
+                    "Type mismatch. Function 'f' returned unexpected results.",
+                    trap.cause().to_string()
+                );
+                assert_eq!(0, thread.stack().depth());
+            }
+        }
+    }
+
+    #[test]
+    pub fn call_depth_is_shared_by_host_callbacks() {
+        /// Calls the module's 'test' export, counting down to zero through the host.
+        fn reenter(
+            host: &mut Host,
+            thread: &mut Thread,
+            values: &[Value],
+        ) -> Result<Vec<Value>, Trap> {
+            let module_addr = host.find_module("test").unwrap();
+            let func_addr = match host.resolve_import(module_addr, "test").unwrap().value() {
+                ExternVal::Func(f) => *f,
+                _ => panic!("'test' is not a function!"),
+            };
+            thread.call(host, module_addr, func_addr, values.to_vec())
+        }
+
+        for engine in &[Engine::Stack, Engine::Slots] {
+            let mut host = host();
+            host.external(Funcs {
+                name: "host",
+                funcs: vec![Arc::new(ExternalFunc::new(
+                    "reenter",
+                    FuncType::new(vec![ValType::I32], vec![ValType::I32]),
+                    reenter,
+                ))],
+            })
+            .unwrap();
+            let text = r#"
+                (import "host" "reenter" (func $reenter (param i32) (result i32)))
+                (func (export "test") (param i32) (result i32)
+                    (if (result i32) (local.get 0)
+                        (then (call $reenter (i32.sub (local.get 0) (i32.const 1))))
+                        (else (i32.const 0))))"#;
+            host.instantiate("test", crate::text::parse_module(text).unwrap())
+                .unwrap();
+            let mut thread = Thread::new();
+            thread.set_engine(*engine);
+
+            // Each call from the host enters a frame for the caller and one for the function
+            thread.stack_mut().set_max_depth(10);
+            assert_eq!(
+                Ok(vec![Value::I32(0)]),
+                reenter(&mut host, &mut thread, &[Value::I32(4)])
+            );
+            let trap = reenter(&mut host, &mut thread, &[Value::I32(5)]).unwrap_err();
+            assert!(*trap.cause() == TrapCause::CallStackExhausted);
+            assert_eq!(0, thread.depth());
+        }
+    }
+
+    #[test]
+    pub fn unmetered_thread_does_not_consume_fuel() {
+        let (mut host, module_addr, func_addr) = instantiate_text(COUNTER);
+        let mut thread = Thread::new();
+        assert_eq!(
+            Ok(vec![Value::I32(2)]),
+            thread.call(&mut host, module_addr, func_addr, vec![Value::I32(1)])
+        );
+        assert_eq!(None, thread.fuel());
+    }
+
+    #[test]
+    pub fn each_instruction_consumes_fuel() {
+        let (mut host, module_addr, func_addr) = instantiate_text(COUNTER);
+        let mut thread = Thread::new();
+        thread.set_fuel(10);
+        assert_eq!(
+            Ok(vec![Value::I32(2)]),
+            thread.call(&mut host, module_addr, func_addr, vec![Value::I32(1)])
+        );
+        assert_eq!(Some(7), thread.fuel());
+    }
+
+    #[test]
+    pub fn running_out_of_fuel_traps_until_fuel_is_added() {
+        let (mut host, module_addr, func_addr) = instantiate_text(COUNTER);
+        let mut thread = Thread::new();
+        thread.set_fuel(2);
+        let trap = thread
+            .call(&mut host, module_addr, func_addr, vec![Value::I32(1)])
+            .unwrap_err();
+        assert!(*trap.cause() == TrapCause::OutOfFuel);
+        assert_eq!(Some(0), thread.fuel());
+
+        thread.add_fuel(3);
+        assert_eq!(
+            Ok(vec![Value::I32(2)]),
+            thread.call(&mut host, module_addr, func_addr, vec![Value::I32(1)])
+        );
+        assert_eq!(Some(0), thread.fuel());
+    }
+
+    #[test]
+    pub fn fuel_cost_can_be_customized() {
+        fn cost(inst: &Instruction) -> u64 {
+            match inst {
+                Instruction::I32Add => 5,
+                _ => 0,
+            }
+        }
+
+        let (mut host, module_addr, func_addr) = instantiate_text(COUNTER);
+        let mut thread = Thread::new();
+        thread.set_fuel(12);
+        thread.set_fuel_cost(cost);
+        assert!(thread
+            .call(&mut host, module_addr, func_addr, vec![Value::I32(1)])
+            .is_ok());
+        assert_eq!(Some(7), thread.fuel());
+    }
+
+    #[test]
+    pub fn host_functions_can_consume_fuel() {
+        let (mut host, module_addr, func_addr) = instantiate_text(
+            r#"
+            (import "metered" "work" (func $work))
+            (func (export "test") (call $work))"#,
+        );
+        let mut thread = Thread::new();
+        thread.set_fuel(11);
+        assert_eq!(
+            Ok(vec![]),
+            thread.call(&mut host, module_addr, func_addr, vec![])
+        );
+        assert_eq!(Some(0), thread.fuel());
+
+        thread.set_fuel(10);
+        let trap = thread
+            .call(&mut host, module_addr, func_addr, vec![])
+            .unwrap_err();
+        assert!(*trap.cause() == TrapCause::OutOfFuel);
+    }
+
+    #[test]
+    pub fn call_depth_is_limited() {
+        let (mut host, module_addr, func_addr) = instantiate_text(
+            r#"
+            (func $count (export "test") (param i32) (result i32)
+                (if (result i32) (local.get 0)
+                    (then (call $count (i32.sub (local.get 0) (i32.const 1))))
+                    (else (i32.const 0))))"#,
+        );
+        let mut thread = Thread::new();
+
+        // The call itself enters a frame for the caller, so 10 frames allow 9 nested calls
+        thread.stack_mut().set_max_depth(10);
+        assert_eq!(
+            Ok(vec![Value::I32(0)]),
+            thread.call(&mut host, module_addr, func_addr, vec![Value::I32(8)])
+        );
+        let trap = thread
+            .call(&mut host, module_addr, func_addr, vec![Value::I32(9)])
+            .unwrap_err();
+        assert!(*trap.cause() == TrapCause::CallStackExhausted);
+        assert_eq!(0, thread.stack().depth());
+    }
+
+    #[test]
+    pub fn call_passes_arguments_in_order() {
+        let (mut host, module_addr, func_addr) = instantiate_text(
+            r#"
+            (func $sub (param i32 i64) (result i32)
+                (i32.sub (local.get 0) (i32.wrap_i64 (local.get 1))))
+            (func (export "test") (param i32) (result i32)
+                (call $sub (local.get 0) (i64.const 3)))"#,
+        );
+        assert_eq!(
+            Ok(vec![Value::I32(7)]),
+            Thread::new().call(&mut host, module_addr, func_addr, vec![Value::I32(10)])
+        );
+    }
+
+    #[test]
+    pub fn deep_recursion_does_not_use_native_stack() {
+        let (mut host, module_addr, func_addr) = instantiate_text(
+            r#"
+            (func $count (export "test") (param i32) (result i32)
+                (if (result i32) (local.get 0)
+                    (then (i32.add
+                        (call $count (i32.sub (local.get 0) (i32.const 1)))
+                        (i32.const 1)))
+                    (else (i32.const 0))))"#,
+        );
+        assert_eq!(
+            Ok(vec![Value::I32(10000)]),
+            Thread::new().call(&mut host, module_addr, func_addr, vec![Value::I32(10000)])
+        );
+    }
+
+    #[test]
+    pub fn suspended_invocation_resumes_where_it_ran_out_of_fuel() {
+        let (mut host, module_addr, func_addr) = instantiate_text(
+            r#"
+            (func $inc (param i32) (result i32) (i32.add (local.get 0) (i32.const 1)))
+            (func (export "test") (param i32) (result i32)
+                (call $inc (call $inc (local.get 0))))"#,
+        );
+        let mut thread = Thread::new();
+        thread.set_fuel(4);
+        let trap = thread
+            .call(&mut host, module_addr, func_addr, vec![Value::I32(1)])
+            .unwrap_err();
+        assert!(*trap.cause() == TrapCause::OutOfFuel);
+        assert!(thread.is_suspended());
+
+        // The first call to $inc is on the stack, waiting to run 'i32.add'
+        assert_eq!(3, thread.stack().depth());
+        let mut results = Vec::new();
+        while thread.is_suspended() {
+            thread.add_fuel(1);
+            results.push(thread.resume(&mut host));
+        }
+        assert_eq!(Some(&Ok(vec![Value::I32(3)])), results.last());
+        assert_eq!(5, results.len());
+        assert_eq!(0, thread.stack().depth());
+    }
+
+    #[test]
+    pub fn resume_fails_without_suspended_invocation() {
+        let (mut host, module_addr, func_addr) = instantiate_text(COUNTER);
+        let mut thread = Thread::new();
+        assert!(thread.resume(&mut host).is_err());
+
+        // Starting a new call discards a suspended invocation
+        thread.set_fuel(1);
+        assert!(thread
+            .call(&mut host, module_addr, func_addr, vec![Value::I32(1)])
+            .is_err());
+        assert!(thread.is_suspended());
+        thread.remove_fuel_limit();
+        assert_eq!(
+            Ok(vec![Value::I32(6)]),
+            thread.call(&mut host, module_addr, func_addr, vec![Value::I32(5)])
+        );
+        assert!(!thread.is_suspended());
+        assert_eq!(0, thread.stack().depth());
+    }
+}
@@ -27,13 +27,16 @@ pub mod interp;
 pub mod module;
 pub mod reader;
 pub mod runtime;
+pub mod text;
+pub mod wast;
+pub mod writer;
 
 pub use crate::error::Error;
-pub use crate::instruction::Instruction;
+pub use crate::instruction::{BranchTable, Instruction};
 pub use crate::location::Location;
 pub use crate::memory::Memory;
 pub use crate::trap::{Trap, TrapCause};
-pub use crate::value::{FromValue, ValType, Value};
+pub use crate::value::{FromValue, ValType, Value, WasmType, WasmValues};
 
 pub(crate) use crate::sparse_vec::SparseVec;
 
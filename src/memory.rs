@@ -1,39 +1,97 @@
-use std::{
-    alloc::{self, Layout},
-    mem, slice,
-};
+use crate::{error::Error, PAGE_SIZE};
 
-use crate::error::Error;
+/// The maximum number of pages a memory can have, if it doesn't specify a smaller maximum.
+pub const MAX_PAGES: usize = 65536;
 
 /// Represents a growable linear memory, with an optional maximum size
-///
-/// WebAssembly memory is inherently "unsafe" in Rust terms because the
-/// WebAssembly runtime doesn't expect the same safety guarantees. It's up to the
-/// WebAssembly program to ensure safety
-pub struct Memory(*mut u8, usize, Option<usize>);
+pub struct Memory {
+    data: Vec<u8>,
+    max_size: Option<usize>,
+}
 
 impl Memory {
     pub fn new(min_size: usize, max_size: Option<usize>) -> Result<Memory, Error> {
-        let layout = Layout::from_size_align(min_size, mem::align_of::<u8>())?;
-        unsafe {
-            let mem = alloc::alloc_zeroed(layout);
-            Ok(Memory(mem, min_size, max_size))
+        let mut data = Vec::new();
+        data.try_reserve_exact(min_size)
+            .map_err(|_| Error::LayoutError)?;
+        data.resize(min_size, 0);
+        Ok(Memory { data, max_size })
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn max_size(&self) -> Option<usize> {
+        self.max_size
+    }
+
+    /// Gets the current size of the memory, in pages.
+    pub fn pages(&self) -> usize {
+        self.data.len() / PAGE_SIZE
+    }
+
+    /// Grows the memory by the specified number of pages, zeroing the new pages.
+    ///
+    /// ## Returns
+    /// The previous size of the memory, in pages, or `None` if the memory could not be grown
+    /// because it would exceed the maximum size or the allocation failed.
+    pub fn grow(&mut self, pages: usize) -> Option<usize> {
+        let old_pages = self.pages();
+        let limit = self.max_size.unwrap_or(MAX_PAGES * PAGE_SIZE);
+        let additional = pages.checked_mul(PAGE_SIZE)?;
+        let new_size = self.data.len().checked_add(additional)?;
+        if new_size > limit {
+            return None;
         }
+
+        // Reserve up front so an allocation failure is reported rather than aborting
+        self.data.try_reserve_exact(additional).ok()?;
+        self.data.resize(new_size, 0);
+        Some(old_pages)
     }
 
-    pub fn ptr(&self) -> *mut u8 {
-        self.0
+    /// Gets the contents of the memory.
+    pub fn bytes(&self) -> &[u8] {
+        &self.data
     }
 
-    pub fn len(&self) -> usize {
-        self.1
+    /// Gets the contents of the memory, for writing.
+    pub fn bytes_mut(&mut self) -> &mut [u8] {
+        &mut self.data
     }
+}
 
-    pub fn max_size(&self) -> Option<usize> {
-        self.2
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn grow_zeroes_new_pages() {
+        let mut mem = Memory::new(PAGE_SIZE, None).unwrap();
+        mem.bytes_mut()[PAGE_SIZE - 1] = 42;
+
+        assert_eq!(Some(1), mem.grow(2));
+        assert_eq!(3, mem.pages());
+        assert_eq!(42, mem.bytes()[PAGE_SIZE - 1]);
+        assert!(mem.bytes()[PAGE_SIZE..].iter().all(|b| *b == 0));
     }
 
-    pub unsafe fn data(&self) -> &mut [u8] {
-        slice::from_raw_parts_mut(self.0, self.1)
+    #[test]
+    pub fn grow_respects_max_size() {
+        let mut mem = Memory::new(PAGE_SIZE, Some(2 * PAGE_SIZE)).unwrap();
+
+        assert_eq!(None, mem.grow(2));
+        assert_eq!(Some(1), mem.grow(1));
+        assert_eq!(None, mem.grow(1));
+        assert_eq!(Some(2), mem.grow(0));
+    }
+
+    #[test]
+    pub fn grow_is_limited_to_max_pages_without_max_size() {
+        let mut mem = Memory::new(0, None).unwrap();
+
+        assert_eq!(None, mem.grow(MAX_PAGES + 1));
+        assert_eq!(0, mem.len());
     }
 }
@@ -12,6 +12,10 @@ pub struct DataItem {
 }
 
 impl DataItem {
+    pub fn new(index: usize, expr: Expr, init: Vec<u8>) -> DataItem {
+        DataItem { index, expr, init }
+    }
+
     pub fn read<R: io::Read>(reader: &mut R) -> Result<DataItem, Error> {
         let index = utils::read_leb128_u32(reader)? as usize;
         let expr = Expr::new(Instruction::read_sequence(reader)?);
@@ -19,6 +23,14 @@ impl DataItem {
         Ok(DataItem { index, expr, init })
     }
 
+    pub fn write<W: io::Write>(&self, writer: &mut W) -> Result<(), Error> {
+        utils::write_leb128_u32(writer, self.index as u32)?;
+        Instruction::write_sequence(self.expr.instructions(), writer)?;
+        utils::write_leb128_u32(writer, self.init.len() as u32)?;
+        writer.write_all(&self.init)?;
+        Ok(())
+    }
+
     pub fn index(&self) -> usize {
         self.index
     }
@@ -0,0 +1,67 @@
+use std::{fmt, io};
+
+use crate::{module::Expr, utils, Error, Instruction};
+
+/// Represents an element segment, used to initialize a range of a table with function indices.
+#[derive(PartialEq, Clone)]
+pub struct ElemItem {
+    index: usize,
+    expr: Expr,
+    init: Vec<usize>,
+}
+
+impl ElemItem {
+    pub fn new(index: usize, expr: Expr, init: Vec<usize>) -> ElemItem {
+        ElemItem { index, expr, init }
+    }
+
+    pub fn read<R: io::Read>(reader: &mut R) -> Result<ElemItem, Error> {
+        let index = utils::read_leb128_u32(reader)? as usize;
+        let expr = Expr::new(Instruction::read_sequence(reader)?);
+        let init = utils::read_vec(reader, |r| Ok(utils::read_leb128_u32(r)? as usize))?;
+        Ok(ElemItem { index, expr, init })
+    }
+
+    pub fn write<W: io::Write>(&self, writer: &mut W) -> Result<(), Error> {
+        utils::write_leb128_u32(writer, self.index as u32)?;
+        Instruction::write_sequence(self.expr.instructions(), writer)?;
+        utils::write_vec(writer, &self.init, |w, func_idx| {
+            utils::write_leb128_u32(w, *func_idx as u32)
+        })
+    }
+
+    /// Gets the index of the table initialized by this segment.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Gets the constant expression used to compute the offset into the table.
+    pub fn expr(&self) -> &Expr {
+        &self.expr
+    }
+
+    /// Gets the function indices to store in the table.
+    pub fn init(&self) -> &[usize] {
+        &self.init
+    }
+}
+
+impl fmt::Display for ElemItem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "(elem")?;
+        if self.index > 0 {
+            write!(f, " {}", self.index)?;
+        }
+        write!(f, " {}", self.expr)?;
+        for func_idx in self.init.iter() {
+            write!(f, " {}", func_idx)?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl fmt::Debug for ElemItem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
@@ -1,26 +1,86 @@
 use std::{fmt, io};
 
-use crate::{
-    module::{MemberDesc, MemoryType},
-    utils, Error,
-};
+use byteorder::{ReadBytesExt, WriteBytesExt};
+
+use crate::{utils, Error};
+
+/// Describes the member of the module referenced by an [`Export`].
+///
+/// Unlike an import, an export refers to a member of the module by its index.
+#[derive(PartialEq, Clone)]
+pub enum ExportDesc {
+    Function(usize),
+    Table(usize),
+    Memory(usize),
+    Global(usize),
+}
+
+impl ExportDesc {
+    pub fn read<R: io::Read>(reader: &mut R) -> Result<ExportDesc, Error> {
+        let code = reader.read_u8()?;
+        let idx = utils::read_leb128_u32(reader)? as usize;
+        match code {
+            0x00 => Ok(ExportDesc::Function(idx)),
+            0x01 => Ok(ExportDesc::Table(idx)),
+            0x02 => Ok(ExportDesc::Memory(idx)),
+            0x03 => Ok(ExportDesc::Global(idx)),
+            _ => Err(Error::InvalidModule),
+        }
+    }
+
+    pub fn write<W: io::Write>(&self, writer: &mut W) -> Result<(), Error> {
+        let (code, idx) = match self {
+            ExportDesc::Function(idx) => (0x00, idx),
+            ExportDesc::Table(idx) => (0x01, idx),
+            ExportDesc::Memory(idx) => (0x02, idx),
+            ExportDesc::Global(idx) => (0x03, idx),
+        };
+        writer.write_u8(code)?;
+        utils::write_leb128_u32(writer, *idx as u32)
+    }
+}
+
+impl fmt::Display for ExportDesc {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExportDesc::Function(x) => write!(f, "(func {})", x),
+            ExportDesc::Table(x) => write!(f, "(table {})", x),
+            ExportDesc::Memory(x) => write!(f, "(memory {})", x),
+            ExportDesc::Global(x) => write!(f, "(global {})", x),
+        }
+    }
+}
+
+impl fmt::Debug for ExportDesc {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
 
 #[derive(PartialEq, Clone)]
 pub struct Export {
     name: String,
-    description: MemberDesc,
+    description: ExportDesc,
 }
 
 impl Export {
     pub fn func<S: Into<String>>(name: S, idx: usize) -> Export {
-        Export::new(name, MemberDesc::Function(idx))
+        Export::new(name, ExportDesc::Function(idx))
     }
 
-    pub fn mem<S: Into<String>>(name: S, typ: MemoryType) -> Export {
-        Export::new(name, MemberDesc::Memory(typ))
+    pub fn table<S: Into<String>>(name: S, idx: usize) -> Export {
+        Export::new(name, ExportDesc::Table(idx))
     }
 
-    pub fn new<S: Into<String>>(name: S, description: MemberDesc) -> Export {
+    pub fn mem<S: Into<String>>(name: S, idx: usize) -> Export {
+        Export::new(name, ExportDesc::Memory(idx))
+    }
+
+    pub fn global<S: Into<String>>(name: S, idx: usize) -> Export {
+        Export::new(name, ExportDesc::Global(idx))
+    }
+
+    pub fn new<S: Into<String>>(name: S, description: ExportDesc) -> Export {
         Export {
             name: name.into(),
             description,
@@ -29,15 +89,20 @@ impl Export {
 
     pub fn read<R: io::Read>(reader: &mut R) -> Result<Export, Error> {
         let name = utils::read_name(reader)?;
-        let description = MemberDesc::read(reader)?;
+        let description = ExportDesc::read(reader)?;
         Ok(Export { name, description })
     }
 
+    pub fn write<W: io::Write>(&self, writer: &mut W) -> Result<(), Error> {
+        utils::write_name(writer, &self.name)?;
+        self.description.write(writer)
+    }
+
     pub fn name(&self) -> &str {
         &self.name
     }
 
-    pub fn description(&self) -> &MemberDesc {
+    pub fn description(&self) -> &ExportDesc {
         &self.description
     }
 }
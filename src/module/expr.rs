@@ -1,6 +1,9 @@
 use std::fmt;
 
-use crate::Instruction;
+use crate::{
+    module::{GlobalType, ValidationErrorKind},
+    Instruction, Value, WasmType,
+};
 
 #[derive(PartialEq, Clone)]
 pub struct Expr(Vec<Instruction>);
@@ -21,6 +24,61 @@ impl Expr {
     pub fn iter(&self) -> impl Iterator<Item = &Instruction> {
         self.0.iter()
     }
+
+    /// Evaluates the expression as a constant expression, producing its single value.
+    ///
+    /// Constant expressions consist of `*.const` instructions, `global.get` of immutable globals
+    /// and the integer `add`, `sub` and `mul` instructions of the extended-const proposal. The
+    /// `global` function provides the type and value of the global with the specified index.
+    pub fn eval_const<F>(&self, global: F) -> Result<Value, ValidationErrorKind>
+    where
+        F: Fn(usize) -> Option<(GlobalType, Value)>,
+    {
+        let mut stack = Vec::new();
+        for inst in self.iter() {
+            let value = match inst {
+                Instruction::I32Const(v)
+                | Instruction::I64Const(v)
+                | Instruction::F32Const(v)
+                | Instruction::F64Const(v) => *v,
+                Instruction::GlobalGet(idx) => match global(*idx as usize) {
+                    Some((typ, _)) if typ.mutable() => {
+                        return Err(ValidationErrorKind::ConstantExpressionRequired)
+                    }
+                    Some((_, value)) => value,
+                    None => return Err(ValidationErrorKind::UnknownGlobal),
+                },
+                Instruction::I32Add => binop(&mut stack, u32::wrapping_add)?,
+                Instruction::I32Sub => binop(&mut stack, u32::wrapping_sub)?,
+                Instruction::I32Mul => binop(&mut stack, u32::wrapping_mul)?,
+                Instruction::I64Add => binop(&mut stack, u64::wrapping_add)?,
+                Instruction::I64Sub => binop(&mut stack, u64::wrapping_sub)?,
+                Instruction::I64Mul => binop(&mut stack, u64::wrapping_mul)?,
+                _ => return Err(ValidationErrorKind::ConstantExpressionRequired),
+            };
+            stack.push(value);
+        }
+
+        match stack.as_slice() {
+            [value] => Ok(*value),
+            _ => Err(ValidationErrorKind::TypeMismatch),
+        }
+    }
+}
+
+fn binop<T: WasmType>(
+    stack: &mut Vec<Value>,
+    op: fn(T, T) -> T,
+) -> Result<Value, ValidationErrorKind> {
+    let mut pop = || {
+        stack
+            .pop()
+            .and_then(|v| T::from_value(v).ok())
+            .ok_or(ValidationErrorKind::TypeMismatch)
+    };
+    let rhs = pop()?;
+    let lhs = pop()?;
+    Ok(op(lhs, rhs).into())
 }
 
 impl fmt::Display for Expr {
@@ -42,3 +100,77 @@ impl fmt::Debug for Expr {
         fmt::Display::fmt(self, f)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Instruction::*, ValType};
+
+    /// Evaluates `insts` with an immutable i32 global, an immutable i64 global and a mutable i32
+    /// global.
+    fn eval(insts: Vec<Instruction>) -> Result<Value, ValidationErrorKind> {
+        Expr::new(insts).eval_const(|idx| match idx {
+            0 => Some((GlobalType::new(ValType::I32, false), Value::I32(666))),
+            1 => Some((GlobalType::new(ValType::I64, false), Value::I64(666))),
+            2 => Some((GlobalType::new(ValType::I32, true), Value::I32(1))),
+            _ => None,
+        })
+    }
+
+    #[test]
+    pub fn evaluates_constant_expressions() {
+        assert_eq!(Ok(Value::F32(1.5)), eval(vec![F32Const(Value::F32(1.5))]));
+        assert_eq!(Ok(Value::I64(666)), eval(vec![GlobalGet(1)]));
+        assert_eq!(
+            Ok(Value::I32(0)),
+            eval(vec![GlobalGet(0), I32Const(Value::from(-666)), I32Add])
+        );
+        assert_eq!(
+            Ok(Value::I64(1330)),
+            eval(vec![
+                GlobalGet(1),
+                I64Const(Value::I64(2)),
+                I64Mul,
+                I64Const(Value::I64(2)),
+                I64Sub,
+            ])
+        );
+        assert_eq!(
+            Ok(Value::from(i32::MIN)),
+            eval(vec![
+                I32Const(Value::from(i32::MAX)),
+                I32Const(Value::I32(1)),
+                I32Add,
+            ])
+        );
+    }
+
+    #[test]
+    pub fn invalid_constant_expressions_are_errors() {
+        assert_eq!(
+            Err(ValidationErrorKind::UnknownGlobal),
+            eval(vec![GlobalGet(100)])
+        );
+        assert_eq!(
+            Err(ValidationErrorKind::ConstantExpressionRequired),
+            eval(vec![GlobalGet(2)])
+        );
+        assert_eq!(
+            Err(ValidationErrorKind::ConstantExpressionRequired),
+            eval(vec![I32Const(Value::I32(1)), I32Eqz])
+        );
+        assert_eq!(
+            Err(ValidationErrorKind::TypeMismatch),
+            eval(vec![I32Const(Value::I32(1)), I32Add])
+        );
+        assert_eq!(
+            Err(ValidationErrorKind::TypeMismatch),
+            eval(vec![GlobalGet(0), GlobalGet(1), I64Add])
+        );
+        assert_eq!(
+            Err(ValidationErrorKind::TypeMismatch),
+            eval(vec![I32Const(Value::I32(1)), I32Const(Value::I32(2))])
+        );
+        assert_eq!(Err(ValidationErrorKind::TypeMismatch), eval(vec![]));
+    }
+}
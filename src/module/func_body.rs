@@ -33,6 +33,29 @@ impl FuncBody {
         Ok(FuncBody { locals, body })
     }
 
+    pub fn write<W: io::Write>(&self, writer: &mut W) -> Result<(), Error> {
+        // Locals are stored expanded, so group runs of the same type back together
+        let mut groups: Vec<(u32, ValType)> = Vec::new();
+        for local in self.locals.iter() {
+            match groups.last_mut() {
+                Some((count, typ)) if typ == local => *count += 1,
+                _ => groups.push((1, *local)),
+            }
+        }
+
+        // The body is prefixed with its size, so write it to a buffer first
+        let mut content = Vec::new();
+        utils::write_vec(&mut content, &groups, |w, (count, typ)| {
+            utils::write_leb128_u32(w, *count)?;
+            typ.write(w)
+        })?;
+        Instruction::write_sequence(&self.body, &mut content)?;
+
+        utils::write_leb128_u32(writer, content.len() as u32)?;
+        writer.write_all(&content)?;
+        Ok(())
+    }
+
     pub fn locals(&self) -> &[ValType] {
         &self.locals
     }
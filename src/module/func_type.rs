@@ -1,6 +1,6 @@
 use std::{fmt, io};
 
-use byteorder::ReadBytesExt;
+use byteorder::{ReadBytesExt, WriteBytesExt};
 
 use crate::{utils, Error, ValType};
 
@@ -33,6 +33,12 @@ impl FuncType {
         }
     }
 
+    pub fn write<W: io::Write>(&self, writer: &mut W) -> Result<(), Error> {
+        writer.write_u8(0x60)?;
+        utils::write_vec(writer, &self.params, |w, p| p.write(w))?;
+        utils::write_vec(writer, &self.results, |w, r| r.write(w))
+    }
+
     pub fn params(&self) -> &[ValType] {
         &self.params
     }
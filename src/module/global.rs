@@ -0,0 +1,57 @@
+use std::{fmt, io};
+
+use crate::{
+    module::{Expr, GlobalType},
+    Error, Instruction,
+};
+
+/// Represents a global variable defined by a module.
+#[derive(PartialEq, Clone)]
+pub struct Global {
+    typ: GlobalType,
+    init: Expr,
+}
+
+impl Global {
+    pub fn new(typ: GlobalType, init: Expr) -> Global {
+        Global { typ, init }
+    }
+
+    pub fn read<R: io::Read>(reader: &mut R) -> Result<Global, Error> {
+        let typ = GlobalType::read(reader)?;
+        let init = Expr::new(Instruction::read_sequence(reader)?);
+        Ok(Global { typ, init })
+    }
+
+    pub fn write<W: io::Write>(&self, writer: &mut W) -> Result<(), Error> {
+        self.typ.write(writer)?;
+        Instruction::write_sequence(self.init.instructions(), writer)
+    }
+
+    pub fn typ(&self) -> &GlobalType {
+        &self.typ
+    }
+
+    /// Gets the constant expression used to initialize the global.
+    pub fn init(&self) -> &Expr {
+        &self.init
+    }
+}
+
+impl fmt::Display for Global {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "(global ")?;
+        if self.typ.mutable() {
+            write!(f, "(mut {})", self.typ.typ())?;
+        } else {
+            write!(f, "{}", self.typ.typ())?;
+        }
+        write!(f, " {})", self.init)
+    }
+}
+
+impl fmt::Debug for Global {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
@@ -1,6 +1,6 @@
 use std::{fmt, io};
 
-use byteorder::ReadBytesExt;
+use byteorder::{ReadBytesExt, WriteBytesExt};
 
 use crate::{Error, ValType};
 
@@ -11,6 +11,10 @@ pub struct GlobalType {
 }
 
 impl GlobalType {
+    pub fn new(typ: ValType, mutable: bool) -> GlobalType {
+        GlobalType { typ, mutable }
+    }
+
     pub fn read<R: io::Read>(reader: &mut R) -> Result<GlobalType, Error> {
         let typ = ValType::read(reader)?;
         let mutable = match reader.read_u8()? {
@@ -21,6 +25,12 @@ impl GlobalType {
         Ok(GlobalType { typ, mutable })
     }
 
+    pub fn write<W: io::Write>(&self, writer: &mut W) -> Result<(), Error> {
+        self.typ.write(writer)?;
+        writer.write_u8(if self.mutable { 0x01 } else { 0x00 })?;
+        Ok(())
+    }
+
     pub fn typ(&self) -> ValType {
         self.typ
     }
@@ -21,6 +21,12 @@ impl Import {
         })
     }
 
+    pub fn write<W: io::Write>(&self, writer: &mut W) -> Result<(), Error> {
+        utils::write_name(writer, &self.module)?;
+        utils::write_name(writer, &self.name)?;
+        self.description.write(writer)
+    }
+
     pub fn new<S: Into<String>, T: Into<String>>(
         module: S,
         name: T,
@@ -1,6 +1,6 @@
 use std::{fmt, io};
 
-use byteorder::ReadBytesExt;
+use byteorder::{ReadBytesExt, WriteBytesExt};
 
 use crate::{
     module::{GlobalType, MemoryType, TableType},
@@ -29,6 +29,27 @@ impl MemberDesc {
             _ => Err(Error::InvalidModule),
         }
     }
+
+    pub fn write<W: io::Write>(&self, writer: &mut W) -> Result<(), Error> {
+        match self {
+            MemberDesc::Function(type_id) => {
+                writer.write_u8(0x00)?;
+                utils::write_leb128_u32(writer, *type_id as u32)
+            }
+            MemberDesc::Table(table) => {
+                writer.write_u8(0x01)?;
+                table.write(writer)
+            }
+            MemberDesc::Memory(mem) => {
+                writer.write_u8(0x02)?;
+                mem.write(writer)
+            }
+            MemberDesc::Global(global) => {
+                writer.write_u8(0x03)?;
+                global.write(writer)
+            }
+        }
+    }
 }
 
 impl fmt::Display for MemberDesc {
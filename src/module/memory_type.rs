@@ -18,6 +18,10 @@ impl MemoryType {
         Ok(MemoryType { min, max })
     }
 
+    pub fn write<W: io::Write>(&self, writer: &mut W) -> Result<(), Error> {
+        utils::write_limits(writer, self.min, self.max)
+    }
+
     pub fn min(&self) -> usize {
         self.min
     }
@@ -1,8 +1,10 @@
 mod data_item;
+mod elem_item;
 mod export;
 mod expr;
 mod func_body;
 mod func_type;
+mod global;
 mod global_type;
 mod import;
 mod member_desc;
@@ -10,16 +12,20 @@ mod memory_type;
 mod module;
 mod module_names;
 mod table_type;
+mod validate;
 
 pub use self::data_item::DataItem;
-pub use self::export::Export;
+pub use self::elem_item::ElemItem;
+pub use self::export::{Export, ExportDesc};
 pub use self::expr::Expr;
 pub use self::func_body::FuncBody;
 pub use self::func_type::FuncType;
+pub use self::global::Global;
 pub use self::global_type::GlobalType;
 pub use self::import::Import;
 pub use self::member_desc::MemberDesc;
 pub use self::memory_type::MemoryType;
 pub use self::module::Module;
 pub use self::module_names::ModuleNames;
-pub use self::table_type::TableType;
+pub use self::table_type::{ElemType, TableType};
+pub use self::validate::{validate, ValidationError, ValidationErrorKind};
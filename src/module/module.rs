@@ -2,11 +2,17 @@ use std::{fmt, io};
 
 use crate::{
     builder::ModuleBuilder,
-    module::{DataItem, Export, FuncBody, FuncType, Import, ModuleNames},
+    module::{
+        DataItem, ElemItem, Export, FuncBody, FuncType, Global, Import, MemoryType, ModuleNames,
+        TableType,
+    },
     reader::{
-        CodeSection, CustomSection, DataSection, ExportSection, FunctionSection, ImportSection,
-        Reader, SectionHeader, SectionId, TypeSection,
+        CodeSection, CustomSection, DataSection, ElementSection, ExportSection, FunctionSection,
+        GlobalSection, ImportSection, MemorySection, Reader, SectionHeader, SectionId,
+        StartSection, TableSection, TypeSection,
     },
+    utils,
+    writer::Writer,
     Error,
 };
 
@@ -16,7 +22,12 @@ pub struct Module {
     types: Vec<FuncType>,
     imports: Vec<Import>,
     funcs: Vec<usize>,
+    tables: Vec<TableType>,
+    mems: Vec<MemoryType>,
+    globals: Vec<Global>,
     exports: Vec<Export>,
+    start: Option<usize>,
+    elems: Vec<ElemItem>,
     code: Vec<FuncBody>,
     data: Vec<DataItem>,
     names: Option<ModuleNames>,
@@ -28,7 +39,12 @@ impl Module {
             types: builder.types,
             imports: builder.imports,
             funcs: builder.funcs,
+            tables: builder.tables,
+            mems: builder.mems,
+            globals: builder.globals,
             exports: builder.exports,
+            start: builder.start,
+            elems: builder.elems,
             code: builder.code,
             data: builder.data,
             names: builder.names,
@@ -49,7 +65,12 @@ impl Module {
         let mut types = None;
         let mut imports = None;
         let mut funcs = None;
+        let mut tables = None;
+        let mut mems = None;
+        let mut globals = None;
         let mut exports = None;
+        let mut start = None;
+        let mut elems = None;
         let mut code = None;
         let mut data = None;
         let mut names = None;
@@ -60,7 +81,12 @@ impl Module {
                 SectionId::Type => types = Some(load_types(&mut r, header)?),
                 SectionId::Import => imports = Some(load_imports(&mut r, header)?),
                 SectionId::Function => funcs = Some(load_functions(&mut r, header)?),
+                SectionId::Table => tables = Some(load_tables(&mut r, header)?),
+                SectionId::Memory => mems = Some(load_mems(&mut r, header)?),
+                SectionId::Global => globals = Some(load_globals(&mut r, header)?),
                 SectionId::Export => exports = Some(load_exports(&mut r, header)?),
+                SectionId::Start => start = Some(load_start(&mut r, header)?),
+                SectionId::Element => elems = Some(load_elems(&mut r, header)?),
                 SectionId::Code => code = Some(load_code(&mut r, header)?),
                 SectionId::Data => data = Some(load_data(&mut r, header)?),
                 SectionId::Custom => {
@@ -72,11 +98,6 @@ impl Module {
                         }
                     }
                 }
-                _ => {
-                    // Unknown section
-                    // TODO: Logging
-                    r.skip(header.size as usize)?;
-                }
             }
         }
 
@@ -84,13 +105,84 @@ impl Module {
             types: types.unwrap_or_else(|| Vec::new()),
             imports: imports.unwrap_or_else(|| Vec::new()),
             funcs: funcs.unwrap_or_else(|| Vec::new()),
+            tables: tables.unwrap_or_else(|| Vec::new()),
+            mems: mems.unwrap_or_else(|| Vec::new()),
+            globals: globals.unwrap_or_else(|| Vec::new()),
             exports: exports.unwrap_or_else(|| Vec::new()),
+            start,
+            elems: elems.unwrap_or_else(|| Vec::new()),
             code: code.unwrap_or_else(|| Vec::new()),
             data: data.unwrap_or_else(|| Vec::new()),
             names,
         })
     }
 
+    /// Writes the module in the binary format to the provided writer, consuming the writer in the process
+    pub fn save<W: io::Write>(&self, mut w: Writer<W>) -> Result<(), Error> {
+        w.write_module_header()?;
+
+        // Empty sections are omitted, they load as empty anyway
+        if !self.types.is_empty() {
+            w.write_section(SectionId::Type, |s| {
+                utils::write_vec(s, &self.types, |s, t| t.write(s))
+            })?;
+        }
+        if !self.imports.is_empty() {
+            w.write_section(SectionId::Import, |s| {
+                utils::write_vec(s, &self.imports, |s, i| i.write(s))
+            })?;
+        }
+        if !self.funcs.is_empty() {
+            w.write_section(SectionId::Function, |s| {
+                utils::write_vec(s, &self.funcs, |s, f| utils::write_leb128_u32(s, *f as u32))
+            })?;
+        }
+        if !self.tables.is_empty() {
+            w.write_section(SectionId::Table, |s| {
+                utils::write_vec(s, &self.tables, |s, t| t.write(s))
+            })?;
+        }
+        if !self.mems.is_empty() {
+            w.write_section(SectionId::Memory, |s| {
+                utils::write_vec(s, &self.mems, |s, m| m.write(s))
+            })?;
+        }
+        if !self.globals.is_empty() {
+            w.write_section(SectionId::Global, |s| {
+                utils::write_vec(s, &self.globals, |s, g| g.write(s))
+            })?;
+        }
+        if !self.exports.is_empty() {
+            w.write_section(SectionId::Export, |s| {
+                utils::write_vec(s, &self.exports, |s, e| e.write(s))
+            })?;
+        }
+        if let Some(start) = self.start {
+            w.write_section(SectionId::Start, |s| {
+                utils::write_leb128_u32(s, start as u32)
+            })?;
+        }
+        if !self.elems.is_empty() {
+            w.write_section(SectionId::Element, |s| {
+                utils::write_vec(s, &self.elems, |s, e| e.write(s))
+            })?;
+        }
+        if !self.code.is_empty() {
+            w.write_section(SectionId::Code, |s| {
+                utils::write_vec(s, &self.code, |s, c| c.write(s))
+            })?;
+        }
+        if !self.data.is_empty() {
+            w.write_section(SectionId::Data, |s| {
+                utils::write_vec(s, &self.data, |s, d| d.write(s))
+            })?;
+        }
+        if let Some(ref names) = self.names {
+            w.write_custom_section("name", |s| names.write(s))?;
+        }
+        Ok(())
+    }
+
     pub fn types(&self) -> &Vec<FuncType> {
         &self.types
     }
@@ -103,10 +195,31 @@ impl Module {
         &self.funcs
     }
 
+    pub fn tables(&self) -> &Vec<TableType> {
+        &self.tables
+    }
+
+    pub fn mems(&self) -> &Vec<MemoryType> {
+        &self.mems
+    }
+
+    pub fn globals(&self) -> &Vec<Global> {
+        &self.globals
+    }
+
     pub fn exports(&self) -> &Vec<Export> {
         &self.exports
     }
 
+    /// Gets the index of the function to invoke when the module is instantiated, if any.
+    pub fn start(&self) -> Option<usize> {
+        self.start
+    }
+
+    pub fn elems(&self) -> &Vec<ElemItem> {
+        &self.elems
+    }
+
     pub fn code(&self) -> &Vec<FuncBody> {
         &self.code
     }
@@ -144,6 +257,30 @@ fn load_functions<R: io::Read>(
     Ok(section.funcs)
 }
 
+fn load_tables<R: io::Read>(
+    r: &mut Reader<R>,
+    header: SectionHeader,
+) -> Result<Vec<TableType>, Error> {
+    let section: TableSection = r.read_section(header)?;
+    Ok(section.tables)
+}
+
+fn load_mems<R: io::Read>(
+    r: &mut Reader<R>,
+    header: SectionHeader,
+) -> Result<Vec<MemoryType>, Error> {
+    let section: MemorySection = r.read_section(header)?;
+    Ok(section.mems)
+}
+
+fn load_globals<R: io::Read>(
+    r: &mut Reader<R>,
+    header: SectionHeader,
+) -> Result<Vec<Global>, Error> {
+    let section: GlobalSection = r.read_section(header)?;
+    Ok(section.globals)
+}
+
 fn load_exports<R: io::Read>(
     r: &mut Reader<R>,
     header: SectionHeader,
@@ -152,6 +289,19 @@ fn load_exports<R: io::Read>(
     Ok(section.exports)
 }
 
+fn load_start<R: io::Read>(r: &mut Reader<R>, header: SectionHeader) -> Result<usize, Error> {
+    let section: StartSection = r.read_section(header)?;
+    Ok(section.func)
+}
+
+fn load_elems<R: io::Read>(
+    r: &mut Reader<R>,
+    header: SectionHeader,
+) -> Result<Vec<ElemItem>, Error> {
+    let section: ElementSection = r.read_section(header)?;
+    Ok(section.elems)
+}
+
 fn load_code<R: io::Read>(
     r: &mut Reader<R>,
     header: SectionHeader,
@@ -188,9 +338,24 @@ impl fmt::Display for Module {
         for import in self.imports().iter() {
             write!(f, " {}", import)?;
         }
+        for table in self.tables().iter() {
+            write!(f, " {}", table)?;
+        }
+        for mem in self.mems().iter() {
+            write!(f, " {}", mem)?;
+        }
+        for global in self.globals().iter() {
+            write!(f, " {}", global)?;
+        }
         for export in self.exports().iter() {
             write!(f, " {}", export)?;
         }
+        if let Some(start) = self.start() {
+            write!(f, " (start {})", start)?;
+        }
+        for elem in self.elems().iter() {
+            write!(f, " {}", elem)?;
+        }
         for data in self.data().iter() {
             write!(f, " {}", data)?;
         }
@@ -1,4 +1,6 @@
-use crate::{reader::NameSection, SparseVec};
+use std::io;
+
+use crate::{reader::NameSection, utils, Error, SparseVec};
 
 #[derive(Clone, PartialEq)]
 pub struct FuncNames {
@@ -71,4 +73,70 @@ impl ModuleNames {
     pub fn funcs(&self) -> &SparseVec<FuncNames> {
         &self.funcs
     }
+
+    /// Writes the content of the `name` custom section describing these names.
+    pub fn write<W: io::Write>(&self, writer: &mut W) -> Result<(), Error> {
+        if let Some(ref module_name) = self.module_name {
+            write_subsection(writer, 0x00, |w| utils::write_name(w, module_name))?;
+        }
+
+        let func_names: Vec<_> = self
+            .funcs
+            .iter()
+            .filter_map(|(idx, f)| f.func_name().map(|name| (*idx, name)))
+            .collect();
+        if !func_names.is_empty() {
+            write_subsection(writer, 0x01, |w| write_name_map(w, &func_names))?;
+        }
+
+        let local_names: Vec<_> = self
+            .funcs
+            .iter()
+            .filter(|(_, f)| f.locals().len() > 0)
+            .collect();
+        if !local_names.is_empty() {
+            write_subsection(writer, 0x02, |w| {
+                utils::write_vec(w, &local_names, |w, (idx, f)| {
+                    utils::write_leb128_u32(w, *idx as u32)?;
+                    let names: Vec<_> = f.locals().iter().map(|(i, n)| (*i, &n[..])).collect();
+                    write_name_map(w, &names)
+                })
+            })?;
+        }
+        Ok(())
+    }
+
+    pub fn set_module_name<S: Into<String>>(&mut self, name: S) {
+        self.module_name = Some(name.into());
+    }
+
+    pub fn set_func_name<S: Into<String>>(&mut self, func_idx: usize, name: S) {
+        let f = self.funcs.get_or_add(func_idx, |_| FuncNames::new());
+        f.func_name = Some(name.into());
+    }
+
+    pub fn set_local_name<S: Into<String>>(&mut self, func_idx: usize, local_idx: usize, name: S) {
+        let f = self.funcs.get_or_add(func_idx, |_| FuncNames::new());
+        f.locals.set(local_idx, name.into());
+    }
+}
+
+fn write_subsection<W, F>(writer: &mut W, id: u8, body: F) -> Result<(), Error>
+where
+    W: io::Write,
+    F: FnOnce(&mut Vec<u8>) -> Result<(), Error>,
+{
+    let mut content = Vec::new();
+    body(&mut content)?;
+    writer.write_all(&[id])?;
+    utils::write_leb128_u32(writer, content.len() as u32)?;
+    writer.write_all(&content)?;
+    Ok(())
+}
+
+fn write_name_map<W: io::Write>(writer: &mut W, names: &[(usize, &str)]) -> Result<(), Error> {
+    utils::write_vec(writer, names, |w, (idx, name)| {
+        utils::write_leb128_u32(w, *idx as u32)?;
+        utils::write_name(w, name)
+    })
 }
@@ -1,6 +1,6 @@
 use std::{fmt, io};
 
-use byteorder::ReadBytesExt;
+use byteorder::{ReadBytesExt, WriteBytesExt};
 
 use crate::{utils, Error};
 
@@ -18,6 +18,14 @@ pub struct TableType {
 }
 
 impl TableType {
+    pub fn new(elem_type: ElemType, min: usize, max: Option<usize>) -> TableType {
+        TableType {
+            elem_type,
+            min,
+            max,
+        }
+    }
+
     pub fn read<R: io::Read>(reader: &mut R) -> Result<TableType, Error> {
         let elem_type = reader.read_u8()?;
         if elem_type != 0x70 {
@@ -32,6 +40,11 @@ impl TableType {
         }
     }
 
+    pub fn write<W: io::Write>(&self, writer: &mut W) -> Result<(), Error> {
+        writer.write_u8(self.elem_type as u8)?;
+        utils::write_limits(writer, self.min, self.max)
+    }
+
     pub fn elem_type(&self) -> ElemType {
         self.elem_type
     }
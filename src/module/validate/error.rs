@@ -0,0 +1,127 @@
+use std::fmt;
+
+#[derive(Clone, PartialEq)]
+pub enum ValidationErrorKind {
+    TypeMismatch,
+    UnknownType,
+    UnknownFunction,
+    UnknownTable,
+    UnknownMemory,
+    UnknownGlobal,
+    UnknownLocal,
+    UnknownLabel,
+    ImmutableGlobal,
+    ConstantExpressionRequired,
+    InvalidAlignment,
+    MultipleTables,
+    MultipleMemories,
+    InvalidLimits,
+    MemorySizeTooLarge,
+    DuplicateExportName,
+    InvalidStartFunction,
+    InconsistentFunctionCount,
+}
+
+impl ValidationErrorKind {
+    pub fn message(&self) -> &'static str {
+        use self::ValidationErrorKind::*;
+
+        // These strings are described by the spec tests. Do not modify them.
+        match self {
+            TypeMismatch => "type mismatch",
+            UnknownType => "unknown type",
+            UnknownFunction => "unknown function",
+            UnknownTable => "unknown table",
+            UnknownMemory => "unknown memory",
+            UnknownGlobal => "unknown global",
+            UnknownLocal => "unknown local",
+            UnknownLabel => "unknown label",
+            ImmutableGlobal => "global is immutable",
+            ConstantExpressionRequired => "constant expression required",
+            InvalidAlignment => "alignment must not be larger than natural",
+            MultipleTables => "multiple tables",
+            MultipleMemories => "multiple memories",
+            InvalidLimits => "size minimum must not be greater than maximum",
+            MemorySizeTooLarge => "memory size must be at most 65536 pages (4GiB)",
+            DuplicateExportName => "duplicate export name",
+            InvalidStartFunction => "start function",
+            InconsistentFunctionCount => "function and code section have inconsistent lengths",
+        }
+    }
+}
+
+impl fmt::Display for ValidationErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message())
+    }
+}
+
+impl fmt::Debug for ValidationErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+/// Describes why a module failed validation and, for errors in function bodies, where.
+#[derive(Clone, PartialEq)]
+pub struct ValidationError {
+    kind: ValidationErrorKind,
+    func: Option<usize>,
+    offset: Option<usize>,
+}
+
+impl ValidationError {
+    pub fn new(kind: ValidationErrorKind) -> ValidationError {
+        ValidationError {
+            kind,
+            func: None,
+            offset: None,
+        }
+    }
+
+    /// Creates an error located at the instruction `offset` in the body of function `func`.
+    pub fn in_func(kind: ValidationErrorKind, func: usize, offset: usize) -> ValidationError {
+        ValidationError {
+            kind,
+            func: Some(func),
+            offset: Some(offset),
+        }
+    }
+
+    pub fn kind(&self) -> &ValidationErrorKind {
+        &self.kind
+    }
+
+    /// Gets the index, in the function index space, of the function containing the error.
+    pub fn func(&self) -> Option<usize> {
+        self.func
+    }
+
+    /// Gets the index of the offending instruction within the function body.
+    pub fn offset(&self) -> Option<usize> {
+        self.offset
+    }
+}
+
+impl From<ValidationErrorKind> for ValidationError {
+    fn from(kind: ValidationErrorKind) -> ValidationError {
+        ValidationError::new(kind)
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.func, self.offset) {
+            (Some(func), Some(offset)) => {
+                write!(f, "{} (func {}, offset {})", self.kind, func, offset)
+            }
+            _ => write!(f, "{}", self.kind),
+        }
+    }
+}
+
+impl fmt::Debug for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
@@ -0,0 +1,446 @@
+use crate::{
+    module::{
+        validate::{Context, ValidationErrorKind},
+        FuncType,
+    },
+    Instruction, ValType,
+};
+
+use self::ValidationErrorKind::*;
+
+/// The type of an operand. `None` represents an unknown type, which only occurs in
+/// unreachable code and matches any other type.
+type Operand = Option<ValType>;
+
+#[derive(Clone, Copy, PartialEq)]
+enum FrameKind {
+    Func,
+    Block,
+    Loop,
+    If,
+    Else,
+}
+
+struct CtrlFrame {
+    kind: FrameKind,
+    end_types: Vec<ValType>,
+    height: usize,
+    unreachable: bool,
+}
+
+impl CtrlFrame {
+    /// Gets the types of the values expected by a branch to this frame's label.
+    fn label_types(&self) -> &[ValType] {
+        if self.kind == FrameKind::Loop {
+            &[]
+        } else {
+            &self.end_types
+        }
+    }
+}
+
+/// Validates a function body using the algorithm described in the appendix of the spec.
+pub struct FuncValidator<'a> {
+    ctx: &'a Context<'a>,
+    locals: Vec<ValType>,
+    opds: Vec<Operand>,
+    ctrls: Vec<CtrlFrame>,
+}
+
+impl<'a> FuncValidator<'a> {
+    pub fn new(ctx: &'a Context<'a>, typ: &FuncType, locals: &[ValType]) -> FuncValidator<'a> {
+        let mut all_locals = typ.params().to_vec();
+        all_locals.extend_from_slice(locals);
+
+        let mut validator = FuncValidator {
+            ctx,
+            locals: all_locals,
+            opds: Vec::new(),
+            ctrls: Vec::new(),
+        };
+        validator.push_ctrl(FrameKind::Func, typ.results().to_vec());
+        validator
+    }
+
+    /// Validates the provided function body.
+    ///
+    /// ## Returns
+    /// The validation error and the offset of the instruction that caused it, if the body is invalid.
+    pub fn validate(mut self, body: &[Instruction]) -> Result<(), (ValidationErrorKind, usize)> {
+        for (offset, inst) in body.iter().enumerate() {
+            self.validate_inst(inst).map_err(|e| (e, offset))?;
+        }
+
+        // The body is terminated by an implicit 'end' which closes the function frame
+        let end = body.len();
+        self.pop_ctrl().map_err(|e| (e, end))?;
+        if self.ctrls.is_empty() {
+            Ok(())
+        } else {
+            Err((TypeMismatch, end))
+        }
+    }
+
+    fn push(&mut self, opd: Operand) {
+        self.opds.push(opd);
+    }
+
+    fn push_val(&mut self, typ: ValType) {
+        self.opds.push(Some(typ));
+    }
+
+    fn pop(&mut self) -> Result<Operand, ValidationErrorKind> {
+        let frame = self
+            .ctrls
+            .last()
+            .expect("Control stack should not be empty!");
+        if self.opds.len() == frame.height {
+            if frame.unreachable {
+                Ok(None)
+            } else {
+                Err(TypeMismatch)
+            }
+        } else {
+            Ok(self.opds.pop().expect("Operand stack should not be empty!"))
+        }
+    }
+
+    fn pop_expect(&mut self, expected: ValType) -> Result<Operand, ValidationErrorKind> {
+        match self.pop()? {
+            None => Ok(Some(expected)),
+            Some(actual) if actual == expected => Ok(Some(actual)),
+            Some(_) => Err(TypeMismatch),
+        }
+    }
+
+    fn pop_vals(&mut self, types: &[ValType]) -> Result<(), ValidationErrorKind> {
+        for typ in types.iter().rev() {
+            self.pop_expect(*typ)?;
+        }
+        Ok(())
+    }
+
+    fn push_vals(&mut self, types: &[ValType]) {
+        for typ in types {
+            self.push_val(*typ);
+        }
+    }
+
+    fn push_ctrl(&mut self, kind: FrameKind, end_types: Vec<ValType>) {
+        self.ctrls.push(CtrlFrame {
+            kind,
+            end_types,
+            height: self.opds.len(),
+            unreachable: false,
+        });
+    }
+
+    fn pop_ctrl(&mut self) -> Result<CtrlFrame, ValidationErrorKind> {
+        let end_types = match self.ctrls.last() {
+            Some(frame) => frame.end_types.clone(),
+            None => return Err(TypeMismatch),
+        };
+        self.pop_vals(&end_types)?;
+
+        let frame = self
+            .ctrls
+            .pop()
+            .expect("Control stack should not be empty!");
+        if self.opds.len() != frame.height {
+            return Err(TypeMismatch);
+        }
+        Ok(frame)
+    }
+
+    fn label_types(&self, depth: u32) -> Result<Vec<ValType>, ValidationErrorKind> {
+        let depth = depth as usize;
+        if depth < self.ctrls.len() {
+            Ok(self.ctrls[self.ctrls.len() - 1 - depth]
+                .label_types()
+                .to_vec())
+        } else {
+            Err(UnknownLabel)
+        }
+    }
+
+    fn unreachable(&mut self) {
+        let frame = self
+            .ctrls
+            .last_mut()
+            .expect("Control stack should not be empty!");
+        self.opds.truncate(frame.height);
+        frame.unreachable = true;
+    }
+
+    fn unop(&mut self, typ: ValType) -> Result<(), ValidationErrorKind> {
+        self.cvtop(typ, typ)
+    }
+
+    fn binop(&mut self, typ: ValType) -> Result<(), ValidationErrorKind> {
+        self.pop_expect(typ)?;
+        self.pop_expect(typ)?;
+        self.push_val(typ);
+        Ok(())
+    }
+
+    fn testop(&mut self, typ: ValType) -> Result<(), ValidationErrorKind> {
+        self.cvtop(typ, ValType::I32)
+    }
+
+    fn relop(&mut self, typ: ValType) -> Result<(), ValidationErrorKind> {
+        self.pop_expect(typ)?;
+        self.pop_expect(typ)?;
+        self.push_val(ValType::I32);
+        Ok(())
+    }
+
+    fn cvtop(&mut self, from: ValType, to: ValType) -> Result<(), ValidationErrorKind> {
+        self.pop_expect(from)?;
+        self.push_val(to);
+        Ok(())
+    }
+
+    fn load(
+        &mut self,
+        align: u32,
+        max_align: u32,
+        typ: ValType,
+    ) -> Result<(), ValidationErrorKind> {
+        self.check_memarg(align, max_align)?;
+        self.cvtop(ValType::I32, typ)
+    }
+
+    fn store(
+        &mut self,
+        align: u32,
+        max_align: u32,
+        typ: ValType,
+    ) -> Result<(), ValidationErrorKind> {
+        self.check_memarg(align, max_align)?;
+        self.pop_expect(typ)?;
+        self.pop_expect(ValType::I32)?;
+        Ok(())
+    }
+
+    /// Checks that a memory is defined and the alignment is no larger than the
+    /// natural alignment, `max_align`, of the access (both are powers of 2).
+    fn check_memarg(&self, align: u32, max_align: u32) -> Result<(), ValidationErrorKind> {
+        if self.ctx.mems == 0 {
+            Err(UnknownMemory)
+        } else if align > max_align {
+            Err(InvalidAlignment)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn block_types(typ: ValType) -> Vec<ValType> {
+        if typ == ValType::Nil {
+            Vec::new()
+        } else {
+            vec![typ]
+        }
+    }
+
+    fn validate_inst(&mut self, inst: &Instruction) -> Result<(), ValidationErrorKind> {
+        use crate::Instruction::*;
+        use crate::ValType::{F32, F64, I32, I64};
+
+        match inst {
+            Unreachable => self.unreachable(),
+            Nop => {}
+            Block(typ) => self.push_ctrl(FrameKind::Block, Self::block_types(*typ)),
+            Loop(typ) => self.push_ctrl(FrameKind::Loop, Self::block_types(*typ)),
+            If(typ) => {
+                self.pop_expect(I32)?;
+                self.push_ctrl(FrameKind::If, Self::block_types(*typ));
+            }
+            Else => {
+                let frame = self.pop_ctrl()?;
+                if frame.kind != FrameKind::If {
+                    return Err(TypeMismatch);
+                }
+                self.push_ctrl(FrameKind::Else, frame.end_types);
+            }
+            End => {
+                let frame = self.pop_ctrl()?;
+                match frame.kind {
+                    // The function frame is closed by the implicit 'end' at the end of the body
+                    FrameKind::Func => return Err(TypeMismatch),
+                    // An 'if' without an 'else' must leave the stack unchanged
+                    FrameKind::If if !frame.end_types.is_empty() => return Err(TypeMismatch),
+                    _ => {}
+                }
+                self.push_vals(&frame.end_types);
+            }
+            Br(depth) => {
+                let types = self.label_types(*depth)?;
+                self.pop_vals(&types)?;
+                self.unreachable();
+            }
+            BrIf(depth) => {
+                let types = self.label_types(*depth)?;
+                self.pop_expect(I32)?;
+                self.pop_vals(&types)?;
+                self.push_vals(&types);
+            }
+            BrTable(table) => {
+                let default_types = self.label_types(table.default())?;
+                for target in table.targets() {
+                    if self.label_types(*target)? != default_types {
+                        return Err(TypeMismatch);
+                    }
+                }
+                self.pop_expect(I32)?;
+                self.pop_vals(&default_types)?;
+                self.unreachable();
+            }
+            Return => {
+                let types = self.ctrls[0].end_types.clone();
+                self.pop_vals(&types)?;
+                self.unreachable();
+            }
+            Call(func_idx) => {
+                let typ = match self.ctx.func_type(*func_idx as usize) {
+                    Some(typ) => typ,
+                    None => return Err(UnknownFunction),
+                };
+                self.pop_vals(typ.params())?;
+                self.push_vals(typ.results());
+            }
+            CallIndirect(type_idx, _) => {
+                if self.ctx.tables == 0 {
+                    return Err(UnknownTable);
+                }
+                let typ = match self.ctx.types.get(*type_idx as usize) {
+                    Some(typ) => typ,
+                    None => return Err(UnknownType),
+                };
+                self.pop_expect(I32)?;
+                self.pop_vals(typ.params())?;
+                self.push_vals(typ.results());
+            }
+            Drop => {
+                self.pop()?;
+            }
+            Select => {
+                self.pop_expect(I32)?;
+                let first = self.pop()?;
+                let second = match first {
+                    Some(typ) => self.pop_expect(typ)?,
+                    None => self.pop()?,
+                };
+                self.push(second);
+            }
+            LocalGet(local_idx) => {
+                let typ = self.local(*local_idx)?;
+                self.push_val(typ);
+            }
+            LocalSet(local_idx) => {
+                let typ = self.local(*local_idx)?;
+                self.pop_expect(typ)?;
+            }
+            LocalTee(local_idx) => {
+                let typ = self.local(*local_idx)?;
+                self.cvtop(typ, typ)?;
+            }
+            GlobalGet(global_idx) => match self.ctx.globals.get(*global_idx as usize) {
+                Some(global) => self.push_val(global.typ()),
+                None => return Err(UnknownGlobal),
+            },
+            GlobalSet(global_idx) => match self.ctx.globals.get(*global_idx as usize) {
+                Some(global) if global.mutable() => {
+                    self.pop_expect(global.typ())?;
+                }
+                Some(_) => return Err(ImmutableGlobal),
+                None => return Err(UnknownGlobal),
+            },
+
+            I32Load(align, _) => self.load(*align, 2, I32)?,
+            I64Load(align, _) => self.load(*align, 3, I64)?,
+            F32Load(align, _) => self.load(*align, 2, F32)?,
+            F64Load(align, _) => self.load(*align, 3, F64)?,
+            I32Load8S(align, _) | I32Load8U(align, _) => self.load(*align, 0, I32)?,
+            I32Load16S(align, _) | I32Load16U(align, _) => self.load(*align, 1, I32)?,
+            I64Load8S(align, _) | I64Load8U(align, _) => self.load(*align, 0, I64)?,
+            I64Load16S(align, _) | I64Load16U(align, _) => self.load(*align, 1, I64)?,
+            I64Load32S(align, _) | I64Load32U(align, _) => self.load(*align, 2, I64)?,
+            I32Store(align, _) => self.store(*align, 2, I32)?,
+            I64Store(align, _) => self.store(*align, 3, I64)?,
+            F32Store(align, _) => self.store(*align, 2, F32)?,
+            F64Store(align, _) => self.store(*align, 3, F64)?,
+            I32Store8(align, _) => self.store(*align, 0, I32)?,
+            I32Store16(align, _) => self.store(*align, 1, I32)?,
+            I64Store8(align, _) => self.store(*align, 0, I64)?,
+            I64Store16(align, _) => self.store(*align, 1, I64)?,
+            I64Store32(align, _) => self.store(*align, 2, I64)?,
+            MemorySize(_) => {
+                if self.ctx.mems == 0 {
+                    return Err(UnknownMemory);
+                }
+                self.push_val(I32);
+            }
+            MemoryGrow(_) => {
+                if self.ctx.mems == 0 {
+                    return Err(UnknownMemory);
+                }
+                self.unop(I32)?;
+            }
+
+            I32Const(_) => self.push_val(I32),
+            I64Const(_) => self.push_val(I64),
+            F32Const(_) => self.push_val(F32),
+            F64Const(_) => self.push_val(F64),
+
+            I32Eqz => self.testop(I32)?,
+            I64Eqz => self.testop(I64)?,
+            I32Eq | I32Ne | I32LtS | I32LtU | I32GtS | I32GtU | I32LeS | I32LeU | I32GeS
+            | I32GeU => self.relop(I32)?,
+            I64Eq | I64Ne | I64LtS | I64LtU | I64GtS | I64GtU | I64LeS | I64LeU | I64GeS
+            | I64GeU => self.relop(I64)?,
+            F32Eq | F32Ne | F32Lt | F32Gt | F32Le | F32Ge => self.relop(F32)?,
+            F64Eq | F64Ne | F64Lt | F64Gt | F64Le | F64Ge => self.relop(F64)?,
+
+            I32Clz | I32Ctz | I32Popcnt => self.unop(I32)?,
+            I64Clz | I64Ctz | I64Popcnt => self.unop(I64)?,
+            F32Abs | F32Neg | F32Ceil | F32Floor | F32Trunc | F32Nearest | F32Sqrt => {
+                self.unop(F32)?
+            }
+            F64Abs | F64Neg | F64Ceil | F64Floor | F64Trunc | F64Nearest | F64Sqrt => {
+                self.unop(F64)?
+            }
+
+            I32Add | I32Sub | I32Mul | I32DivS | I32DivU | I32RemS | I32RemU | I32And | I32Or
+            | I32Xor | I32Shl | I32ShrS | I32ShrU | I32Rotl | I32Rotr => self.binop(I32)?,
+            I64Add | I64Sub | I64Mul | I64DivS | I64DivU | I64RemS | I64RemU | I64And | I64Or
+            | I64Xor | I64Shl | I64ShrS | I64ShrU | I64Rotl | I64Rotr => self.binop(I64)?,
+            F32Add | F32Sub | F32Mul | F32Div | F32Min | F32Max | F32Copysign => self.binop(F32)?,
+            F64Add | F64Sub | F64Mul | F64Div | F64Min | F64Max | F64Copysign => self.binop(F64)?,
+
+            I32WrapI64 => self.cvtop(I64, I32)?,
+            I32TruncF32S | I32TruncF32U => self.cvtop(F32, I32)?,
+            I32TruncF64S | I32TruncF64U => self.cvtop(F64, I32)?,
+            I64ExtendI32S | I64ExtendI32U => self.cvtop(I32, I64)?,
+            I64TruncF32S | I64TruncF32U => self.cvtop(F32, I64)?,
+            I64TruncF64S | I64TruncF64U => self.cvtop(F64, I64)?,
+            F32ConvertI32S | F32ConvertI32U => self.cvtop(I32, F32)?,
+            F32ConvertI64S | F32ConvertI64U => self.cvtop(I64, F32)?,
+            F32DemoteF64 => self.cvtop(F64, F32)?,
+            F64ConvertI32S | F64ConvertI32U => self.cvtop(I32, F64)?,
+            F64ConvertI64S | F64ConvertI64U => self.cvtop(I64, F64)?,
+            F64PromoteF32 => self.cvtop(F32, F64)?,
+            I32ReinterpretF32 => self.cvtop(F32, I32)?,
+            I64ReinterpretF64 => self.cvtop(F64, I64)?,
+            F32ReinterpretI32 => self.cvtop(I32, F32)?,
+            F64ReinterpretI64 => self.cvtop(I64, F64)?,
+        }
+        Ok(())
+    }
+
+    fn local(&self, local_idx: u32) -> Result<ValType, ValidationErrorKind> {
+        match self.locals.get(local_idx as usize) {
+            Some(typ) => Ok(*typ),
+            None => Err(UnknownLocal),
+        }
+    }
+}
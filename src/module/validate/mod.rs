@@ -0,0 +1,450 @@
+mod error;
+mod func;
+
+pub use self::error::{ValidationError, ValidationErrorKind};
+
+use std::collections::HashSet;
+
+use crate::{
+    memory::MAX_PAGES,
+    module::{Expr, FuncType, GlobalType, MemberDesc, Module},
+    Instruction, ValType,
+};
+
+use self::{func::FuncValidator, ValidationErrorKind::*};
+
+/// The types of the entities visible to the code being validated.
+pub struct Context<'a> {
+    types: &'a [FuncType],
+    funcs: Vec<usize>,
+    tables: usize,
+    mems: usize,
+    globals: Vec<GlobalType>,
+}
+
+impl<'a> Context<'a> {
+    fn func_type(&self, func_idx: usize) -> Option<&'a FuncType> {
+        self.funcs
+            .get(func_idx)
+            .and_then(|type_idx| self.types.get(*type_idx))
+    }
+}
+
+/// Validates the provided [`Module`] according to the validation rules in the WebAssembly spec.
+pub fn validate(module: &Module) -> Result<(), ValidationError> {
+    let mut ctx = Context {
+        types: module.types(),
+        funcs: Vec::new(),
+        tables: 0,
+        mems: 0,
+        globals: Vec::new(),
+    };
+
+    for import in module.imports() {
+        match import.description() {
+            MemberDesc::Function(type_idx) => {
+                check_type(module, *type_idx)?;
+                ctx.funcs.push(*type_idx);
+            }
+            MemberDesc::Table(table) => {
+                check_limits(table.min(), table.max())?;
+                ctx.tables += 1;
+            }
+            MemberDesc::Memory(mem) => {
+                check_limits(mem.min(), mem.max())?;
+                check_memory_size(mem.min(), mem.max())?;
+                ctx.mems += 1;
+            }
+            MemberDesc::Global(global) => ctx.globals.push(global.clone()),
+        }
+    }
+
+    // Constant expressions may only refer to imported globals
+    let imported_globals = ctx.globals.len();
+
+    for type_idx in module.funcs() {
+        check_type(module, *type_idx)?;
+        ctx.funcs.push(*type_idx);
+    }
+    if module.funcs().len() != module.code().len() {
+        return Err(InconsistentFunctionCount.into());
+    }
+
+    for table in module.tables() {
+        check_limits(table.min(), table.max())?;
+        ctx.tables += 1;
+    }
+    if ctx.tables > 1 {
+        return Err(MultipleTables.into());
+    }
+
+    for mem in module.mems() {
+        check_limits(mem.min(), mem.max())?;
+        check_memory_size(mem.min(), mem.max())?;
+        ctx.mems += 1;
+    }
+    if ctx.mems > 1 {
+        return Err(MultipleMemories.into());
+    }
+
+    for global in module.globals() {
+        validate_const_expr(&ctx, imported_globals, global.init(), global.typ().typ())?;
+        ctx.globals.push(global.typ().clone());
+    }
+
+    for elem in module.elems() {
+        if elem.index() >= ctx.tables {
+            return Err(UnknownTable.into());
+        }
+        validate_const_expr(&ctx, imported_globals, elem.expr(), ValType::I32)?;
+        if elem
+            .init()
+            .iter()
+            .any(|func_idx| *func_idx >= ctx.funcs.len())
+        {
+            return Err(UnknownFunction.into());
+        }
+    }
+
+    for data in module.data() {
+        if data.index() >= ctx.mems {
+            return Err(UnknownMemory.into());
+        }
+        validate_const_expr(&ctx, imported_globals, data.expr(), ValType::I32)?;
+    }
+
+    if let Some(func_idx) = module.start() {
+        match ctx.func_type(func_idx) {
+            Some(typ) if typ.params().is_empty() && typ.results().is_empty() => {}
+            Some(_) => return Err(InvalidStartFunction.into()),
+            None => return Err(UnknownFunction.into()),
+        }
+    }
+
+    validate_exports(module, &ctx)?;
+
+    let imported_funcs = ctx.funcs.len() - module.funcs().len();
+    for (i, code) in module.code().iter().enumerate() {
+        let func_idx = imported_funcs + i;
+        let typ = ctx
+            .func_type(func_idx)
+            .expect("Function types should have been checked!");
+        FuncValidator::new(&ctx, typ, code.locals())
+            .validate(code.body())
+            .map_err(|(kind, offset)| ValidationError::in_func(kind, func_idx, offset))?;
+    }
+
+    Ok(())
+}
+
+fn check_type(module: &Module, type_idx: usize) -> Result<(), ValidationError> {
+    if type_idx < module.types().len() {
+        Ok(())
+    } else {
+        Err(UnknownType.into())
+    }
+}
+
+fn check_limits(min: usize, max: Option<usize>) -> Result<(), ValidationError> {
+    match max {
+        Some(max) if min > max => Err(InvalidLimits.into()),
+        _ => Ok(()),
+    }
+}
+
+fn check_memory_size(min: usize, max: Option<usize>) -> Result<(), ValidationError> {
+    if min > MAX_PAGES || max.is_some_and(|max| max > MAX_PAGES) {
+        Err(MemorySizeTooLarge.into())
+    } else {
+        Ok(())
+    }
+}
+
+fn validate_exports(module: &Module, ctx: &Context) -> Result<(), ValidationError> {
+    use crate::module::ExportDesc;
+
+    let mut names = HashSet::new();
+    for export in module.exports() {
+        if !names.insert(export.name()) {
+            return Err(DuplicateExportName.into());
+        }
+
+        match export.description() {
+            ExportDesc::Function(idx) if *idx >= ctx.funcs.len() => {
+                return Err(UnknownFunction.into())
+            }
+            ExportDesc::Table(idx) if *idx >= ctx.tables => return Err(UnknownTable.into()),
+            ExportDesc::Memory(idx) if *idx >= ctx.mems => return Err(UnknownMemory.into()),
+            ExportDesc::Global(idx) if *idx >= ctx.globals.len() => {
+                return Err(UnknownGlobal.into())
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Validates a constant expression producing a single value of type `expected`.
+///
+/// Only the first `imported_globals` globals, which must be immutable, may be referenced. The
+/// integer `add`, `sub` and `mul` instructions are allowed, as in the extended-const proposal.
+fn validate_const_expr(
+    ctx: &Context,
+    imported_globals: usize,
+    expr: &Expr,
+    expected: ValType,
+) -> Result<(), ValidationError> {
+    let mut stack = Vec::new();
+    for inst in expr.iter() {
+        match inst {
+            Instruction::I32Const(_) => stack.push(ValType::I32),
+            Instruction::I64Const(_) => stack.push(ValType::I64),
+            Instruction::F32Const(_) => stack.push(ValType::F32),
+            Instruction::F64Const(_) => stack.push(ValType::F64),
+            Instruction::GlobalGet(idx) => {
+                let idx = *idx as usize;
+                if idx >= imported_globals {
+                    return Err(UnknownGlobal.into());
+                }
+                let global = &ctx.globals[idx];
+                if global.mutable() {
+                    return Err(ConstantExpressionRequired.into());
+                }
+                stack.push(global.typ());
+            }
+            Instruction::I32Add | Instruction::I32Sub | Instruction::I32Mul => {
+                pop_operands(&mut stack, ValType::I32)?
+            }
+            Instruction::I64Add | Instruction::I64Sub | Instruction::I64Mul => {
+                pop_operands(&mut stack, ValType::I64)?
+            }
+            _ => return Err(ConstantExpressionRequired.into()),
+        }
+    }
+
+    if stack.len() == 1 && stack[0] == expected {
+        Ok(())
+    } else {
+        Err(TypeMismatch.into())
+    }
+}
+
+/// Pops the two operands of a binary operator of type `typ`, and pushes its result.
+fn pop_operands(stack: &mut Vec<ValType>, typ: ValType) -> Result<(), ValidationError> {
+    match (stack.pop(), stack.pop()) {
+        (Some(rhs), Some(lhs)) if rhs == typ && lhs == typ => {
+            stack.push(typ);
+            Ok(())
+        }
+        _ => Err(TypeMismatch.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        builder::{FuncBuilder, ModuleBuilder},
+        module::{
+            validate, Export, Expr, Global, GlobalType, Import, MemberDesc, MemoryType, Module,
+            ValidationErrorKind,
+        },
+        Instruction::{self, *},
+        ValType, Value,
+    };
+
+    fn func_module(func: FuncBuilder) -> Module {
+        ModuleBuilder::new().func(func).build()
+    }
+
+    fn assert_invalid(module: Module, expected: ValidationErrorKind) {
+        match validate(&module) {
+            Err(e) => assert_eq!(expected, *e.kind()),
+            Ok(()) => panic!("Expected validation to fail"),
+        }
+    }
+
+    #[test]
+    pub fn accepts_valid_function() {
+        let func = FuncBuilder::new()
+            .param(ValType::I32)
+            .result(ValType::I32)
+            .body(vec![
+                Block(ValType::I32),
+                LocalGet(0),
+                If(ValType::I32),
+                I32Const(Value::I32(1)),
+                Else,
+                I32Const(Value::I32(2)),
+                End,
+                End,
+            ]);
+        assert_eq!(Ok(()), validate(&func_module(func)));
+    }
+
+    #[test]
+    pub fn reports_function_and_offset() {
+        let func = FuncBuilder::new().result(ValType::I32).body(vec![
+            Nop,
+            I64Const(Value::I64(1)),
+            I32Eqz,
+        ]);
+        let err = validate(&func_module(func)).unwrap_err();
+        assert_eq!(ValidationErrorKind::TypeMismatch, *err.kind());
+        assert_eq!(Some(0), err.func());
+        assert_eq!(Some(2), err.offset());
+    }
+
+    #[test]
+    pub fn rejects_missing_result() {
+        let func = FuncBuilder::new().result(ValType::I32).body(vec![Nop]);
+        assert_invalid(func_module(func), ValidationErrorKind::TypeMismatch);
+    }
+
+    #[test]
+    pub fn rejects_extra_values_at_end_of_block() {
+        let func = FuncBuilder::new().body(vec![Block(ValType::Nil), I32Const(Value::I32(1)), End]);
+        assert_invalid(func_module(func), ValidationErrorKind::TypeMismatch);
+    }
+
+    #[test]
+    pub fn rejects_if_without_else_producing_value() {
+        let func = FuncBuilder::new().result(ValType::I32).body(vec![
+            I32Const(Value::I32(1)),
+            If(ValType::I32),
+            I32Const(Value::I32(1)),
+            End,
+        ]);
+        assert_invalid(func_module(func), ValidationErrorKind::TypeMismatch);
+    }
+
+    #[test]
+    pub fn accepts_any_operands_after_unreachable() {
+        let func =
+            FuncBuilder::new()
+                .result(ValType::F64)
+                .body(vec![Unreachable, I32Add, Drop, Select]);
+        assert_eq!(Ok(()), validate(&func_module(func)));
+    }
+
+    #[test]
+    pub fn rejects_unknown_indices() {
+        let unknown_local = FuncBuilder::new().body(vec![LocalGet(0), Drop]);
+        assert_invalid(
+            func_module(unknown_local),
+            ValidationErrorKind::UnknownLocal,
+        );
+
+        let unknown_label = FuncBuilder::new().body(vec![Br(1)]);
+        assert_invalid(
+            func_module(unknown_label),
+            ValidationErrorKind::UnknownLabel,
+        );
+
+        let unknown_func = FuncBuilder::new().body(vec![Call(1)]);
+        assert_invalid(
+            func_module(unknown_func),
+            ValidationErrorKind::UnknownFunction,
+        );
+
+        let unknown_global = FuncBuilder::new().body(vec![GlobalGet(0), Drop]);
+        assert_invalid(
+            func_module(unknown_global),
+            ValidationErrorKind::UnknownGlobal,
+        );
+
+        let unknown_memory = FuncBuilder::new().body(vec![MemorySize(0), Drop]);
+        assert_invalid(
+            func_module(unknown_memory),
+            ValidationErrorKind::UnknownMemory,
+        );
+
+        let unknown_table =
+            FuncBuilder::new().body(vec![I32Const(Value::I32(0)), CallIndirect(0, 0)]);
+        assert_invalid(
+            func_module(unknown_table),
+            ValidationErrorKind::UnknownTable,
+        );
+    }
+
+    #[test]
+    pub fn rejects_alignment_larger_than_natural() {
+        let mut builder = ModuleBuilder::new();
+        builder.imports.push(Import::new(
+            "env",
+            "memory",
+            MemberDesc::Memory(MemoryType::new(1, None)),
+        ));
+        let module = builder
+            .func(
+                FuncBuilder::new()
+                    .result(ValType::I32)
+                    .body(vec![I32Const(Value::I32(0)), I32Load16U(2, 0)]),
+            )
+            .build();
+        assert_invalid(module, ValidationErrorKind::InvalidAlignment);
+    }
+
+    #[test]
+    pub fn rejects_non_constant_global_initializer() {
+        let module = ModuleBuilder::new()
+            .global(Global::new(
+                GlobalType::new(ValType::I32, false),
+                Expr::new(vec![
+                    Instruction::I32Const(Value::I32(1)),
+                    Instruction::I32Const(Value::I32(2)),
+                    Instruction::I32DivS,
+                ]),
+            ))
+            .build();
+        assert_invalid(module, ValidationErrorKind::ConstantExpressionRequired);
+    }
+
+    #[test]
+    pub fn accepts_extended_constant_expressions() {
+        let mut builder = ModuleBuilder::new();
+        builder.imports.push(Import::new(
+            "env",
+            "base",
+            MemberDesc::Global(GlobalType::new(ValType::I64, false)),
+        ));
+        let init = Expr::new(vec![
+            Instruction::GlobalGet(0),
+            Instruction::I64Const(Value::I64(8)),
+            Instruction::I64Mul,
+            Instruction::I64Const(Value::I64(1)),
+            Instruction::I64Add,
+        ]);
+        let module = builder
+            .global(Global::new(GlobalType::new(ValType::I64, false), init))
+            .build();
+        assert_eq!(Ok(()), validate(&module));
+
+        let mixed = ModuleBuilder::new()
+            .global(Global::new(
+                GlobalType::new(ValType::I32, false),
+                Expr::new(vec![
+                    Instruction::I32Const(Value::I32(1)),
+                    Instruction::I64Const(Value::I64(2)),
+                    Instruction::I32Sub,
+                ]),
+            ))
+            .build();
+        assert_invalid(mixed, ValidationErrorKind::TypeMismatch);
+    }
+
+    #[test]
+    pub fn rejects_start_function_with_params() {
+        let module = ModuleBuilder::new()
+            .func(FuncBuilder::new().param(ValType::I32))
+            .start(0)
+            .build();
+        assert_invalid(module, ValidationErrorKind::InvalidStartFunction);
+    }
+
+    #[test]
+    pub fn rejects_duplicate_export_names() {
+        let mut builder = ModuleBuilder::new().func(FuncBuilder::new().export_as("f"));
+        builder.exports.push(Export::func("f", 0));
+        assert_invalid(builder.build(), ValidationErrorKind::DuplicateExportName);
+    }
+}
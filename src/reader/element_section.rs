@@ -0,0 +1,15 @@
+use std::io;
+
+use crate::{module::ElemItem, reader::Section, utils, Error};
+
+pub struct ElementSection {
+    pub elems: Vec<ElemItem>,
+}
+
+impl Section for ElementSection {
+    fn read<R: io::Read>(reader: &mut R) -> Result<ElementSection, Error> {
+        let elems = utils::read_vec(reader, |r| ElemItem::read(r))?;
+
+        Ok(ElementSection { elems })
+    }
+}
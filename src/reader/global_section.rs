@@ -0,0 +1,15 @@
+use std::io;
+
+use crate::{module::Global, reader::Section, utils, Error};
+
+pub struct GlobalSection {
+    pub globals: Vec<Global>,
+}
+
+impl Section for GlobalSection {
+    fn read<R: io::Read>(reader: &mut R) -> Result<GlobalSection, Error> {
+        let globals = utils::read_vec(reader, |r| Global::read(r))?;
+
+        Ok(GlobalSection { globals })
+    }
+}
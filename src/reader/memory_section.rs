@@ -0,0 +1,15 @@
+use std::io;
+
+use crate::{module::MemoryType, reader::Section, utils, Error};
+
+pub struct MemorySection {
+    pub mems: Vec<MemoryType>,
+}
+
+impl Section for MemorySection {
+    fn read<R: io::Read>(reader: &mut R) -> Result<MemorySection, Error> {
+        let mems = utils::read_vec(reader, |r| MemoryType::read(r))?;
+
+        Ok(MemorySection { mems })
+    }
+}
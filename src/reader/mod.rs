@@ -1,21 +1,31 @@
 mod code_section;
 mod custom_section;
 mod data_section;
+mod element_section;
 mod export_section;
 mod function_section;
+mod global_section;
 mod import_section;
+mod memory_section;
 mod name_section;
 mod section_header;
+mod start_section;
+mod table_section;
 mod type_section;
 
 pub use self::code_section::CodeSection;
 pub use self::custom_section::CustomSection;
 pub use self::data_section::DataSection;
+pub use self::element_section::ElementSection;
 pub use self::export_section::ExportSection;
 pub use self::function_section::FunctionSection;
+pub use self::global_section::GlobalSection;
 pub use self::import_section::ImportSection;
+pub use self::memory_section::MemorySection;
 pub use self::name_section::NameSection;
 pub use self::section_header::{SectionHeader, SectionId};
+pub use self::start_section::StartSection;
+pub use self::table_section::TableSection;
 pub use self::type_section::TypeSection;
 
 use std::io;
@@ -50,7 +60,7 @@ impl<R: io::Read> Reader<R> {
         let magic_num = LittleEndian::read_u32(&magic);
 
         if magic_num != EXPECTED_MAGIC {
-            return Err(Error::InvalidModule);
+            return Err(Error::InvalidMagic);
         }
 
         let mut version = [0u8; 4];
@@ -63,7 +73,8 @@ impl<R: io::Read> Reader<R> {
 
     pub fn read_section_header(&mut self) -> Result<Option<SectionHeader>, Error> {
         let id = match self.source.read_u8() {
-            Ok(i) => SectionId::from(i),
+            Ok(i) if i <= SectionId::Data as u8 => SectionId::from(i),
+            Ok(i) => return Err(Error::UnknownSection(i)),
             Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
             Err(e) => return Err(e.into()),
         };
@@ -92,5 +103,10 @@ fn read_section_helper<R: io::Read, S: Section>(
     reader: R,
     header: SectionHeader,
 ) -> Result<S, Error> {
-    S::read(&mut reader.take(header.size as u64))
+    let mut content = reader.take(header.size as u64);
+    let section = S::read(&mut content)?;
+    if content.limit() != 0 {
+        return Err(Error::SectionSizeMismatch);
+    }
+    Ok(section)
 }
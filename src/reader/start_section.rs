@@ -0,0 +1,15 @@
+use std::io;
+
+use crate::{reader::Section, utils, Error};
+
+pub struct StartSection {
+    pub func: usize,
+}
+
+impl Section for StartSection {
+    fn read<R: io::Read>(reader: &mut R) -> Result<StartSection, Error> {
+        let func = utils::read_leb128_u32(reader)? as usize;
+
+        Ok(StartSection { func })
+    }
+}
@@ -0,0 +1,15 @@
+use std::io;
+
+use crate::{module::TableType, reader::Section, utils, Error};
+
+pub struct TableSection {
+    pub tables: Vec<TableType>,
+}
+
+impl Section for TableSection {
+    fn read<R: io::Read>(reader: &mut R) -> Result<TableSection, Error> {
+        let tables = utils::read_vec(reader, |r| TableType::read(r))?;
+
+        Ok(TableSection { tables })
+    }
+}
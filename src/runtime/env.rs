@@ -1,7 +1,7 @@
 use std::sync::Arc;
 
 use crate::{
-    hosting::{ExternalFunc, ExternalMemory, ExternalModule, Host},
+    hosting::{ExternalFunc, ExternalGlobal, ExternalMemory, ExternalModule, ExternalTable, Host},
     interp::Thread,
     module::FuncType,
     FromValue, Trap, ValType, Value,
@@ -34,33 +34,31 @@ impl ExternalModule for Env {
         &self.funcs
     }
 
+    fn tables(&self) -> &[ExternalTable] {
+        &[]
+    }
+
     fn mems(&self) -> &[ExternalMemory] {
         &self.mems
     }
+
+    fn globals(&self) -> &[ExternalGlobal] {
+        &[]
+    }
 }
 
 fn print(host: &mut Host, thread: &mut Thread, values: &[Value]) -> Result<Vec<Value>, Trap> {
-    let (count, start) = (
+    let (start, count) = (
         u32::from_value(values[0])? as usize,
         u32::from_value(values[1])? as usize,
     );
 
     let module = thread.stack().current().frame().module();
-    let end = start + count;
 
     // Get memory 0 for the current frame
     let mem_addr = host.resolve_mem(module, 0);
     let mem_inst = host.get_mem(mem_addr);
-    let mem = mem_inst.memory();
-
-    // Safe as long as other threads (which don't even exist in WASM yet)
-    // aren't accessing memory. When threading exists, WASM will provide
-    // it's own synchronization primitives.
-    unsafe {
-        // Read the memory sequence in as a UTF-8 string
-        let s = std::str::from_utf8(&mem.data()[start..end]).unwrap();
-        println!("{}", s);
-    }
+    println!("{}", mem_inst.view().read_str(start, count)?);
 
     Ok(Vec::new())
 }
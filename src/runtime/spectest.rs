@@ -1,24 +1,46 @@
 use std::sync::Arc;
 
 use crate::{
-    hosting::{ExternalFunc, ExternalMemory, ExternalModule, Host},
-    interp::Thread,
-    module::FuncType,
-    Trap, ValType, Value,
+    hosting::{ExternalFunc, ExternalGlobal, ExternalMemory, ExternalModule, ExternalTable},
+    module::GlobalType,
+    ValType, Value,
 };
 
 pub struct SpecTest {
     funcs: Vec<Arc<ExternalFunc>>,
+    tables: Vec<ExternalTable>,
+    globals: Vec<ExternalGlobal>,
 }
 
 impl SpecTest {
     pub fn new() -> SpecTest {
         SpecTest {
-            funcs: vec![Arc::new(ExternalFunc::new(
-                "print_i32",
-                FuncType::new(vec![ValType::I32], vec![]),
-                print_i32,
-            ))],
+            funcs: vec![Arc::new(ExternalFunc::wrap("print_i32", |value: i32| {
+                println!("{} : {}", value, ValType::I32)
+            }))],
+            tables: vec![ExternalTable::new("table", 10, Some(20))],
+            globals: vec![
+                ExternalGlobal::new(
+                    "global_i32",
+                    GlobalType::new(ValType::I32, false),
+                    Value::I32(666),
+                ),
+                ExternalGlobal::new(
+                    "global_i64",
+                    GlobalType::new(ValType::I64, false),
+                    Value::I64(666),
+                ),
+                ExternalGlobal::new(
+                    "global_f32",
+                    GlobalType::new(ValType::F32, false),
+                    Value::F32(666.6),
+                ),
+                ExternalGlobal::new(
+                    "global_f64",
+                    GlobalType::new(ValType::F64, false),
+                    Value::F64(666.6),
+                ),
+            ],
         }
     }
 }
@@ -32,20 +54,15 @@ impl ExternalModule for SpecTest {
         &self.funcs
     }
 
+    fn tables(&self) -> &[ExternalTable] {
+        &self.tables
+    }
+
     fn mems(&self) -> &[ExternalMemory] {
         &[]
     }
-}
 
-fn print_i32(_host: &mut Host, _thread: &mut Thread, values: &[Value]) -> Result<Vec<Value>, Trap> {
-    let value = match values.iter().next() {
-        Some(Value::I32(v)) => v,
-        Some(v) => {
-            return Err(format!("Type mismatch, expected an i32 but found a {}", v.typ()).into())
-        }
-        None => return Err("Stack underflow!".into()),
-    };
-    println!("{} : {}", value, ValType::I32);
-
-    Ok(Vec::new())
+    fn globals(&self) -> &[ExternalGlobal] {
+        &self.globals
+    }
 }
@@ -0,0 +1,200 @@
+//! Parsing of the WebAssembly text format.
+
+mod error;
+mod number;
+mod parser;
+mod sexpr;
+
+pub use self::error::{ParseError, Position};
+pub use self::parser::{parse_const, parse_module_expr, Cursor};
+pub use self::sexpr::{parse, SExpr};
+
+use crate::module::Module;
+
+/// Parses a module written in the WebAssembly text format.
+///
+/// The text may either be a single `(module ...)` expression, or a sequence of bare module
+/// fields (the abbreviation allowed for a file containing a single module). `$name`
+/// identifiers for the module, functions and locals are recorded in the module's names.
+pub fn parse_module(text: &str) -> Result<Module, ParseError> {
+    let exprs = sexpr::parse(text)?;
+    match exprs.first() {
+        Some(first) if exprs.len() == 1 && first.is_list_of("module") => {
+            parse_module_expr(first).map(|(_, module)| module)
+        }
+        _ => parser::parse_fields(&exprs, None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{interp::exec::tests::invoke, module::MemoryType, TrapCause, Value};
+
+    fn run(text: &str, name: &str, args: Vec<Value>) -> Vec<Value> {
+        invoke(parse_module(text).unwrap(), name, args).unwrap()
+    }
+
+    #[test]
+    pub fn runs_flat_instructions() {
+        let text = r#"
+            (module
+              (func $add (export "add") (param $a i32) (param $b i32) (result i32)
+                local.get $a
+                local.get $b
+                i32.add))
+        "#;
+        assert_eq!(
+            vec![Value::I32(5)],
+            run(text, "add", vec![Value::I32(2), Value::I32(3)])
+        );
+    }
+
+    #[test]
+    pub fn runs_folded_instructions() {
+        let text = r#"
+            (func (export "fac") (param i64) (result i64)
+              (if (result i64) (i64.eqz (local.get 0))
+                (then (i64.const 1))
+                (else
+                  (i64.mul
+                    (local.get 0)
+                    (call 0 (i64.sub (local.get 0) (i64.const 1)))))))
+        "#;
+        assert_eq!(vec![Value::I64(120)], run(text, "fac", vec![Value::I64(5)]));
+    }
+
+    #[test]
+    pub fn resolves_labels_by_name() {
+        let text = r#"
+            (func (export "count") (param $n i32) (result i32) (local $i i32)
+              block $done
+                loop $next
+                  (br_if $done (i32.ge_u (local.get $i) (local.get $n)))
+                  (local.set $i (i32.add (local.get $i) (i32.const 1)))
+                  br $next
+                end
+              end
+              local.get $i)
+        "#;
+        assert_eq!(vec![Value::I32(7)], run(text, "count", vec![Value::I32(7)]));
+    }
+
+    #[test]
+    pub fn parses_tables_and_globals() {
+        let text = r#"
+            (module
+              (type $ret (func (result i32)))
+              (global $base (mut i32) (i32.const 40))
+              (table funcref (elem $one $two))
+              (func $one (result i32) (i32.const 1))
+              (func $two (result i32) (i32.const 2))
+              (func (export "test") (param i32) (result i32)
+                (global.set $base (i32.add (global.get $base) (i32.const 1)))
+                (i32.add
+                  (global.get $base)
+                  (call_indirect (type $ret) (local.get 0)))))
+        "#;
+        assert_eq!(vec![Value::I32(43)], run(text, "test", vec![Value::I32(1)]));
+    }
+
+    #[test]
+    pub fn parses_memory_instructions() {
+        let text = r#"
+            (module
+              (import "env" "memory" (memory 1))
+              (data (i32.const 8) "\2a\00\00\00")
+              (func (export "test") (result i32)
+                (i32.store offset=4 align=4 (i32.const 0) (i32.const 1))
+                (i32.add (i32.load (i32.const 4)) (i32.load8_u offset=8 (i32.const 0)))))
+        "#;
+        assert_eq!(vec![Value::I32(43)], run(text, "test", vec![]));
+    }
+
+    #[test]
+    pub fn parses_defined_memories() {
+        let module =
+            parse_module(r#"(module (memory $m 1 2) (data $m (i32.const 0) "a"))"#).unwrap();
+        assert_eq!(vec![MemoryType::new(1, Some(2))], *module.mems());
+        assert_eq!(0, module.data()[0].index());
+
+        let text = r#"
+            (module
+              (memory (data "\2a" "\00\00\00"))
+              (func (export "test") (result i32) (i32.add (memory.size) (i32.load (i32.const 0)))))
+        "#;
+        assert_eq!(vec![Value::I32(43)], run(text, "test", vec![]));
+    }
+
+    #[test]
+    pub fn records_names() {
+        let module =
+            parse_module(r#"(module $m (func $f (param $x i32) (local $y i64)) (func $g))"#)
+                .unwrap();
+        let names = module.names().unwrap();
+        assert_eq!(Some("m"), names.module_name());
+        let f = names.funcs().get(0).unwrap();
+        assert_eq!(Some("f"), f.func_name());
+        assert_eq!(Some("x"), f.local_name(0));
+        assert_eq!(Some("y"), f.local_name(1));
+        assert_eq!(Some("g"), names.funcs().get(1).unwrap().func_name());
+    }
+
+    #[test]
+    pub fn parses_binary_modules() {
+        let text = r#"(module binary "\00asm" "\01\00\00\00")"#;
+        assert_eq!(0, parse_module(text).unwrap().funcs().len());
+    }
+
+    #[test]
+    pub fn reports_malformed_binary_modules() {
+        let error = |bytes: &str| {
+            let text = format!(r#"(module binary "\00asm" "\01\00\00\00" "{}")"#, bytes);
+            parse_module(&text).unwrap_err().message().to_owned()
+        };
+        let magic = parse_module(r#"(module binary "\00asn" "\01\00\00\00")"#).unwrap_err();
+        assert_eq!("magic header not detected", magic.message());
+        assert_eq!("malformed section id", error(r"\0c\00"));
+        assert_eq!("section size mismatch", error(r"\01\02\00\00"));
+        assert_eq!("unexpected end", error(r"\01\02\01"));
+
+        // Section sizes are u32s, which take at most 5 bytes
+        assert_eq!(
+            "integer representation too long",
+            error(r"\01\80\80\80\80\80\00")
+        );
+        assert_eq!("integer too large", error(r"\01\80\80\80\80\10"));
+        let padded = r#"(module binary "\00asm" "\01\00\00\00" "\01\81\80\80\80\00\00")"#;
+        assert!(parse_module(padded).is_ok());
+
+        // A global initialized with (i32.const -1), in 5 bytes and then with invalid encodings
+        let global = |leb: &str| format!(r"\06\{:02x}\01\7f\00\41{}\0b", 5 + leb.len() / 3, leb);
+        let text = format!(
+            r#"(module binary "\00asm" "\01\00\00\00" "{}")"#,
+            global(r"\ff\ff\ff\ff\7f")
+        );
+        assert!(parse_module(&text).is_ok());
+        assert_eq!("integer too large", error(&global(r"\ff\ff\ff\ff\4f")));
+        assert_eq!(
+            "integer representation too long",
+            error(&global(r"\ff\ff\ff\ff\ff\7f"))
+        );
+    }
+
+    #[test]
+    pub fn runs_unreachable() {
+        let module = parse_module("(func (export \"test\") unreachable)").unwrap();
+        let trap = invoke(module, "test", vec![]).unwrap_err();
+        assert!(*trap.cause() == TrapCause::Unreachable);
+    }
+
+    #[test]
+    pub fn reports_error_positions() {
+        let err = parse_module("(module\n  (func (call $missing)))").unwrap_err();
+        assert_eq!(Position::new(2, 15), err.position());
+        assert_eq!("unknown function $missing", err.message());
+
+        let err = parse_module("(module (func i32.bogus))").unwrap_err();
+        assert_eq!("unknown operator i32.bogus", err.message());
+    }
+}
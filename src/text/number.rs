@@ -0,0 +1,280 @@
+//! Parsing of the numeric literals allowed by the text format.
+
+/// Splits an optional sign off the front of a literal, returning `true` if it is negative.
+fn split_sign(s: &str) -> (bool, &str) {
+    if let Some(rest) = s.strip_prefix('-') {
+        (true, rest)
+    } else if let Some(rest) = s.strip_prefix('+') {
+        (false, rest)
+    } else {
+        (false, s)
+    }
+}
+
+/// Removes the '_' separators from a literal, rejecting misplaced ones.
+fn strip_underscores(s: &str) -> Option<String> {
+    if s.starts_with('_') || s.ends_with('_') || s.contains("__") {
+        None
+    } else {
+        Some(s.replace('_', ""))
+    }
+}
+
+/// Parses an unsigned integer literal, without a sign.
+fn parse_magnitude(s: &str) -> Option<u64> {
+    let s = strip_underscores(s)?;
+    let (digits, radix) = match s.strip_prefix("0x") {
+        Some(hex) => (hex, 16),
+        None => (&s[..], 10),
+    };
+    if digits.is_empty() || !digits.chars().all(|c| c.is_digit(radix)) {
+        return None;
+    }
+    u64::from_str_radix(digits, radix).ok()
+}
+
+/// Parses an unsigned index, alignment or offset.
+pub fn parse_u32(s: &str) -> Option<u32> {
+    if s.starts_with('+') || s.starts_with('-') {
+        return None;
+    }
+    parse_magnitude(s).and_then(|v| {
+        if v <= u32::MAX as u64 {
+            Some(v as u32)
+        } else {
+            None
+        }
+    })
+}
+
+/// Parses an `i32` literal, which may be written as a signed or unsigned value.
+pub fn parse_i32(s: &str) -> Option<u32> {
+    let (negative, rest) = split_sign(s);
+    let magnitude = parse_magnitude(rest)?;
+    if negative {
+        if magnitude <= 1 << 31 {
+            Some((magnitude as u32).wrapping_neg())
+        } else {
+            None
+        }
+    } else if magnitude <= u32::MAX as u64 {
+        Some(magnitude as u32)
+    } else {
+        None
+    }
+}
+
+/// Parses an `i64` literal, which may be written as a signed or unsigned value.
+pub fn parse_i64(s: &str) -> Option<u64> {
+    let (negative, rest) = split_sign(s);
+    let magnitude = parse_magnitude(rest)?;
+    if negative {
+        if magnitude <= 1 << 63 {
+            Some(magnitude.wrapping_neg())
+        } else {
+            None
+        }
+    } else {
+        Some(magnitude)
+    }
+}
+
+pub fn parse_f32(s: &str) -> Option<f32> {
+    parse_float(s, 23, 8, |s| {
+        s.parse::<f32>().ok().map(|f| f.to_bits() as u64)
+    })
+    .map(|bits| f32::from_bits(bits as u32))
+}
+
+pub fn parse_f64(s: &str) -> Option<f64> {
+    parse_float(s, 52, 11, |s| s.parse::<f64>().ok().map(|f| f.to_bits())).map(f64::from_bits)
+}
+
+/// Parses a float literal into the bits of an IEEE 754 value with the specified number of
+/// mantissa and exponent bits. Decimal literals are handed to `parse_decimal`, which must
+/// round correctly.
+fn parse_float<F>(s: &str, mant_bits: u32, exp_bits: u32, parse_decimal: F) -> Option<u64>
+where
+    F: Fn(&str) -> Option<u64>,
+{
+    let (negative, rest) = split_sign(s);
+    let sign_bit = if negative {
+        1 << (mant_bits + exp_bits)
+    } else {
+        0
+    };
+    let exp_mask = (1 << exp_bits) - 1;
+    let infinity = exp_mask << mant_bits;
+
+    let magnitude = if rest == "inf" {
+        infinity
+    } else if rest == "nan" {
+        // The canonical NaN has only the most significant mantissa bit set
+        infinity | (1 << (mant_bits - 1))
+    } else if let Some(payload) = rest.strip_prefix("nan:0x") {
+        let payload = parse_magnitude(&format!("0x{}", payload))?;
+        if payload == 0 || payload >= (1 << mant_bits) {
+            return None;
+        }
+        infinity | payload
+    } else if let Some(hex) = rest.strip_prefix("0x") {
+        parse_hex_float(&strip_underscores(hex)?, mant_bits, exp_bits)?
+    } else {
+        let decimal = strip_underscores(rest)?;
+        if !decimal.starts_with(|c: char| c.is_ascii_digit())
+            || !decimal.chars().all(|c| {
+                c.is_ascii_digit() || c == '.' || c == 'e' || c == 'E' || c == '+' || c == '-'
+            })
+        {
+            return None;
+        }
+        let bits = parse_decimal(&decimal)?;
+        if bits == infinity {
+            // The literal is out of range
+            return None;
+        }
+        bits
+    };
+    Some(sign_bit | magnitude)
+}
+
+/// Parses the digits of a hexadecimal float (after the '0x'), rounding to nearest-even.
+fn parse_hex_float(s: &str, mant_bits: u32, exp_bits: u32) -> Option<u64> {
+    let (digits, exp) = match s.find(['p', 'P']) {
+        Some(idx) => {
+            let (sign, exp) = split_sign(&s[idx + 1..]);
+            if exp.is_empty() || !exp.chars().all(|c| c.is_ascii_digit()) {
+                return None;
+            }
+            // Clamp huge exponents, they overflow or underflow either way
+            let exp = exp.parse::<i64>().unwrap_or(100_000).min(100_000);
+            (&s[..idx], if sign { -exp } else { exp })
+        }
+        None => (s, 0),
+    };
+
+    let (int_part, frac_part) = match digits.find('.') {
+        Some(idx) => (&digits[..idx], &digits[idx + 1..]),
+        None => (digits, ""),
+    };
+    if int_part.is_empty() {
+        return None;
+    }
+
+    // Accumulate up to 60 bits of significand, remembering if any non-zero bits were dropped
+    let mut sig: u64 = 0;
+    let mut exp = exp;
+    let mut sticky = false;
+    for (i, c) in int_part.chars().chain(frac_part.chars()).enumerate() {
+        let d = c.to_digit(16)? as u64;
+        let is_frac = i >= int_part.len();
+        if sig >> 56 == 0 {
+            sig = sig * 16 + d;
+            if is_frac {
+                exp -= 4;
+            }
+        } else {
+            sticky |= d != 0;
+            if !is_frac {
+                exp += 4;
+            }
+        }
+    }
+
+    if sig == 0 {
+        return Some(0);
+    }
+
+    let bias = (1i64 << (exp_bits - 1)) - 1;
+    let emin = 1 - bias;
+    let msb = 63 - sig.leading_zeros() as i64;
+    let e = msb + exp;
+    if e > bias {
+        return None;
+    }
+
+    // The exponent of the least significant bit in the result
+    let lsb_exp = e.max(emin) - mant_bits as i64;
+    let shift = lsb_exp - exp;
+    let mut mant: u64 = if shift <= 0 {
+        sig << (-shift)
+    } else if shift >= 64 {
+        0
+    } else {
+        let rem = sig & ((1u64 << shift) - 1);
+        let half = 1u64 << (shift - 1);
+        let mant = sig >> shift;
+        if rem > half || (rem == half && (sticky || mant & 1 == 1)) {
+            mant + 1
+        } else {
+            mant
+        }
+    };
+
+    let mut lsb_exp = lsb_exp;
+    if mant >> (mant_bits + 1) != 0 {
+        // Rounding carried into a new bit
+        mant >>= 1;
+        lsb_exp += 1;
+    }
+
+    if mant >> mant_bits == 0 {
+        // Subnormal (or zero)
+        Some(mant)
+    } else {
+        let biased = lsb_exp + mant_bits as i64 + bias;
+        if biased >= (1 << exp_bits) - 1 {
+            return None;
+        }
+        Some(((biased as u64) << mant_bits) | (mant & ((1 << mant_bits) - 1)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn parses_integers() {
+        assert_eq!(Some(0xFFFF_FFFF), parse_i32("-1"));
+        assert_eq!(Some(0xFFFF_FFFF), parse_i32("0xffff_ffff"));
+        assert_eq!(Some(0x8000_0000), parse_i32("-2147483648"));
+        assert_eq!(None, parse_i32("4294967296"));
+        assert_eq!(None, parse_i32("1__0"));
+        assert_eq!(
+            Some(0x8000_0000_0000_0000),
+            parse_i64("-0x8000000000000000")
+        );
+        assert_eq!(None, parse_u32("-1"));
+    }
+
+    #[test]
+    pub fn parses_decimal_floats() {
+        assert_eq!(Some(1.5), parse_f32("1.5"));
+        assert_eq!(Some((-0.0f64).to_bits()), parse_f64("-0").map(f64::to_bits));
+        assert_eq!(Some(1e10), parse_f64("1e10"));
+        assert_eq!(None, parse_f32("1e39"));
+    }
+
+    #[test]
+    pub fn parses_hex_floats() {
+        assert_eq!(Some(12.0), parse_f64("0x1.8p3"));
+        assert_eq!(Some(f32::from_bits(1)), parse_f32("0x1p-149"));
+        assert_eq!(Some(f32::MAX), parse_f32("0x1.fffffep127"));
+        assert_eq!(None, parse_f32("0x1p128"));
+        // Halfway between 1 and the next float rounds to even
+        assert_eq!(Some(1.0), parse_f32("0x1.000001p0"));
+        assert_eq!(Some(f32::from_bits(0x3f80_0002)), parse_f32("0x1.000003p0"));
+    }
+
+    #[test]
+    pub fn parses_special_floats() {
+        assert_eq!(Some(f32::INFINITY), parse_f32("inf"));
+        assert_eq!(Some(0x7fc0_0000), parse_f32("nan").map(f32::to_bits));
+        assert_eq!(Some(0xff80_0001), parse_f32("-nan:0x1").map(f32::to_bits));
+        assert_eq!(
+            Some(0x7ff8_0000_0000_0000),
+            parse_f64("nan").map(f64::to_bits)
+        );
+    }
+}
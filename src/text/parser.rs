@@ -0,0 +1,1165 @@
+use std::{collections::HashMap, io::Cursor as IoCursor};
+
+use crate::{
+    builder::{FuncBuilder, ModuleBuilder, TypeUse},
+    module::{
+        DataItem, ElemItem, ElemType, Export, ExportDesc, Expr, FuncType, Global, GlobalType,
+        Import, MemberDesc, MemoryType, Module, ModuleNames, TableType,
+    },
+    reader::Reader,
+    text::{number, sexpr::SExpr, ParseError, Position},
+    BranchTable, Error, Instruction, ValType, Value, PAGE_SIZE,
+};
+
+/// Parses a `(module ...)` expression.
+///
+/// ## Returns
+/// The module's identifier (if any) and the parsed module.
+pub fn parse_module_expr(expr: &SExpr) -> Result<(Option<String>, Module), ParseError> {
+    let items = match expr.as_list() {
+        Some(items) if expr.is_list_of("module") => items,
+        _ => return Err(ParseError::new("expected '(module ...)'", expr.position())),
+    };
+
+    let mut cursor = Cursor::new(&items[1..], expr.position());
+    let id = cursor.take_id().map(|id| id.to_owned());
+
+    let module = match cursor.peek_atom() {
+        Some("binary") => {
+            cursor.take();
+            let bytes = cursor.take_strings();
+            cursor.expect_end()?;
+            let reader = Reader::new(IoCursor::new(bytes));
+            Module::load(reader)
+                .map_err(|e| ParseError::new(binary_error_message(e), expr.position()))?
+        }
+        Some("quote") => {
+            cursor.take();
+            let bytes = cursor.take_strings();
+            cursor.expect_end()?;
+            let text = match String::from_utf8(bytes) {
+                Ok(text) => text,
+                Err(_) => return Err(ParseError::new("malformed UTF-8 encoding", expr.position())),
+            };
+            let fields = super::sexpr::parse(&text)?;
+            parse_fields(&fields, id.as_ref().map(|id| &id[..]))?
+        }
+        _ => parse_fields(cursor.rest(), id.as_ref().map(|id| &id[..]))?,
+    };
+    Ok((id, module))
+}
+
+/// Describes an error loading a binary module, with the spec's message where there is one.
+fn binary_error_message(e: Error) -> String {
+    match e {
+        Error::InvalidMagic => "magic header not detected".to_owned(),
+        Error::UnsupportedVersion { .. } => "unknown binary version".to_owned(),
+        Error::UnknownSection(_) => "malformed section id".to_owned(),
+        Error::SectionSizeMismatch => "section size mismatch".to_owned(),
+        Error::IntegerTooLong => "integer representation too long".to_owned(),
+        Error::IntegerTooLarge => "integer too large".to_owned(),
+        Error::Utf8Error(_) => "malformed UTF-8 encoding".to_owned(),
+        Error::IoError(_) => "unexpected end".to_owned(),
+        e => format!("malformed binary module: {:?}", e),
+    }
+}
+
+/// Parses a sequence of module fields into a [`Module`].
+pub fn parse_fields(fields: &[SExpr], id: Option<&str>) -> Result<Module, ParseError> {
+    let mut parser = ModuleParser::new();
+    if let Some(id) = id {
+        parser.names.set_module_name(&id[1..]);
+    }
+
+    parser.declare(fields)?;
+    for field in fields {
+        parser.parse_field(field)?;
+    }
+    Ok(parser.build())
+}
+
+#[derive(Default)]
+struct IndexSpace {
+    count: u32,
+    ids: HashMap<String, u32>,
+}
+
+impl IndexSpace {
+    fn declare(&mut self, id: Option<&str>, pos: Position) -> Result<(), ParseError> {
+        if let Some(id) = id {
+            if self.ids.insert(id.to_owned(), self.count).is_some() {
+                return Err(ParseError::new(format!("duplicate identifier {}", id), pos));
+            }
+        }
+        self.count += 1;
+        Ok(())
+    }
+
+    fn resolve(&self, atom: &str, pos: Position, what: &str) -> Result<u32, ParseError> {
+        if atom.starts_with('$') {
+            match self.ids.get(atom) {
+                Some(idx) => Ok(*idx),
+                None => Err(ParseError::new(format!("unknown {} {}", what, atom), pos)),
+            }
+        } else {
+            number::parse_u32(atom)
+                .ok_or_else(|| ParseError::new(format!("invalid {} index", what), pos))
+        }
+    }
+}
+
+/// The identifiers in scope while parsing a function body (or a constant expression).
+#[derive(Default)]
+struct FuncScope {
+    locals: IndexSpace,
+    labels: Vec<Option<String>>,
+}
+
+impl FuncScope {
+    fn resolve_label(&self, atom: &str, pos: Position) -> Result<u32, ParseError> {
+        if atom.starts_with('$') {
+            match self
+                .labels
+                .iter()
+                .rev()
+                .position(|l| l.as_ref().map(|l| &l[..]) == Some(atom))
+            {
+                Some(depth) => Ok(depth as u32),
+                None => Err(ParseError::new(format!("unknown label {}", atom), pos)),
+            }
+        } else {
+            number::parse_u32(atom).ok_or_else(|| ParseError::new("invalid label", pos))
+        }
+    }
+}
+
+struct ModuleParser {
+    builder: ModuleBuilder,
+    names: ModuleNames,
+    types: IndexSpace,
+    funcs: IndexSpace,
+    tables: IndexSpace,
+    mems: IndexSpace,
+    globals: IndexSpace,
+    /// The number of functions defined so far, used to detect imports following definitions.
+    defined: usize,
+}
+
+impl ModuleParser {
+    fn new() -> ModuleParser {
+        ModuleParser {
+            builder: ModuleBuilder::new(),
+            names: ModuleNames::new(),
+            types: IndexSpace::default(),
+            funcs: IndexSpace::default(),
+            tables: IndexSpace::default(),
+            mems: IndexSpace::default(),
+            globals: IndexSpace::default(),
+            defined: 0,
+        }
+    }
+
+    fn build(mut self) -> Module {
+        self.builder.names = Some(self.names);
+        self.builder.build()
+    }
+
+    /// Assigns indices to every identifier declared by the module fields, and defines the
+    /// explicit types so that they occupy the first indices in the type index space.
+    fn declare(&mut self, fields: &[SExpr]) -> Result<(), ParseError> {
+        for field in fields {
+            let items = field_items(field)?;
+            let mut cursor = Cursor::new(&items[1..], field.position());
+            match items[0].as_atom() {
+                Some("type") => {
+                    let id = cursor.take_id();
+                    self.types.declare(id, field.position())?;
+                    let typ = match cursor.take_list_of("func") {
+                        Some(func) => {
+                            let mut func = Cursor::new(func, field.position());
+                            let (typ, _) = parse_func_type(&mut func)?;
+                            func.expect_end()?;
+                            typ
+                        }
+                        None => {
+                            return Err(ParseError::new("expected '(func ...)'", cursor.position()))
+                        }
+                    };
+                    cursor.expect_end()?;
+                    self.builder.types.push(typ);
+                }
+                Some("import") => {
+                    cursor.expect_string()?;
+                    cursor.expect_string()?;
+                    let desc = match cursor.take() {
+                        Some(desc) => desc,
+                        None => {
+                            return Err(ParseError::new(
+                                "expected import description",
+                                cursor.position(),
+                            ))
+                        }
+                    };
+                    let kind = desc.head().unwrap_or("");
+                    let desc_items = field_items(desc)?;
+                    let id = Cursor::new(&desc_items[1..], desc.position()).take_id();
+                    self.space_mut(kind, desc.position())?
+                        .declare(id, desc.position())?;
+                }
+                Some(kind @ "func")
+                | Some(kind @ "table")
+                | Some(kind @ "memory")
+                | Some(kind @ "global") => {
+                    let id = cursor.take_id();
+                    self.space_mut(kind, field.position())?
+                        .declare(id, field.position())?;
+                }
+                Some("export") | Some("start") | Some("elem") | Some("data") => {}
+                _ => return Err(ParseError::new("unknown module field", field.position())),
+            }
+        }
+        Ok(())
+    }
+
+    fn space_mut(&mut self, kind: &str, pos: Position) -> Result<&mut IndexSpace, ParseError> {
+        match kind {
+            "func" => Ok(&mut self.funcs),
+            "table" => Ok(&mut self.tables),
+            "memory" => Ok(&mut self.mems),
+            "global" => Ok(&mut self.globals),
+            _ => Err(ParseError::new("unknown import kind", pos)),
+        }
+    }
+
+    fn parse_field(&mut self, field: &SExpr) -> Result<(), ParseError> {
+        let items = field_items(field)?;
+        let mut cursor = Cursor::new(&items[1..], field.position());
+        match items[0].as_atom() {
+            Some("type") => Ok(()),
+            Some("import") => self.parse_import(&mut cursor),
+            Some("func") => self.parse_func(&mut cursor),
+            Some("table") => self.parse_table(&mut cursor),
+            Some("memory") => self.parse_memory(&mut cursor),
+            Some("global") => self.parse_global(&mut cursor),
+            Some("export") => self.parse_export(&mut cursor),
+            Some("start") => {
+                let (atom, pos) = cursor.expect_atom()?;
+                let func_idx = self.funcs.resolve(atom, pos, "function")?;
+                cursor.expect_end()?;
+                self.builder.start = Some(func_idx as usize);
+                Ok(())
+            }
+            Some("elem") => self.parse_elem(&mut cursor),
+            Some("data") => self.parse_data(&mut cursor),
+            _ => Err(ParseError::new("unknown module field", field.position())),
+        }
+    }
+
+    fn add_import(
+        &mut self,
+        module: String,
+        name: String,
+        desc: MemberDesc,
+        pos: Position,
+    ) -> Result<(), ParseError> {
+        let defined = self.defined
+            + self.builder.tables.len()
+            + self.builder.mems.len()
+            + self.builder.globals.len();
+        if defined > 0 {
+            return Err(ParseError::new(
+                "imports must occur before all definitions",
+                pos,
+            ));
+        }
+        self.builder.imports.push(Import::new(module, name, desc));
+        Ok(())
+    }
+
+    fn parse_import(&mut self, cursor: &mut Cursor) -> Result<(), ParseError> {
+        let module = cursor.expect_name()?;
+        let name = cursor.expect_name()?;
+        let desc = match cursor.take() {
+            Some(desc) => desc,
+            None => {
+                return Err(ParseError::new(
+                    "expected import description",
+                    cursor.position(),
+                ))
+            }
+        };
+        cursor.expect_end()?;
+
+        let desc_items = field_items(desc)?;
+        let mut desc_cursor = Cursor::new(&desc_items[1..], desc.position());
+        let id = desc_cursor.take_id();
+        let member = match desc.head() {
+            Some("func") => {
+                let func_idx = self.imported_funcs();
+                let (type_idx, param_ids) = self.parse_type_use(&mut desc_cursor)?;
+                self.name_func(func_idx, id, &param_ids);
+                MemberDesc::Function(type_idx as usize)
+            }
+            Some("table") => MemberDesc::Table(parse_table_type(&mut desc_cursor)?),
+            Some("memory") => MemberDesc::Memory(parse_memory_type(&mut desc_cursor)?),
+            Some("global") => MemberDesc::Global(parse_global_type(&mut desc_cursor)?),
+            _ => return Err(ParseError::new("unknown import kind", desc.position())),
+        };
+        desc_cursor.expect_end()?;
+        self.add_import(module, name, member, desc.position())
+    }
+
+    /// Gets the number of functions imported so far, which is the index of the next import.
+    fn imported_funcs(&self) -> usize {
+        self.builder
+            .imports
+            .iter()
+            .filter(|i| matches!(i.description(), MemberDesc::Function(_)))
+            .count()
+    }
+
+    fn name_func(&mut self, func_idx: usize, id: Option<&str>, local_ids: &[Option<String>]) {
+        if let Some(id) = id {
+            self.names.set_func_name(func_idx, &id[1..]);
+        }
+        for (local_idx, local_id) in local_ids.iter().enumerate() {
+            if let Some(local_id) = local_id {
+                self.names
+                    .set_local_name(func_idx, local_idx, &local_id[1..]);
+            }
+        }
+    }
+
+    /// Parses inline `(export "name")` abbreviations, returning the names.
+    fn parse_inline_exports(cursor: &mut Cursor) -> Result<Vec<String>, ParseError> {
+        let mut exports = Vec::new();
+        while let Some(items) = cursor.take_list_of("export") {
+            let mut export = Cursor::new(items, cursor.position());
+            exports.push(export.expect_name()?);
+            export.expect_end()?;
+        }
+        Ok(exports)
+    }
+
+    /// Parses an inline `(import "module" "name")` abbreviation.
+    fn parse_inline_import(cursor: &mut Cursor) -> Result<Option<(String, String)>, ParseError> {
+        match cursor.take_list_of("import") {
+            Some(items) => {
+                let mut import = Cursor::new(items, cursor.position());
+                let module = import.expect_name()?;
+                let name = import.expect_name()?;
+                import.expect_end()?;
+                Ok(Some((module, name)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn add_exports(&mut self, names: Vec<String>, desc: ExportDesc) {
+        for name in names {
+            self.builder.exports.push(Export::new(name, desc.clone()));
+        }
+    }
+
+    fn parse_func(&mut self, cursor: &mut Cursor) -> Result<(), ParseError> {
+        let pos = cursor.position();
+        let id = cursor.take_id();
+        let exports = Self::parse_inline_exports(cursor)?;
+        let import = Self::parse_inline_import(cursor)?;
+        let (type_idx, mut local_ids) = self.parse_type_use(cursor)?;
+
+        let func_idx = match import {
+            Some((module, name)) => {
+                let func_idx = self.imported_funcs();
+                cursor.expect_end()?;
+                self.add_import(module, name, MemberDesc::Function(type_idx as usize), pos)?;
+                func_idx
+            }
+            None => {
+                let mut locals = Vec::new();
+                while let Some(items) = cursor.take_list_of("local") {
+                    let mut local = Cursor::new(items, pos);
+                    match local.take_id() {
+                        Some(local_id) => {
+                            locals.push(local.expect_val_type()?);
+                            local_ids.push(Some(local_id.to_owned()));
+                        }
+                        None => {
+                            while !local.is_empty() {
+                                locals.push(local.expect_val_type()?);
+                                local_ids.push(None);
+                            }
+                        }
+                    }
+                    local.expect_end()?;
+                }
+
+                let mut scope = FuncScope::default();
+                for local_id in local_ids.iter() {
+                    scope
+                        .locals
+                        .declare(local_id.as_ref().map(|l| &l[..]), pos)?;
+                }
+                let mut body = Vec::new();
+                self.parse_instrs(cursor.rest(), &mut scope, &mut body)?;
+
+                let mut func = FuncBuilder::new().type_id(type_idx as usize);
+                func.locals = locals;
+                func.body = body;
+                self.defined += 1;
+                self.builder.add_func(func)
+            }
+        };
+
+        self.name_func(func_idx, id, &local_ids);
+        self.add_exports(exports, ExportDesc::Function(func_idx));
+        Ok(())
+    }
+
+    fn parse_table(&mut self, cursor: &mut Cursor) -> Result<(), ParseError> {
+        let pos = cursor.position();
+        cursor.take_id();
+        let table_idx = self.tables_declared();
+        let exports = Self::parse_inline_exports(cursor)?;
+
+        if let Some((module, name)) = Self::parse_inline_import(cursor)? {
+            let table = parse_table_type(cursor)?;
+            cursor.expect_end()?;
+            self.add_import(module, name, MemberDesc::Table(table), pos)?;
+        } else if matches!(cursor.peek_atom(), Some("funcref") | Some("anyfunc")) {
+            // (table funcref (elem ...)) defines a table exactly large enough for the elements
+            cursor.take();
+            let elems = match cursor.take_list_of("elem") {
+                Some(elems) => elems,
+                None => return Err(ParseError::new("expected '(elem ...)'", cursor.position())),
+            };
+            cursor.expect_end()?;
+            let init = self.parse_func_indices(&mut Cursor::new(elems, pos))?;
+            self.builder.tables.push(TableType::new(
+                ElemType::AnyFunc,
+                init.len(),
+                Some(init.len()),
+            ));
+            self.builder.elems.push(ElemItem::new(
+                table_idx,
+                Expr::new(vec![Instruction::I32Const(Value::I32(0))]),
+                init,
+            ));
+        } else {
+            let table = parse_table_type(cursor)?;
+            cursor.expect_end()?;
+            self.builder.tables.push(table);
+        }
+
+        self.add_exports(exports, ExportDesc::Table(table_idx));
+        Ok(())
+    }
+
+    fn tables_declared(&self) -> usize {
+        let imported = self
+            .builder
+            .imports
+            .iter()
+            .filter(|i| matches!(i.description(), MemberDesc::Table(_)))
+            .count();
+        imported + self.builder.tables.len()
+    }
+
+    fn parse_memory(&mut self, cursor: &mut Cursor) -> Result<(), ParseError> {
+        let pos = cursor.position();
+        cursor.take_id();
+        let mem_idx = self
+            .builder
+            .imports
+            .iter()
+            .filter(|i| matches!(i.description(), MemberDesc::Memory(_)))
+            .count()
+            + self.builder.mems.len();
+        let exports = Self::parse_inline_exports(cursor)?;
+
+        match Self::parse_inline_import(cursor)? {
+            Some((module, name)) => {
+                let mem = parse_memory_type(cursor)?;
+                cursor.expect_end()?;
+                self.add_import(module, name, MemberDesc::Memory(mem), pos)?;
+            }
+            None => match cursor.take_list_of("data") {
+                // (memory (data ...)) defines a memory exactly large enough for the data
+                Some(data) => {
+                    cursor.expect_end()?;
+                    let mut data = Cursor::new(data, pos);
+                    let init = data.take_strings();
+                    data.expect_end()?;
+                    let pages = init.len().div_ceil(PAGE_SIZE);
+                    self.builder.mems.push(MemoryType::new(pages, Some(pages)));
+                    self.builder.data.push(DataItem::new(
+                        mem_idx,
+                        Expr::new(vec![Instruction::I32Const(Value::I32(0))]),
+                        init,
+                    ));
+                }
+                None => {
+                    let mem = parse_memory_type(cursor)?;
+                    cursor.expect_end()?;
+                    self.builder.mems.push(mem);
+                }
+            },
+        }
+
+        self.add_exports(exports, ExportDesc::Memory(mem_idx));
+        Ok(())
+    }
+
+    fn parse_global(&mut self, cursor: &mut Cursor) -> Result<(), ParseError> {
+        let pos = cursor.position();
+        cursor.take_id();
+        let exports = Self::parse_inline_exports(cursor)?;
+        let import = Self::parse_inline_import(cursor)?;
+        let typ = parse_global_type(cursor)?;
+
+        let global_idx = match import {
+            Some((module, name)) => {
+                cursor.expect_end()?;
+                let global_idx = self
+                    .builder
+                    .imports
+                    .iter()
+                    .filter(|i| matches!(i.description(), MemberDesc::Global(_)))
+                    .count();
+                self.add_import(module, name, MemberDesc::Global(typ), pos)?;
+                global_idx
+            }
+            None => {
+                let init = self.parse_const_expr(cursor.rest())?;
+                self.builder.add_global(Global::new(typ, init))
+            }
+        };
+
+        self.add_exports(exports, ExportDesc::Global(global_idx));
+        Ok(())
+    }
+
+    fn parse_export(&mut self, cursor: &mut Cursor) -> Result<(), ParseError> {
+        let name = cursor.expect_name()?;
+        let desc = match cursor.take() {
+            Some(desc) => desc,
+            None => {
+                return Err(ParseError::new(
+                    "expected export description",
+                    cursor.position(),
+                ))
+            }
+        };
+        cursor.expect_end()?;
+
+        let items = field_items(desc)?;
+        let mut desc_cursor = Cursor::new(&items[1..], desc.position());
+        let (atom, pos) = desc_cursor.expect_atom()?;
+        desc_cursor.expect_end()?;
+        let desc = match desc.head() {
+            Some("func") => {
+                ExportDesc::Function(self.funcs.resolve(atom, pos, "function")? as usize)
+            }
+            Some("table") => ExportDesc::Table(self.tables.resolve(atom, pos, "table")? as usize),
+            Some("memory") => ExportDesc::Memory(self.mems.resolve(atom, pos, "memory")? as usize),
+            Some("global") => {
+                ExportDesc::Global(self.globals.resolve(atom, pos, "global")? as usize)
+            }
+            _ => return Err(ParseError::new("unknown export kind", desc.position())),
+        };
+        self.builder.exports.push(Export::new(name, desc));
+        Ok(())
+    }
+
+    fn parse_elem(&mut self, cursor: &mut Cursor) -> Result<(), ParseError> {
+        let table_idx = match cursor.peek() {
+            Some(SExpr::Atom(atom, pos)) => {
+                cursor.take();
+                self.tables.resolve(atom, *pos, "table")?
+            }
+            _ => 0,
+        };
+        let offset = self.parse_offset(cursor)?;
+        let init = self.parse_func_indices(cursor)?;
+        self.builder
+            .elems
+            .push(ElemItem::new(table_idx as usize, offset, init));
+        Ok(())
+    }
+
+    fn parse_data(&mut self, cursor: &mut Cursor) -> Result<(), ParseError> {
+        let mem_idx = match cursor.peek() {
+            Some(SExpr::Atom(atom, pos)) => {
+                cursor.take();
+                self.mems.resolve(atom, *pos, "memory")?
+            }
+            _ => 0,
+        };
+        let offset = self.parse_offset(cursor)?;
+        let init = cursor.take_strings();
+        cursor.expect_end()?;
+        self.builder
+            .data
+            .push(DataItem::new(mem_idx as usize, offset, init));
+        Ok(())
+    }
+
+    /// Parses an `(offset instr*)` expression, or its abbreviation as a single folded instruction.
+    fn parse_offset(&mut self, cursor: &mut Cursor) -> Result<Expr, ParseError> {
+        if let Some(items) = cursor.take_list_of("offset") {
+            self.parse_const_expr(items)
+        } else {
+            match cursor.take() {
+                Some(expr @ SExpr::List(..)) => self.parse_const_expr(std::slice::from_ref(expr)),
+                _ => Err(ParseError::new(
+                    "expected offset expression",
+                    cursor.position(),
+                )),
+            }
+        }
+    }
+
+    fn parse_func_indices(&mut self, cursor: &mut Cursor) -> Result<Vec<usize>, ParseError> {
+        let mut init = Vec::new();
+        while !cursor.is_empty() {
+            let (atom, pos) = cursor.expect_atom()?;
+            init.push(self.funcs.resolve(atom, pos, "function")? as usize);
+        }
+        Ok(init)
+    }
+
+    fn parse_const_expr(&mut self, items: &[SExpr]) -> Result<Expr, ParseError> {
+        let mut insts = Vec::new();
+        self.parse_instrs(items, &mut FuncScope::default(), &mut insts)?;
+        Ok(Expr::new(insts))
+    }
+
+    /// Parses a type use, `(type idx)? (param ...)* (result ...)*`, adding a new type if needed.
+    ///
+    /// ## Returns
+    /// The type index and the identifiers of the parameters.
+    fn parse_type_use(
+        &mut self,
+        cursor: &mut Cursor,
+    ) -> Result<(u32, Vec<Option<String>>), ParseError> {
+        let explicit = match cursor.take_list_of("type") {
+            Some(items) => {
+                let mut type_ref = Cursor::new(items, cursor.position());
+                let (atom, pos) = type_ref.expect_atom()?;
+                type_ref.expect_end()?;
+                Some((self.types.resolve(atom, pos, "type")?, pos))
+            }
+            None => None,
+        };
+        let (typ, param_ids) = parse_func_type(cursor)?;
+
+        match explicit {
+            Some((type_idx, pos)) => {
+                // Inline parameters and results, if present, must match the referenced type
+                let has_inline = !typ.params().is_empty() || !typ.results().is_empty();
+                match self.builder.types.get(type_idx as usize) {
+                    Some(existing) if has_inline && *existing != typ => {
+                        Err(ParseError::new("inline function type does not match", pos))
+                    }
+                    Some(existing) => {
+                        let param_ids = if has_inline {
+                            param_ids
+                        } else {
+                            vec![None; existing.params().len()]
+                        };
+                        Ok((type_idx, param_ids))
+                    }
+                    None => Err(ParseError::new("unknown type", pos)),
+                }
+            }
+            None => {
+                let mut type_use = TypeUse::new();
+                type_use.params = typ.params().to_vec();
+                type_use.results = typ.results().to_vec();
+                Ok((self.builder.add_type(type_use) as u32, param_ids))
+            }
+        }
+    }
+
+    fn parse_instrs(
+        &mut self,
+        items: &[SExpr],
+        scope: &mut FuncScope,
+        out: &mut Vec<Instruction>,
+    ) -> Result<(), ParseError> {
+        let mut cursor = Cursor::new(
+            items,
+            items.first().map_or(Position::new(0, 0), |i| i.position()),
+        );
+        while let Some(item) = cursor.take() {
+            match item {
+                SExpr::List(..) => self.parse_folded(item, scope, out)?,
+                SExpr::Atom(keyword, pos) => match &keyword[..] {
+                    "block" | "loop" | "if" => {
+                        let label = cursor.take_id().map(|l| l.to_owned());
+                        let typ = parse_block_type(&mut cursor)?;
+                        scope.labels.push(label);
+                        out.push(block_instruction(keyword, typ));
+                    }
+                    "else" | "end" => {
+                        let label = cursor.take_id();
+                        let current = match scope.labels.last() {
+                            Some(current) => current,
+                            None => {
+                                return Err(ParseError::new(
+                                    format!("unexpected '{}'", keyword),
+                                    *pos,
+                                ))
+                            }
+                        };
+                        if label.is_some() && current.as_ref().map(|l| &l[..]) != label {
+                            return Err(ParseError::new("mismatching label", *pos));
+                        }
+                        if keyword == "else" {
+                            out.push(Instruction::Else);
+                        } else {
+                            scope.labels.pop();
+                            out.push(Instruction::End);
+                        }
+                    }
+                    _ => {
+                        let inst = self.parse_op(keyword, *pos, &mut cursor, scope)?;
+                        out.push(inst);
+                    }
+                },
+                SExpr::Str(_, pos) => return Err(ParseError::new("unexpected string", *pos)),
+            }
+        }
+        Ok(())
+    }
+
+    fn parse_folded(
+        &mut self,
+        expr: &SExpr,
+        scope: &mut FuncScope,
+        out: &mut Vec<Instruction>,
+    ) -> Result<(), ParseError> {
+        let items = field_items(expr)?;
+        let pos = expr.position();
+        let keyword = match items[0].as_atom() {
+            Some(keyword) => keyword,
+            None => return Err(ParseError::new("expected instruction", pos)),
+        };
+        let mut cursor = Cursor::new(&items[1..], pos);
+
+        match keyword {
+            "block" | "loop" => {
+                let label = cursor.take_id().map(|l| l.to_owned());
+                let typ = parse_block_type(&mut cursor)?;
+                out.push(block_instruction(keyword, typ));
+                scope.labels.push(label);
+                self.parse_instrs(cursor.rest(), scope, out)?;
+                scope.labels.pop();
+                out.push(Instruction::End);
+            }
+            "if" => {
+                let label = cursor.take_id().map(|l| l.to_owned());
+                let typ = parse_block_type(&mut cursor)?;
+
+                // The condition is made up of any folded instructions before '(then ...)'
+                while let Some(cond) = cursor.peek() {
+                    if cond.is_list_of("then") {
+                        break;
+                    }
+                    cursor.take();
+                    self.parse_folded(cond, scope, out)?;
+                }
+
+                out.push(Instruction::If(typ));
+                scope.labels.push(label);
+                match cursor.take_list_of("then") {
+                    Some(then) => self.parse_instrs(then, scope, out)?,
+                    None => {
+                        return Err(ParseError::new("expected '(then ...)'", cursor.position()))
+                    }
+                }
+                if let Some(els) = cursor.take_list_of("else") {
+                    out.push(Instruction::Else);
+                    self.parse_instrs(els, scope, out)?;
+                }
+                cursor.expect_end()?;
+                scope.labels.pop();
+                out.push(Instruction::End);
+            }
+            _ => {
+                let inst = self.parse_op(keyword, pos, &mut cursor, scope)?;
+                self.parse_instrs(cursor.rest(), scope, out)?;
+                out.push(inst);
+            }
+        }
+        Ok(())
+    }
+
+    /// Parses a non-block instruction and its immediates.
+    fn parse_op(
+        &mut self,
+        keyword: &str,
+        pos: Position,
+        cursor: &mut Cursor,
+        scope: &mut FuncScope,
+    ) -> Result<Instruction, ParseError> {
+        use crate::Instruction::*;
+
+        if let Some(inst) = Instruction::from_name(keyword) {
+            return Ok(inst);
+        }
+
+        let inst = match keyword {
+            "br" | "br_if" => {
+                let (atom, pos) = cursor.expect_atom()?;
+                let depth = scope.resolve_label(atom, pos)?;
+                if keyword == "br" {
+                    Br(depth)
+                } else {
+                    BrIf(depth)
+                }
+            }
+            "br_table" => {
+                let mut targets = Vec::new();
+                while let Some(SExpr::Atom(atom, pos)) = cursor.peek() {
+                    cursor.take();
+                    targets.push(scope.resolve_label(atom, *pos)?);
+                }
+                match targets.pop() {
+                    Some(default) => BrTable(BranchTable::new(targets, default)),
+                    None => return Err(ParseError::new("expected a label", pos)),
+                }
+            }
+            "call" => {
+                let (atom, pos) = cursor.expect_atom()?;
+                Call(self.funcs.resolve(atom, pos, "function")?)
+            }
+            "call_indirect" => {
+                let table_idx = match cursor.peek() {
+                    Some(SExpr::Atom(atom, pos)) => {
+                        cursor.take();
+                        self.tables.resolve(atom, *pos, "table")?
+                    }
+                    _ => 0,
+                };
+                let (type_idx, param_ids) = self.parse_type_use(cursor)?;
+                if param_ids.iter().any(|p| p.is_some()) {
+                    return Err(ParseError::new("unexpected parameter identifier", pos));
+                }
+                CallIndirect(type_idx, table_idx)
+            }
+            "local.get" | "get_local" | "local.set" | "set_local" | "local.tee" | "tee_local" => {
+                let (atom, pos) = cursor.expect_atom()?;
+                let local_idx = scope.locals.resolve(atom, pos, "local")?;
+                match keyword {
+                    "local.get" | "get_local" => LocalGet(local_idx),
+                    "local.set" | "set_local" => LocalSet(local_idx),
+                    _ => LocalTee(local_idx),
+                }
+            }
+            "global.get" | "get_global" | "global.set" | "set_global" => {
+                let (atom, pos) = cursor.expect_atom()?;
+                let global_idx = self.globals.resolve(atom, pos, "global")?;
+                match keyword {
+                    "global.get" | "get_global" => GlobalGet(global_idx),
+                    _ => GlobalSet(global_idx),
+                }
+            }
+            "memory.size" | "current_memory" => MemorySize(0),
+            "memory.grow" | "grow_memory" => MemoryGrow(0),
+            "i32.const" | "i64.const" | "f32.const" | "f64.const" => {
+                let (atom, pos) = cursor.expect_atom()?;
+                let value = parse_const(keyword, atom)
+                    .ok_or_else(|| ParseError::new("constant out of range", pos))?;
+                match value {
+                    Value::I32(_) => I32Const(value),
+                    Value::I64(_) => I64Const(value),
+                    Value::F32(_) => F32Const(value),
+                    _ => F64Const(value),
+                }
+            }
+            _ => {
+                let natural = match natural_alignment(keyword) {
+                    Some(natural) => natural,
+                    None => {
+                        return Err(ParseError::new(
+                            format!("unknown operator {}", keyword),
+                            pos,
+                        ))
+                    }
+                };
+                let mut offset = 0;
+                let mut align = natural;
+                if let Some(value) = cursor.peek_atom().and_then(|a| a.strip_prefix("offset=")) {
+                    cursor.take();
+                    offset = number::parse_u32(value)
+                        .ok_or_else(|| ParseError::new("invalid offset", pos))?;
+                }
+                if let Some(value) = cursor.peek_atom().and_then(|a| a.strip_prefix("align=")) {
+                    cursor.take();
+                    align = number::parse_u32(value)
+                        .filter(|a| a.is_power_of_two())
+                        .ok_or_else(|| ParseError::new("alignment must be a power of two", pos))?;
+                }
+                Instruction::from_memarg_name(keyword, align.trailing_zeros(), offset)
+                    .expect("Memory instruction should have a memarg")
+            }
+        };
+        Ok(inst)
+    }
+}
+
+/// Gets the natural alignment, in bytes, of the memory instruction with the specified name.
+fn natural_alignment(name: &str) -> Option<u32> {
+    let (typ, op) = match name.find('.') {
+        Some(idx) => (&name[..idx], &name[idx + 1..]),
+        None => return None,
+    };
+    let size = op
+        .strip_prefix("load")
+        .or_else(|| op.strip_prefix("store"))?;
+    let bits = match size.split('_').next() {
+        Some("") => match typ {
+            "i32" | "f32" => 32,
+            "i64" | "f64" => 64,
+            _ => return None,
+        },
+        Some("8") => 8,
+        Some("16") => 16,
+        Some("32") => 32,
+        _ => return None,
+    };
+    Some(bits / 8)
+}
+
+/// Parses a constant literal for the specified `*.const` instruction.
+pub fn parse_const(keyword: &str, atom: &str) -> Option<Value> {
+    match keyword {
+        "i32.const" => number::parse_i32(atom).map(Value::I32),
+        "i64.const" => number::parse_i64(atom).map(Value::I64),
+        "f32.const" => number::parse_f32(atom).map(Value::F32),
+        "f64.const" => number::parse_f64(atom).map(Value::F64),
+        _ => None,
+    }
+}
+
+fn block_instruction(keyword: &str, typ: ValType) -> Instruction {
+    match keyword {
+        "block" => Instruction::Block(typ),
+        "loop" => Instruction::Loop(typ),
+        _ => Instruction::If(typ),
+    }
+}
+
+/// Parses an optional `(result t)` block type.
+fn parse_block_type(cursor: &mut Cursor) -> Result<ValType, ParseError> {
+    match cursor.take_list_of("result") {
+        Some(items) => {
+            let mut result = Cursor::new(items, cursor.position());
+            let typ = result.expect_val_type()?;
+            result.expect_end()?;
+            Ok(typ)
+        }
+        None => Ok(ValType::Nil),
+    }
+}
+
+/// Parses `(param ...)* (result ...)*`, returning the type and the identifiers of the parameters.
+fn parse_func_type(cursor: &mut Cursor) -> Result<(FuncType, Vec<Option<String>>), ParseError> {
+    let mut params = Vec::new();
+    let mut param_ids = Vec::new();
+    let mut results = Vec::new();
+
+    while let Some(items) = cursor.take_list_of("param") {
+        let mut param = Cursor::new(items, cursor.position());
+        match param.take_id() {
+            Some(id) => {
+                params.push(param.expect_val_type()?);
+                param_ids.push(Some(id.to_owned()));
+            }
+            None => {
+                while !param.is_empty() {
+                    params.push(param.expect_val_type()?);
+                    param_ids.push(None);
+                }
+            }
+        }
+        param.expect_end()?;
+    }
+    while let Some(items) = cursor.take_list_of("result") {
+        let mut result = Cursor::new(items, cursor.position());
+        while !result.is_empty() {
+            results.push(result.expect_val_type()?);
+        }
+    }
+
+    Ok((FuncType::new(params, results), param_ids))
+}
+
+fn parse_limits(cursor: &mut Cursor) -> Result<(usize, Option<usize>), ParseError> {
+    let (atom, pos) = cursor.expect_atom()?;
+    let min = number::parse_u32(atom).ok_or_else(|| ParseError::new("invalid limit", pos))?;
+    let max = match cursor.peek() {
+        Some(SExpr::Atom(atom, pos)) if atom.starts_with(|c: char| c.is_ascii_digit()) => {
+            cursor.take();
+            Some(number::parse_u32(atom).ok_or_else(|| ParseError::new("invalid limit", *pos))?)
+        }
+        _ => None,
+    };
+    Ok((min as usize, max.map(|m| m as usize)))
+}
+
+fn parse_table_type(cursor: &mut Cursor) -> Result<TableType, ParseError> {
+    let (min, max) = parse_limits(cursor)?;
+    match cursor.expect_atom()? {
+        ("funcref", _) | ("anyfunc", _) => Ok(TableType::new(ElemType::AnyFunc, min, max)),
+        (_, pos) => Err(ParseError::new("expected 'funcref'", pos)),
+    }
+}
+
+fn parse_memory_type(cursor: &mut Cursor) -> Result<MemoryType, ParseError> {
+    let (min, max) = parse_limits(cursor)?;
+    Ok(MemoryType::new(min, max))
+}
+
+fn parse_global_type(cursor: &mut Cursor) -> Result<GlobalType, ParseError> {
+    match cursor.take_list_of("mut") {
+        Some(items) => {
+            let mut mutable = Cursor::new(items, cursor.position());
+            let typ = mutable.expect_val_type()?;
+            mutable.expect_end()?;
+            Ok(GlobalType::new(typ, true))
+        }
+        None => Ok(GlobalType::new(cursor.expect_val_type()?, false)),
+    }
+}
+
+/// Gets the items of a list that starts with a keyword.
+fn field_items(expr: &SExpr) -> Result<&[SExpr], ParseError> {
+    match expr.as_list() {
+        Some(items) if expr.head().is_some() => Ok(items),
+        _ => Err(ParseError::new("expected a keyword", expr.position())),
+    }
+}
+
+/// A cursor over the items of a list.
+pub struct Cursor<'a> {
+    items: &'a [SExpr],
+    index: usize,
+    /// The position reported for errors when the cursor is at the end of the list.
+    end: Position,
+}
+
+impl<'a> Cursor<'a> {
+    pub fn new(items: &'a [SExpr], end: Position) -> Cursor<'a> {
+        Cursor {
+            items,
+            index: 0,
+            end,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index >= self.items.len()
+    }
+
+    pub fn position(&self) -> Position {
+        match self.peek() {
+            Some(item) => item.position(),
+            None => self.end,
+        }
+    }
+
+    pub fn peek(&self) -> Option<&'a SExpr> {
+        self.items.get(self.index)
+    }
+
+    pub fn take(&mut self) -> Option<&'a SExpr> {
+        let item = self.items.get(self.index);
+        if item.is_some() {
+            self.index += 1;
+        }
+        item
+    }
+
+    pub fn rest(&self) -> &'a [SExpr] {
+        &self.items[self.index.min(self.items.len())..]
+    }
+
+    pub fn peek_atom(&self) -> Option<&'a str> {
+        self.peek().and_then(|i| i.as_atom())
+    }
+
+    /// Consumes an identifier (an atom starting with '$'), if one is next.
+    pub fn take_id(&mut self) -> Option<&'a str> {
+        match self.peek_atom() {
+            Some(atom) if atom.starts_with('$') => {
+                self.index += 1;
+                Some(atom)
+            }
+            _ => None,
+        }
+    }
+
+    /// Consumes a list starting with `keyword`, if one is next, returning the items after the keyword.
+    pub fn take_list_of(&mut self, keyword: &str) -> Option<&'a [SExpr]> {
+        match self.peek() {
+            Some(item) if item.is_list_of(keyword) => {
+                self.index += 1;
+                item.as_list().map(|items| &items[1..])
+            }
+            _ => None,
+        }
+    }
+
+    /// Consumes all consecutive strings, concatenating them.
+    pub fn take_strings(&mut self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        while let Some(SExpr::Str(s, _)) = self.peek() {
+            bytes.extend_from_slice(s);
+            self.index += 1;
+        }
+        bytes
+    }
+
+    pub fn expect_atom(&mut self) -> Result<(&'a str, Position), ParseError> {
+        match self.take() {
+            Some(SExpr::Atom(atom, pos)) => Ok((atom, *pos)),
+            Some(item) => Err(ParseError::new("unexpected token", item.position())),
+            None => Err(ParseError::new("unexpected end of list", self.end)),
+        }
+    }
+
+    pub fn expect_string(&mut self) -> Result<&'a [u8], ParseError> {
+        match self.take() {
+            Some(SExpr::Str(s, _)) => Ok(s),
+            Some(item) => Err(ParseError::new("expected a string", item.position())),
+            None => Err(ParseError::new("expected a string", self.end)),
+        }
+    }
+
+    /// Consumes a string which must be valid UTF-8.
+    pub fn expect_name(&mut self) -> Result<String, ParseError> {
+        let pos = self.position();
+        let bytes = self.expect_string()?;
+        String::from_utf8(bytes.to_vec())
+            .map_err(|_| ParseError::new("malformed UTF-8 encoding", pos))
+    }
+
+    pub fn expect_val_type(&mut self) -> Result<ValType, ParseError> {
+        match self.expect_atom()? {
+            ("i32", _) => Ok(ValType::I32),
+            ("i64", _) => Ok(ValType::I64),
+            ("f32", _) => Ok(ValType::F32),
+            ("f64", _) => Ok(ValType::F64),
+            (_, pos) => Err(ParseError::new("expected a value type", pos)),
+        }
+    }
+
+    pub fn expect_end(&self) -> Result<(), ParseError> {
+        match self.peek() {
+            Some(item) => Err(ParseError::new("unexpected token", item.position())),
+            None => Ok(()),
+        }
+    }
+}
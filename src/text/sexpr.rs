@@ -0,0 +1,255 @@
+use std::{iter::Peekable, str::Chars};
+
+use crate::text::{ParseError, Position};
+
+/// A node in the S-expression tree underlying the text format.
+#[derive(Clone, PartialEq, Debug)]
+pub enum SExpr {
+    /// A keyword, identifier (including the leading `$`), or number.
+    Atom(String, Position),
+    /// A string literal, which may contain arbitrary bytes.
+    Str(Vec<u8>, Position),
+    List(Vec<SExpr>, Position),
+}
+
+impl SExpr {
+    pub fn position(&self) -> Position {
+        match self {
+            SExpr::Atom(_, p) | SExpr::Str(_, p) | SExpr::List(_, p) => *p,
+        }
+    }
+
+    pub fn as_atom(&self) -> Option<&str> {
+        match self {
+            SExpr::Atom(a, _) => Some(a),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&[SExpr]> {
+        match self {
+            SExpr::List(items, _) => Some(items),
+            _ => None,
+        }
+    }
+
+    /// Gets the keyword at the start of this list, if this is a list starting with an atom.
+    pub fn head(&self) -> Option<&str> {
+        self.as_list()
+            .and_then(|items| items.first())
+            .and_then(|h| h.as_atom())
+    }
+
+    /// Gets a boolean indicating if this is a list starting with the specified keyword.
+    pub fn is_list_of(&self, keyword: &str) -> bool {
+        self.head() == Some(keyword)
+    }
+}
+
+/// Parses the provided text into a sequence of top-level S-expressions.
+pub fn parse(text: &str) -> Result<Vec<SExpr>, ParseError> {
+    let mut lexer = Lexer::new(text);
+    let mut stack: Vec<(Vec<SExpr>, Position)> = Vec::new();
+    let mut top = Vec::new();
+
+    while let Some((token, pos)) = lexer.next_token()? {
+        let expr = match token {
+            Token::Open => {
+                stack.push((Vec::new(), pos));
+                continue;
+            }
+            Token::Close => match stack.pop() {
+                Some((items, start)) => SExpr::List(items, start),
+                None => return Err(ParseError::new("unexpected ')'", pos)),
+            },
+            Token::Atom(a) => SExpr::Atom(a, pos),
+            Token::Str(s) => SExpr::Str(s, pos),
+        };
+
+        match stack.last_mut() {
+            Some((items, _)) => items.push(expr),
+            None => top.push(expr),
+        }
+    }
+
+    match stack.pop() {
+        Some((_, start)) => Err(ParseError::new("unclosed '('", start)),
+        None => Ok(top),
+    }
+}
+
+enum Token {
+    Open,
+    Close,
+    Atom(String),
+    Str(Vec<u8>),
+}
+
+struct Lexer<'a> {
+    chars: Peekable<Chars<'a>>,
+    line: usize,
+    column: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(text: &'a str) -> Lexer<'a> {
+        Lexer {
+            chars: text.chars().peekable(),
+            line: 1,
+            column: 1,
+        }
+    }
+
+    fn position(&self) -> Position {
+        Position::new(self.line, self.column)
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn next_token(&mut self) -> Result<Option<(Token, Position)>, ParseError> {
+        loop {
+            let pos = self.position();
+            let c = match self.chars.peek() {
+                Some(c) => *c,
+                None => return Ok(None),
+            };
+
+            match c {
+                ' ' | '\t' | '\n' | '\r' => {
+                    self.bump();
+                }
+                ';' => {
+                    self.bump();
+                    if self.bump() != Some(';') {
+                        return Err(ParseError::new("unexpected ';'", pos));
+                    }
+                    // Line comment
+                    while let Some(c) = self.bump() {
+                        if c == '\n' {
+                            break;
+                        }
+                    }
+                }
+                '(' => {
+                    self.bump();
+                    if self.chars.peek() == Some(&';') {
+                        self.skip_block_comment(pos)?;
+                    } else {
+                        return Ok(Some((Token::Open, pos)));
+                    }
+                }
+                ')' => {
+                    self.bump();
+                    return Ok(Some((Token::Close, pos)));
+                }
+                '"' => {
+                    self.bump();
+                    return Ok(Some((Token::Str(self.read_string(pos)?), pos)));
+                }
+                _ => {
+                    let mut atom = String::new();
+                    while let Some(&c) = self.chars.peek() {
+                        match c {
+                            ' ' | '\t' | '\n' | '\r' | '(' | ')' | '"' | ';' => break,
+                            _ => {
+                                atom.push(c);
+                                self.bump();
+                            }
+                        }
+                    }
+                    return Ok(Some((Token::Atom(atom), pos)));
+                }
+            }
+        }
+    }
+
+    /// Skips a (possibly nested) block comment. The opening '(' has already been consumed.
+    fn skip_block_comment(&mut self, start: Position) -> Result<(), ParseError> {
+        let mut depth = 1;
+        self.bump();
+        while depth > 0 {
+            match self.bump() {
+                Some('(') if self.chars.peek() == Some(&';') => {
+                    self.bump();
+                    depth += 1;
+                }
+                Some(';') if self.chars.peek() == Some(&')') => {
+                    self.bump();
+                    depth -= 1;
+                }
+                Some(_) => {}
+                None => return Err(ParseError::new("unclosed block comment", start)),
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads a string literal. The opening '"' has already been consumed.
+    fn read_string(&mut self, start: Position) -> Result<Vec<u8>, ParseError> {
+        let mut bytes = Vec::new();
+        loop {
+            let pos = self.position();
+            match self.bump() {
+                Some('"') => return Ok(bytes),
+                Some('\\') => match self.bump() {
+                    Some('t') => bytes.push(b'\t'),
+                    Some('n') => bytes.push(b'\n'),
+                    Some('r') => bytes.push(b'\r'),
+                    Some('"') => bytes.push(b'"'),
+                    Some('\'') => bytes.push(b'\''),
+                    Some('\\') => bytes.push(b'\\'),
+                    Some('u') => {
+                        let c = self.read_unicode_escape(pos)?;
+                        let mut buf = [0; 4];
+                        bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+                    }
+                    Some(h) => {
+                        let l = self.bump();
+                        match (h.to_digit(16), l.and_then(|l| l.to_digit(16))) {
+                            (Some(h), Some(l)) => bytes.push((h * 16 + l) as u8),
+                            _ => return Err(ParseError::new("invalid string escape", pos)),
+                        }
+                    }
+                    None => return Err(ParseError::new("unclosed string", start)),
+                },
+                Some(c) => {
+                    let mut buf = [0; 4];
+                    bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+                }
+                None => return Err(ParseError::new("unclosed string", start)),
+            }
+        }
+    }
+
+    fn read_unicode_escape(&mut self, pos: Position) -> Result<char, ParseError> {
+        if self.bump() != Some('{') {
+            return Err(ParseError::new("invalid unicode escape", pos));
+        }
+        let mut value: u32 = 0;
+        loop {
+            match self.bump() {
+                Some('}') => break,
+                Some(c) => match c.to_digit(16) {
+                    Some(d) => {
+                        value = value
+                            .checked_mul(16)
+                            .and_then(|v| v.checked_add(d))
+                            .ok_or_else(|| ParseError::new("invalid unicode escape", pos))?
+                    }
+                    None => return Err(ParseError::new("invalid unicode escape", pos)),
+                },
+                None => return Err(ParseError::new("invalid unicode escape", pos)),
+            }
+        }
+        std::char::from_u32(value).ok_or_else(|| ParseError::new("invalid unicode escape", pos))
+    }
+}
@@ -4,12 +4,19 @@ use crate::{interp::StackTrace, ValType};
 
 #[derive(Clone, PartialEq)]
 pub enum TrapCause {
+    Unreachable,
     IntegerOverflow,
     IntegerDivideByZero,
     InvalidConversionToInteger,
+    OutOfBoundsMemoryAccess,
+    UndefinedElement,
+    UninitializedElement,
+    IndirectCallTypeMismatch,
     StackUnderflow,
     StackNotEmpty,
     TypeMismatch { expected: ValType, actual: ValType },
+    CallStackExhausted,
+    OutOfFuel,
     Other(Cow<'static, str>),
 }
 
@@ -20,13 +27,20 @@ impl TrapCause {
         match self {
             // Well-known traps return static strings
             // These strings are described by the spec tests. Do not modify them.
+            Unreachable => "unreachable".into(),
             IntegerOverflow => "integer overflow".into(),
             IntegerDivideByZero => "integer divide by zero".into(),
             InvalidConversionToInteger => "invalid conversion to integer".into(),
             StackNotEmpty => "stack not empty".into(),
+            OutOfBoundsMemoryAccess => "out of bounds memory access".into(),
+            UndefinedElement => "undefined element".into(),
+            UninitializedElement => "uninitialized element".into(),
+            IndirectCallTypeMismatch => "indirect call type mismatch".into(),
+            CallStackExhausted => "call stack exhausted".into(),
 
             // These are other well-known traps that we define
             StackUnderflow => "stack underflow".into(),
+            OutOfFuel => "out of fuel".into(),
             TypeMismatch { expected, actual } => {
                 format!("type mismatch (expected: {}, actual {})", expected, actual).into()
             }
@@ -1,10 +1,13 @@
 use std::io;
 
-use byteorder::ReadBytesExt;
+use byteorder::{ReadBytesExt, WriteBytesExt};
 
 use crate::Error;
 
 pub trait FromLeb128 {
+    /// The number of bits in the type, which limits the length of its encoding.
+    const BITS: u32;
+
     fn from_leb128_u(leb: u64) -> Self;
     fn from_leb128_s(leb: i64) -> Self;
 }
@@ -12,6 +15,8 @@ pub trait FromLeb128 {
 macro_rules! impl_from_leb {
     ($target: ty) => {
         impl FromLeb128 for $target {
+            const BITS: u32 = <$target>::BITS;
+
             fn from_leb128_u(leb: u64) -> $target {
                 leb as $target
             }
@@ -31,11 +36,43 @@ impl_from_leb!(i32);
 impl_from_leb!(i64);
 
 pub fn read_leb128_s<R: io::Read, T: FromLeb128>(r: &mut R) -> Result<T, Error> {
-    Ok(T::from_leb128_s(leb128::read::signed(r)?))
+    Ok(T::from_leb128_s(read_leb128(r, T::BITS, true)? as i64))
 }
 
 pub fn read_leb128_u32<R: io::Read>(r: &mut R) -> Result<u32, Error> {
-    Ok(leb128::read::unsigned(r)? as u32)
+    Ok(read_leb128(r, 32, false)? as u32)
+}
+
+/// Reads a LEB128 integer of a type with the specified number of bits, sign-extending it if
+/// `signed` is set.
+///
+/// The encoding may be padded, but can't use more bytes than the type needs, and the unused bits
+/// of the last byte must be zero (or, for signed integers, copies of the sign bit).
+fn read_leb128<R: io::Read>(r: &mut R, bits: u32, signed: bool) -> Result<u64, Error> {
+    let max_bytes = bits.div_ceil(7);
+    let mut result = 0u64;
+    for i in 0..max_bytes {
+        let byte = r.read_u8()?;
+        let shift = i * 7;
+        result |= u64::from(byte & 0x7F) << shift;
+        if byte & 0x80 != 0 {
+            continue;
+        }
+
+        if i == max_bytes - 1 {
+            let used = bits - shift;
+            let unused = (byte & 0x7F) >> (if signed { used - 1 } else { used });
+            let sign_copies = if signed { 0x7F >> (used - 1) } else { 0 };
+            if unused != 0 && unused != sign_copies {
+                return Err(Error::IntegerTooLarge);
+            }
+        }
+        if signed && shift + 7 < 64 && byte & 0x40 != 0 {
+            result |= !0 << (shift + 7);
+        }
+        return Ok(result);
+    }
+    Err(Error::IntegerTooLong)
 }
 
 pub fn read_vec<R, F, I>(r: &mut R, mut body: F) -> Result<Vec<I>, Error>
@@ -72,3 +109,45 @@ pub fn read_limits<R: io::Read>(r: &mut R) -> Result<(usize, Option<usize>), Err
         _ => Err(Error::InvalidModule),
     }
 }
+
+pub fn write_leb128_s<W: io::Write>(w: &mut W, val: i64) -> Result<(), Error> {
+    leb128::write::signed(w, val)?;
+    Ok(())
+}
+
+pub fn write_leb128_u32<W: io::Write>(w: &mut W, val: u32) -> Result<(), Error> {
+    leb128::write::unsigned(w, val as u64)?;
+    Ok(())
+}
+
+pub fn write_vec<W, F, I>(w: &mut W, items: &[I], mut body: F) -> Result<(), Error>
+where
+    W: io::Write,
+    F: FnMut(&mut W, &I) -> Result<(), Error>,
+{
+    write_leb128_u32(w, items.len() as u32)?;
+    for item in items {
+        body(w, item)?;
+    }
+    Ok(())
+}
+
+pub fn write_name<W: io::Write>(w: &mut W, name: &str) -> Result<(), Error> {
+    write_leb128_u32(w, name.len() as u32)?;
+    w.write_all(name.as_bytes())?;
+    Ok(())
+}
+
+pub fn write_limits<W: io::Write>(w: &mut W, min: usize, max: Option<usize>) -> Result<(), Error> {
+    match max {
+        None => {
+            w.write_u8(0x00)?;
+            write_leb128_u32(w, min as u32)
+        }
+        Some(max) => {
+            w.write_u8(0x01)?;
+            write_leb128_u32(w, min as u32)?;
+            write_leb128_u32(w, max as u32)
+        }
+    }
+}
@@ -1,6 +1,6 @@
 use std::{fmt, io};
 
-use byteorder::ReadBytesExt;
+use byteorder::{ReadBytesExt, WriteBytesExt};
 
 use crate::{Error, TrapCause};
 
@@ -32,6 +32,11 @@ impl ValType {
         let v = reader.read_u8()?;
         ValType::from_u8(v)
     }
+
+    pub fn write<W: io::Write>(&self, writer: &mut W) -> Result<(), Error> {
+        writer.write_u8(*self as u8)?;
+        Ok(())
+    }
 }
 
 impl fmt::Display for ValType {
@@ -148,3 +153,94 @@ impl_from_value!(i32, I32);
 impl_from_value!(i64, I64);
 impl_from_value!(f32, F32);
 impl_from_value!(f64, F64);
+
+/// A Rust type that represents a WebAssembly value of a particular type.
+pub trait WasmType: FromValue + Into<Value> {
+    fn val_type() -> ValType;
+}
+
+macro_rules! impl_wasm_type {
+    ($t: ty, $v: ident) => {
+        impl WasmType for $t {
+            fn val_type() -> ValType {
+                ValType::$v
+            }
+        }
+    };
+}
+
+impl_wasm_type!(u32, I32);
+impl_wasm_type!(u64, I64);
+impl_wasm_type!(i32, I32);
+impl_wasm_type!(i64, I64);
+impl_wasm_type!(f32, F32);
+impl_wasm_type!(f64, F64);
+
+/// A sequence of Rust values with WebAssembly types, such as the parameters or results of a
+/// function.
+///
+/// This is implemented for `()`, every [`WasmType`] and tuples of up to 8 of them.
+pub trait WasmValues: Sized {
+    fn types() -> Vec<ValType>;
+    fn into_values(self) -> Vec<Value>;
+
+    /// Takes the values from `values`, which should already have been checked against `types()`.
+    fn from_values(values: &mut dyn Iterator<Item = Value>) -> Result<Self, TrapCause>;
+}
+
+impl WasmValues for () {
+    fn types() -> Vec<ValType> {
+        Vec::new()
+    }
+
+    fn into_values(self) -> Vec<Value> {
+        Vec::new()
+    }
+
+    fn from_values(_values: &mut dyn Iterator<Item = Value>) -> Result<(), TrapCause> {
+        Ok(())
+    }
+}
+
+impl<T: WasmType> WasmValues for T {
+    fn types() -> Vec<ValType> {
+        vec![T::val_type()]
+    }
+
+    fn into_values(self) -> Vec<Value> {
+        vec![self.into()]
+    }
+
+    fn from_values(values: &mut dyn Iterator<Item = Value>) -> Result<T, TrapCause> {
+        T::from_value(values.next().unwrap_or(Value::Nil))
+    }
+}
+
+macro_rules! impl_wasm_values_for_tuple {
+    ($($t: ident),*) => {
+        impl<$($t: WasmType),*> WasmValues for ($($t,)*) {
+            fn types() -> Vec<ValType> {
+                vec![$($t::val_type()),*]
+            }
+
+            #[allow(non_snake_case)]
+            fn into_values(self) -> Vec<Value> {
+                let ($($t,)*) = self;
+                vec![$($t.into()),*]
+            }
+
+            fn from_values(values: &mut dyn Iterator<Item = Value>) -> Result<Self, TrapCause> {
+                Ok(($(<$t as WasmValues>::from_values(values)?,)*))
+            }
+        }
+    };
+}
+
+impl_wasm_values_for_tuple!(A);
+impl_wasm_values_for_tuple!(A, B);
+impl_wasm_values_for_tuple!(A, B, C);
+impl_wasm_values_for_tuple!(A, B, C, D);
+impl_wasm_values_for_tuple!(A, B, C, D, E);
+impl_wasm_values_for_tuple!(A, B, C, D, E, G);
+impl_wasm_values_for_tuple!(A, B, C, D, E, G, H);
+impl_wasm_values_for_tuple!(A, B, C, D, E, G, H, I);
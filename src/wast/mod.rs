@@ -0,0 +1,7 @@
+//! Running of `.wast` spec test scripts.
+
+mod runner;
+mod script;
+
+pub use self::runner::{DirectiveResult, Report, Runner};
+pub use self::script::{Action, Command, Directive, Expected, Script};
@@ -0,0 +1,530 @@
+use std::{collections::HashMap, fmt};
+
+use crate::{
+    hosting::{Host, Instance},
+    module::{validate, Module},
+    runtime,
+    text::{self, SExpr},
+    wast::{Action, Command, Directive, Expected, Script},
+    Error, ValType, Value,
+};
+
+/// The outcome of running a single directive.
+pub struct DirectiveResult {
+    pub line: usize,
+    pub command: &'static str,
+    /// `Err` with a description of the failure if the directive failed.
+    pub outcome: Result<(), String>,
+}
+
+impl fmt::Display for DirectiveResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.outcome {
+            Ok(()) => write!(f, "line {}: {} ok", self.line, self.command),
+            Err(ref e) => write!(f, "line {}: {} FAILED: {}", self.line, self.command, e),
+        }
+    }
+}
+
+/// The results of running every directive in a script.
+pub struct Report {
+    results: Vec<DirectiveResult>,
+}
+
+impl Report {
+    pub fn results(&self) -> &[DirectiveResult] {
+        &self.results
+    }
+
+    pub fn passed(&self) -> usize {
+        self.results.iter().filter(|r| r.outcome.is_ok()).count()
+    }
+
+    pub fn failed(&self) -> usize {
+        self.results.len() - self.passed()
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = &DirectiveResult> {
+        self.results.iter().filter(|r| r.outcome.is_err())
+    }
+}
+
+/// Runs the directives of `.wast` scripts against a [`Host`].
+pub struct Runner {
+    host: Host,
+    /// Modules defined by the script with a `$name`.
+    named: HashMap<String, Instance>,
+    /// The most recently defined module, which actions without a module name apply to.
+    current: Option<Instance>,
+    /// The number of modules defined so far, used to give each instance a unique name.
+    count: usize,
+}
+
+impl Runner {
+    /// Creates a runner with the 'env' and 'spectest' modules available for import.
+    pub fn new() -> Runner {
+        let mut host = Host::new();
+        host.external(runtime::Env::new()).unwrap();
+        host.external(runtime::SpecTest::new()).unwrap();
+
+        Runner {
+            host,
+            named: HashMap::new(),
+            current: None,
+            count: 0,
+        }
+    }
+
+    pub fn host(&self) -> &Host {
+        &self.host
+    }
+
+    /// Runs every directive in the script, continuing past failures.
+    pub fn run(&mut self, script: &Script) -> Report {
+        let results = script
+            .directives()
+            .iter()
+            .map(|directive| self.run_directive(directive))
+            .collect();
+        Report { results }
+    }
+
+    pub fn run_directive(&mut self, directive: &Directive) -> DirectiveResult {
+        DirectiveResult {
+            line: directive.line,
+            command: directive.command.name(),
+            outcome: self.run_command(&directive.command),
+        }
+    }
+
+    fn run_command(&mut self, command: &Command) -> Result<(), String> {
+        match command {
+            Command::Module(expr) => {
+                let (id, module) = parse(expr)?;
+                let instance = self.instantiate(module).map_err(describe_error)?;
+                if let Some(id) = id {
+                    self.named.insert(id, instance);
+                }
+                self.current = Some(instance);
+                Ok(())
+            }
+            Command::Register { name, module } => {
+                let instance = self.resolve_module(module)?;
+                self.host.register(name.clone(), instance.addr());
+                Ok(())
+            }
+            Command::Action(action) => self.perform(action).map(|_| ()),
+            Command::AssertReturn(action, expected) => {
+                let actual = self.perform(action)?;
+                if actual.len() == expected.len()
+                    && actual
+                        .iter()
+                        .zip(expected.iter())
+                        .all(|(a, e)| matches(e, a))
+                {
+                    Ok(())
+                } else {
+                    Err(format!(
+                        "expected {}, got {}",
+                        format_expected(expected),
+                        format_values(&actual)
+                    ))
+                }
+            }
+            Command::AssertTrap(action, message) => match self.perform(action) {
+                Ok(values) => Err(format!(
+                    "expected trap '{}', got {}",
+                    message,
+                    format_values(&values)
+                )),
+                Err(e) => expect_message(message, &e),
+            },
+            Command::AssertModuleTrap(expr, message) => {
+                let (_, module) = parse(expr)?;
+                match self.instantiate(module) {
+                    Ok(_) => Err(format!("expected trap '{}', module instantiated", message)),
+                    Err(Error::Trap(trap)) => expect_message(message, &trap.cause().to_string()),
+                    // Newer versions of the spec report segments that don't fit as traps
+                    Err(Error::ElemSegmentOutOfBounds { .. }) => {
+                        expect_message(message, "out of bounds table access")
+                    }
+                    Err(Error::DataSegmentOutOfBounds { .. }) => {
+                        expect_message(message, "out of bounds memory access")
+                    }
+                    Err(e) => Err(format!(
+                        "expected trap '{}', got {}",
+                        message,
+                        describe_error(e)
+                    )),
+                }
+            }
+            Command::AssertExhaustion(action, message) => match self.perform(action) {
+                Ok(values) => Err(format!(
+                    "expected exhaustion '{}', got {}",
+                    message,
+                    format_values(&values)
+                )),
+                Err(e) => expect_message(message, &e),
+            },
+            Command::AssertMalformed(expr, message) => match text::parse_module_expr(expr) {
+                Ok(_) => Err(format!(
+                    "expected malformed module '{}', module parsed",
+                    message
+                )),
+                Err(e) => expect_message(message, e.message()),
+            },
+            Command::AssertInvalid(expr, message) => {
+                let (_, module) = parse(expr)?;
+                match validate(&module) {
+                    Ok(()) => Err(format!(
+                        "expected invalid module '{}', module is valid",
+                        message
+                    )),
+                    Err(e) => expect_message(message, &e.kind().to_string()),
+                }
+            }
+            Command::AssertUnlinkable(expr, message) => {
+                let (_, module) = parse(expr)?;
+                match self.instantiate(module) {
+                    Ok(_) => Err(format!(
+                        "expected unlinkable module '{}', module instantiated",
+                        message
+                    )),
+                    Err(Error::ModuleNotFound { .. }) | Err(Error::ExportNotFound { .. }) => {
+                        expect_message(message, "unknown import")
+                    }
+                    Err(Error::ExportTypeMismatch { .. }) => {
+                        expect_message(message, "incompatible import type")
+                    }
+                    // The MVP spec reports segments that don't fit as link errors
+                    Err(Error::ElemSegmentOutOfBounds { .. }) => {
+                        expect_message(message, "elements segment does not fit")
+                    }
+                    Err(Error::DataSegmentOutOfBounds { .. }) => {
+                        expect_message(message, "data segment does not fit")
+                    }
+                    Err(e) => Err(format!(
+                        "expected link error '{}', got {}",
+                        message,
+                        describe_error(e)
+                    )),
+                }
+            }
+            Command::AssertUninstantiable(expr, message) => {
+                let (_, module) = parse(expr)?;
+                match self.instantiate(module) {
+                    Ok(_) => Err(format!(
+                        "expected uninstantiable module '{}', module instantiated",
+                        message
+                    )),
+                    Err(Error::Trap(trap)) => expect_message(message, &trap.cause().to_string()),
+                    Err(Error::ElemSegmentOutOfBounds { .. }) => {
+                        expect_message(message, "out of bounds table access")
+                    }
+                    Err(Error::DataSegmentOutOfBounds { .. }) => {
+                        expect_message(message, "out of bounds memory access")
+                    }
+                    Err(e) => Err(format!("expected '{}', got {}", message, describe_error(e))),
+                }
+            }
+        }
+    }
+
+    fn instantiate(&mut self, module: Module) -> Result<Instance, Error> {
+        self.count += 1;
+        let name = format!("module{}", self.count);
+        self.host.instantiate(name, module)
+    }
+
+    fn resolve_module(&self, module: &Option<String>) -> Result<Instance, String> {
+        match module {
+            Some(name) => match self.named.get(name) {
+                Some(addr) => Ok(*addr),
+                None => Err(format!("unknown module {}", name)),
+            },
+            None => self
+                .current
+                .ok_or_else(|| "no module has been defined".to_owned()),
+        }
+    }
+
+    /// Performs an action, returning the values it produced or a description of the failure.
+    fn perform(&mut self, action: &Action) -> Result<Vec<Value>, String> {
+        match action {
+            Action::Invoke {
+                module,
+                field,
+                args,
+            } => {
+                let instance = self.resolve_module(module)?;
+                let func = match instance.get_func(&self.host, field) {
+                    Some(func) => func,
+                    None => {
+                        self.expect_export(instance, field)?;
+                        return Err(format!("export '{}' is not a function", field));
+                    }
+                };
+                func.call(&mut self.host, args)
+                    .map_err(|trap| trap.cause().to_string())
+            }
+            Action::Get { module, field } => {
+                let instance = self.resolve_module(module)?;
+                match instance.get_global(&self.host, field) {
+                    Some(global) => Ok(vec![global.value()]),
+                    None => {
+                        self.expect_export(instance, field)?;
+                        Err(format!("export '{}' is not a global", field))
+                    }
+                }
+            }
+        }
+    }
+
+    /// Checks that the instance has an export with the specified name.
+    fn expect_export(&self, instance: Instance, field: &str) -> Result<(), String> {
+        match self.host.resolve_import(instance.addr(), field) {
+            Ok(_) => Ok(()),
+            Err(e) => Err(describe_error(e)),
+        }
+    }
+}
+
+impl Default for Runner {
+    fn default() -> Runner {
+        Runner::new()
+    }
+}
+
+/// Parses a `(module ...)` expression, describing the failure if it is malformed.
+fn parse(expr: &SExpr) -> Result<(Option<String>, Module), String> {
+    text::parse_module_expr(expr).map_err(|e| format!("malformed module: {}", e))
+}
+
+/// Checks that an actual failure message starts with the expected one.
+fn expect_message(expected: &str, actual: &str) -> Result<(), String> {
+    if actual.starts_with(expected) {
+        Ok(())
+    } else {
+        Err(format!("expected '{}', got '{}'", expected, actual))
+    }
+}
+
+fn describe_error(e: Error) -> String {
+    match e {
+        Error::Trap(trap) => trap.cause().to_string(),
+        Error::Validation(e) => format!("invalid module: {}", e),
+        Error::ExportTypeMismatch {
+            module,
+            name,
+            expected,
+            actual,
+        } => format!(
+            "incompatible import type for '{}'.'{}': expected {}, found {}",
+            module, name, expected, actual
+        ),
+        e => format!("{:?}", e),
+    }
+}
+
+fn matches(expected: &Expected, actual: &Value) -> bool {
+    match (expected, actual) {
+        // Floats are compared by their bits, so that signed zeros and NaN payloads are checked
+        (Expected::Value(Value::F32(e)), Value::F32(a)) => e.to_bits() == a.to_bits(),
+        (Expected::Value(Value::F64(e)), Value::F64(a)) => e.to_bits() == a.to_bits(),
+        (Expected::Value(e), a) => e == a,
+        (Expected::CanonicalNan(ValType::F32), Value::F32(a)) => {
+            a.to_bits() & 0x7fff_ffff == 0x7fc0_0000
+        }
+        (Expected::CanonicalNan(ValType::F64), Value::F64(a)) => {
+            a.to_bits() & 0x7fff_ffff_ffff_ffff == 0x7ff8_0000_0000_0000
+        }
+        (Expected::ArithmeticNan(ValType::F32), Value::F32(a)) => {
+            a.is_nan() && a.to_bits() & 0x0040_0000 != 0
+        }
+        (Expected::ArithmeticNan(ValType::F64), Value::F64(a)) => {
+            a.is_nan() && a.to_bits() & 0x0008_0000_0000_0000 != 0
+        }
+        _ => false,
+    }
+}
+
+fn format_values(values: &[Value]) -> String {
+    let values: Vec<_> = values
+        .iter()
+        .map(|v| format!("{}:{} (0x{:X})", v.typ(), v, v))
+        .collect();
+    format!("[{}]", values.join(", "))
+}
+
+fn format_expected(expected: &[Expected]) -> String {
+    let expected: Vec<_> = expected
+        .iter()
+        .map(|e| match e {
+            Expected::Value(v) => format!("{}:{} (0x{:X})", v.typ(), v, v),
+            Expected::CanonicalNan(t) => format!("{}:nan:canonical", t),
+            Expected::ArithmeticNan(t) => format!("{}:nan:arithmetic", t),
+        })
+        .collect();
+    format!("[{}]", expected.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(text: &str) -> Report {
+        Runner::new().run(&Script::parse(text).unwrap())
+    }
+
+    fn assert_all_pass(text: &str) {
+        let report = run(text);
+        let failures: Vec<_> = report.failures().map(|f| f.to_string()).collect();
+        assert!(failures.is_empty(), "{}", failures.join("\n"));
+    }
+
+    #[test]
+    pub fn runs_assertions() {
+        assert_all_pass(
+            r#"
+            (module $m
+              (global (export "g") i64 (i64.const -7))
+              (func (export "div") (param i32 i32) (result i32)
+                (i32.div_s (local.get 0) (local.get 1)))
+              (func (export "nan") (result f32) (f32.div (f32.const 0) (f32.const 0)))
+              (func (export "neg_zero") (result f64) (f64.const -0)))
+            (assert_return (invoke "div" (i32.const 7) (i32.const -2)) (i32.const -3))
+            (assert_return (invoke $m "div" (i32.const 6) (i32.const 3)) (i32.const 2))
+            (assert_return (get "g") (i64.const -7))
+            (assert_return (invoke "nan") (f32.const nan:arithmetic))
+            (assert_return (invoke "neg_zero") (f64.const -0x0p+0))
+            (assert_trap (invoke "div" (i32.const 1) (i32.const 0)) "integer divide by zero")
+            (invoke "div" (i32.const 1) (i32.const 1))
+            (assert_invalid (module (func (result i32) (i64.const 0))) "type mismatch")
+            (assert_malformed (module quote "(func i32.bogus)") "unknown operator")
+            (assert_malformed (module binary "\00asm\02\00\00\00") "unknown binary version")
+            (assert_unlinkable (module (import "nowhere" "f" (func))) "unknown import")
+            (assert_trap (module (func $start unreachable) (start $start)) "unreachable")
+            (assert_uninstantiable
+              (module (table 1 funcref) (func) (elem (i32.const 5) 0))
+              "out of bounds table access")
+            "#,
+        );
+    }
+
+    #[test]
+    pub fn links_registered_modules() {
+        assert_all_pass(
+            r#"
+            (module $a
+              (global (export "g") (mut i32) (i32.const 1))
+              (func (export "get") (result i32) (global.get 0))
+              (func (export "set") (param i32) (global.set 0 (local.get 0))))
+            (register "a" $a)
+            (module $b
+              (import "a" "get" (func $get (result i32)))
+              (func (export "twice") (result i32) (i32.mul (call $get) (i32.const 2))))
+            (invoke $a "set" (i32.const 21))
+            (assert_return (invoke $b "twice") (i32.const 42))
+            (assert_return (get $a "g") (i32.const 21))
+            (assert_return (invoke "twice") (i32.const 42))
+            (register "b")
+            (module (import "b" "twice" (func (result i32))))
+            (assert_unlinkable (module (import "a" "missing" (func))) "unknown import")
+            (assert_unlinkable (module (import "a" "get" (func))) "incompatible import type")
+            (assert_unlinkable (module (import "a" "g" (global i32))) "incompatible import type")
+            "#,
+        );
+    }
+
+    #[test]
+    pub fn detects_call_stack_exhaustion() {
+        assert_all_pass(
+            r#"
+            (module
+              (func $runaway (export "runaway") (call $runaway))
+              (func $even (export "even") (param i32) (result i32)
+                (if (result i32) (local.get 0)
+                  (then (call $odd (i32.sub (local.get 0) (i32.const 1))))
+                  (else (i32.const 1))))
+              (func $odd (param i32) (result i32)
+                (if (result i32) (local.get 0)
+                  (then (call $even (i32.sub (local.get 0) (i32.const 1))))
+                  (else (i32.const 0)))))
+            (assert_exhaustion (invoke "runaway") "call stack exhausted")
+            (assert_return (invoke "even" (i32.const 100)) (i32.const 1))
+            (assert_exhaustion (invoke "even" (i32.const 100000)) "call stack exhausted")
+            "#,
+        );
+    }
+
+    #[test]
+    pub fn reports_unknown_modules() {
+        let report = run(r#"(module) (register "x" $nope) (invoke $nope "f")"#);
+        assert_eq!(2, report.failed());
+        assert_eq!(
+            Err("unknown module $nope".to_owned()),
+            report.results()[1].outcome
+        );
+    }
+
+    #[test]
+    pub fn reports_failures() {
+        let report = run(r#"
+            (module (func (export "one") (result i32) (i32.const 1)))
+            (assert_return (invoke "one") (i32.const 2))
+            (assert_trap (invoke "one") "unreachable")
+            (assert_invalid (module (func)) "type mismatch")
+            "#);
+        assert_eq!(1, report.passed());
+        assert_eq!(3, report.failed());
+        assert_eq!(
+            Err("expected [i32:2 (0x2)], got [i32:1 (0x1)]".to_owned()),
+            report.results()[1].outcome
+        );
+        assert_eq!(3, report.results()[1].line);
+    }
+
+    #[test]
+    pub fn checks_module_failure_messages() {
+        let report = run(r#"
+            (assert_malformed (module quote "(func i32.bogus)") "unexpected end")
+            (assert_trap (module (func $start unreachable) (start $start)) "unreachable")
+            (assert_uninstantiable
+              (module (func $start unreachable) (start $start))
+              "integer divide by zero")
+            (assert_uninstantiable
+              (module (memory 0) (data (i32.const 1) "x"))
+              "out of bounds table access")
+            (assert_trap
+              (module (memory 0) (data (i32.const 1) "x"))
+              "out of bounds memory access")
+            (assert_unlinkable
+              (module (table 1 funcref) (func) (elem (i32.const 1) 0))
+              "data segment does not fit")
+            (assert_unlinkable
+              (module (table 1 funcref) (func) (elem (i32.const 1) 0))
+              "elements segment does not fit")
+            "#);
+        assert_eq!(3, report.passed());
+        assert_eq!(4, report.failed());
+        assert!(report.results()[0].outcome.is_err());
+        assert_eq!(
+            Err("expected 'integer divide by zero', got 'unreachable'".to_owned()),
+            report.results()[2].outcome
+        );
+        assert_eq!(
+            Err(
+                "expected 'out of bounds table access', got 'out of bounds memory access'"
+                    .to_owned()
+            ),
+            report.results()[3].outcome
+        );
+        assert_eq!(
+            Err(
+                "expected 'data segment does not fit', got 'elements segment does not fit'"
+                    .to_owned()
+            ),
+            report.results()[5].outcome
+        );
+    }
+}
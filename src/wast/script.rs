@@ -0,0 +1,225 @@
+use crate::{
+    text::{self, Cursor, ParseError, SExpr},
+    ValType, Value,
+};
+
+/// An action performed against a module instance.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Action {
+    /// Invokes the exported function `field`, in the module named `module` (or the current module).
+    Invoke {
+        module: Option<String>,
+        field: String,
+        args: Vec<Value>,
+    },
+    /// Gets the value of the exported global `field`, in the module named `module` (or the current module).
+    Get {
+        module: Option<String>,
+        field: String,
+    },
+}
+
+/// A result expected by `assert_return`.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Expected {
+    Value(Value),
+    /// Any NaN with the canonical payload (only the most significant mantissa bit set).
+    CanonicalNan(ValType),
+    /// Any NaN with the most significant mantissa bit set.
+    ArithmeticNan(ValType),
+}
+
+/// A command in a `.wast` script.
+///
+/// Modules are kept as S-expressions, so that parse errors can be reported (or expected, by
+/// `assert_malformed`) when the command runs rather than when the script is parsed.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Command {
+    Module(SExpr),
+    Register {
+        name: String,
+        module: Option<String>,
+    },
+    Action(Action),
+    AssertReturn(Action, Vec<Expected>),
+    AssertTrap(Action, String),
+    /// An `assert_trap` wrapping a module, whose start function is expected to trap.
+    AssertModuleTrap(SExpr, String),
+    AssertExhaustion(Action, String),
+    AssertInvalid(SExpr, String),
+    AssertMalformed(SExpr, String),
+    AssertUnlinkable(SExpr, String),
+    AssertUninstantiable(SExpr, String),
+}
+
+impl Command {
+    /// Gets the keyword that introduces this command in the script.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Command::Module(_) => "module",
+            Command::Register { .. } => "register",
+            Command::Action(Action::Invoke { .. }) => "invoke",
+            Command::Action(Action::Get { .. }) => "get",
+            Command::AssertReturn(..) => "assert_return",
+            Command::AssertTrap(..) | Command::AssertModuleTrap(..) => "assert_trap",
+            Command::AssertExhaustion(..) => "assert_exhaustion",
+            Command::AssertInvalid(..) => "assert_invalid",
+            Command::AssertMalformed(..) => "assert_malformed",
+            Command::AssertUnlinkable(..) => "assert_unlinkable",
+            Command::AssertUninstantiable(..) => "assert_uninstantiable",
+        }
+    }
+}
+
+/// A command along with the line it starts on.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Directive {
+    pub line: usize,
+    pub command: Command,
+}
+
+/// A parsed `.wast` script.
+pub struct Script {
+    directives: Vec<Directive>,
+}
+
+impl Script {
+    pub fn parse(text: &str) -> Result<Script, ParseError> {
+        let directives = text::parse(text)?
+            .iter()
+            .map(|expr| {
+                Ok(Directive {
+                    line: expr.position().line,
+                    command: parse_command(expr)?,
+                })
+            })
+            .collect::<Result<_, ParseError>>()?;
+        Ok(Script { directives })
+    }
+
+    pub fn directives(&self) -> &[Directive] {
+        &self.directives
+    }
+}
+
+fn parse_command(expr: &SExpr) -> Result<Command, ParseError> {
+    let items = match expr.as_list() {
+        Some(items) if expr.head().is_some() => items,
+        _ => return Err(ParseError::new("expected a command", expr.position())),
+    };
+    let mut cursor = Cursor::new(&items[1..], expr.position());
+
+    let command = match expr.head() {
+        Some("module") => return Ok(Command::Module(expr.clone())),
+        Some("register") => {
+            let name = cursor.expect_name()?;
+            let module = cursor.take_id().map(|id| id.to_owned());
+            Command::Register { name, module }
+        }
+        Some("invoke") | Some("get") => return Ok(Command::Action(parse_action(expr)?)),
+        Some("assert_return") => {
+            let action = parse_action(expect_expr(&mut cursor)?)?;
+            let mut expected = Vec::new();
+            while let Some(result) = cursor.take() {
+                expected.push(parse_expected(result)?);
+            }
+            Command::AssertReturn(action, expected)
+        }
+        Some("assert_trap") => {
+            let target = expect_expr(&mut cursor)?;
+            let message = cursor.expect_name()?;
+            if target.is_list_of("module") {
+                Command::AssertModuleTrap(target.clone(), message)
+            } else {
+                Command::AssertTrap(parse_action(target)?, message)
+            }
+        }
+        Some("assert_exhaustion") => {
+            let action = parse_action(expect_expr(&mut cursor)?)?;
+            Command::AssertExhaustion(action, cursor.expect_name()?)
+        }
+        Some(kind @ "assert_invalid")
+        | Some(kind @ "assert_malformed")
+        | Some(kind @ "assert_unlinkable")
+        | Some(kind @ "assert_uninstantiable") => {
+            let module = expect_expr(&mut cursor)?.clone();
+            let message = cursor.expect_name()?;
+            match kind {
+                "assert_invalid" => Command::AssertInvalid(module, message),
+                "assert_malformed" => Command::AssertMalformed(module, message),
+                "assert_unlinkable" => Command::AssertUnlinkable(module, message),
+                _ => Command::AssertUninstantiable(module, message),
+            }
+        }
+        _ => return Err(ParseError::new("unknown command", expr.position())),
+    };
+    cursor.expect_end()?;
+    Ok(command)
+}
+
+fn expect_expr<'a>(cursor: &mut Cursor<'a>) -> Result<&'a SExpr, ParseError> {
+    let pos = cursor.position();
+    match cursor.take() {
+        Some(expr @ SExpr::List(..)) => Ok(expr),
+        _ => Err(ParseError::new("expected a list", pos)),
+    }
+}
+
+fn parse_action(expr: &SExpr) -> Result<Action, ParseError> {
+    let items = match expr.as_list() {
+        Some(items) if expr.head().is_some() => items,
+        _ => return Err(ParseError::new("expected an action", expr.position())),
+    };
+    let mut cursor = Cursor::new(&items[1..], expr.position());
+    let module = cursor.take_id().map(|id| id.to_owned());
+    let field = cursor.expect_name()?;
+
+    let action = match expr.head() {
+        Some("invoke") => {
+            let mut args = Vec::new();
+            while let Some(arg) = cursor.take() {
+                args.push(parse_value(arg)?);
+            }
+            Action::Invoke {
+                module,
+                field,
+                args,
+            }
+        }
+        Some("get") => Action::Get { module, field },
+        _ => return Err(ParseError::new("expected an action", expr.position())),
+    };
+    cursor.expect_end()?;
+    Ok(action)
+}
+
+/// Parses a `(t.const c)` expression.
+fn parse_value(expr: &SExpr) -> Result<Value, ParseError> {
+    let (keyword, literal) = parse_const_expr(expr)?;
+    match text::parse_const(keyword, literal) {
+        Some(value) => Ok(value),
+        None => Err(ParseError::new("invalid constant", expr.position())),
+    }
+}
+
+fn parse_expected(expr: &SExpr) -> Result<Expected, ParseError> {
+    let (keyword, literal) = parse_const_expr(expr)?;
+    let typ = match keyword {
+        "f32.const" => ValType::F32,
+        "f64.const" => ValType::F64,
+        _ => ValType::Nil,
+    };
+    match literal {
+        "nan:canonical" if typ != ValType::Nil => Ok(Expected::CanonicalNan(typ)),
+        "nan:arithmetic" if typ != ValType::Nil => Ok(Expected::ArithmeticNan(typ)),
+        _ => parse_value(expr).map(Expected::Value),
+    }
+}
+
+fn parse_const_expr(expr: &SExpr) -> Result<(&str, &str), ParseError> {
+    if let Some([SExpr::Atom(keyword, _), SExpr::Atom(literal, _)]) = expr.as_list() {
+        Ok((keyword, literal))
+    } else {
+        Err(ParseError::new("expected a constant", expr.position()))
+    }
+}
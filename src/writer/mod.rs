@@ -0,0 +1,163 @@
+use std::io;
+
+use byteorder::{LittleEndian, WriteBytesExt};
+
+use crate::{reader::SectionId, utils, Error};
+
+const MAGIC: u32 = 0x6D736100;
+const VERSION: u32 = 1;
+
+/// Writes the binary format of a WebAssembly module to the provided sink.
+///
+/// See [`Module::save`](crate::module::Module::save) to write an entire module.
+pub struct Writer<W: io::Write> {
+    sink: W,
+}
+
+impl<W: io::Write> Writer<W> {
+    pub fn new(sink: W) -> Writer<W> {
+        Writer { sink }
+    }
+
+    pub fn write_module_header(&mut self) -> Result<(), Error> {
+        self.sink.write_u32::<LittleEndian>(MAGIC)?;
+        self.sink.write_u32::<LittleEndian>(VERSION)?;
+        Ok(())
+    }
+
+    /// Writes a section with the specified ID, using `body` to write its content.
+    pub fn write_section<F>(&mut self, id: SectionId, body: F) -> Result<(), Error>
+    where
+        F: FnOnce(&mut Vec<u8>) -> Result<(), Error>,
+    {
+        // Sections are prefixed with their size, so write the content to a buffer first
+        let mut content = Vec::new();
+        body(&mut content)?;
+
+        self.sink.write_u8(id as u8)?;
+        utils::write_leb128_u32(&mut self.sink, content.len() as u32)?;
+        self.sink.write_all(&content)?;
+        Ok(())
+    }
+
+    /// Writes a custom section with the specified name, using `body` to write its content.
+    pub fn write_custom_section<F>(&mut self, name: &str, body: F) -> Result<(), Error>
+    where
+        F: FnOnce(&mut Vec<u8>) -> Result<(), Error>,
+    {
+        self.write_section(SectionId::Custom, |w| {
+            utils::write_name(w, name)?;
+            body(w)
+        })
+    }
+
+    /// Consumes the writer, returning the underlying sink.
+    pub fn into_inner(self) -> W {
+        self.sink
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::{
+        builder::{FuncBuilder, ModuleBuilder},
+        module::Module,
+        reader::Reader,
+        text, Instruction, ValType, Value,
+    };
+
+    fn write(module: &Module) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        module.save(Writer::new(&mut bytes)).unwrap();
+        bytes
+    }
+
+    fn round_trip(module: Module) {
+        let bytes = write(&module);
+        let loaded = Module::load(Reader::new(Cursor::new(bytes))).unwrap();
+        assert_eq!(module, loaded);
+    }
+
+    #[test]
+    pub fn writes_expected_bytes() {
+        let module = ModuleBuilder::new()
+            .func(
+                FuncBuilder::new()
+                    .export_as("one")
+                    .result(ValType::I32)
+                    .body(vec![Instruction::I32Const(Value::I32(1))]),
+            )
+            .build();
+        assert_eq!(
+            vec![
+                0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00, // header
+                0x01, 0x05, 0x01, 0x60, 0x00, 0x01, 0x7F, // type section
+                0x03, 0x02, 0x01, 0x00, // function section
+                0x07, 0x07, 0x01, 0x03, b'o', b'n', b'e', 0x00, 0x00, // export section
+                0x0A, 0x06, 0x01, 0x04, 0x00, 0x41, 0x01, 0x0B, // code section
+            ],
+            write(&module)
+        );
+    }
+
+    #[test]
+    pub fn round_trips_empty_module() {
+        round_trip(ModuleBuilder::new().build());
+    }
+
+    #[test]
+    pub fn round_trips_all_sections() {
+        round_trip(
+            text::parse_module(
+                r#"
+                (module $test
+                  (type $binop (func (param i32 i32) (result i32)))
+                  (import "env" "print" (func $print (param i32)))
+                  (import "env" "table" (table 1 10 funcref))
+                  (import "env" "memory" (memory 1))
+                  (import "env" "base" (global $base i32))
+                  (global $counter (mut i64) (i64.const -1))
+                  (global f32 (f32.const -0x1.8p3))
+                  (global f64 (f64.const 1e100))
+                  (export "counter" (global $counter))
+                  (export "table" (table 0))
+                  (start $init)
+                  (elem (global.get $base) $add $init)
+                  (data (i32.const 16) "hello\00world")
+                  (func $init)
+                  (func $add (export "add") (type $binop) (param $a i32) (param $b i32) (result i32)
+                    (local $x i64) (local i64 f32) (local $y f64) (local i32)
+                    (block $outer (result i32)
+                      (loop $inner
+                        (br_table $outer $inner $outer (local.get $a))
+                      )
+                      (i32.const 0x7fffffff)
+                    )
+                    (call_indirect (type $binop) (local.get $a) (local.get $b) (i32.const 0))
+                    (i32.store8 offset=3 (i32.const 0) (i32.load16_s align=1 (i32.const 65536)))
+                    (drop (memory.grow (memory.size)))
+                    (if (result i32) (local.get $b)
+                      (then (i32.add))
+                      (else (i32.sub)))
+                    (call $print (i32.const -2147483648))
+                  )
+                )
+            "#,
+            )
+            .unwrap(),
+        );
+    }
+
+    #[test]
+    pub fn round_trips_defined_memory() {
+        round_trip(
+            text::parse_module(
+                r#"(module (memory (export "memory") 1 16) (data (i32.const 0) "data"))"#,
+            )
+            .unwrap(),
+        );
+    }
+}
@@ -1,195 +1,143 @@
 extern crate warthog;
 
-use std::io::Cursor;
+use std::{fs, path::PathBuf};
 
-use warthog::{
-    hosting::{ExternVal, Host, ModuleAddr},
-    interp::Thread,
-    module::Module,
-    reader::Reader,
-    runtime, Trap, Value,
-};
+use warthog::wast::{Runner, Script};
 
-macro_rules! vals {
-    ($($v: expr),*) => {
-        vec![$(
-            ::warthog::Value::from($v)
-        ),*]
+// The scripts come from the spec repository, checked out as a git submodule under
+// vendor/webassembly. They're ignored by default so `cargo test` works without the submodule,
+// run them with `git submodule update --init && cargo test -- --ignored`.
+macro_rules! spec_tests {
+    ($($name: ident),*) => {
+        spec_tests!($($name => stringify!($name)),*);
     };
-}
-
-mod spec;
-
-pub struct TestContext {
-    current_line: usize,
-    active_module: Option<ModuleAddr>,
-    host: Host,
-}
-
-impl TestContext {
-    pub fn new() -> TestContext {
-        let mut host = Host::new();
-
-        // Install the Env and SpecTest modules
-        host.external(runtime::Env::new()).unwrap();
-        host.external(runtime::SpecTest::new()).unwrap();
-
-        TestContext {
-            current_line: 0,
-            active_module: None,
-            host,
-        }
-    }
-
-    pub fn set_line(&mut self, line: usize) {
-        self.current_line = line;
-    }
-
-    pub fn load_module(&mut self, module_name: &str, module_data: &[u8]) {
-        let module = {
-            let mut cur = Cursor::new(module_data);
-            let mut reader = Reader::new(cur);
-            match Module::load(reader) {
-                Ok(m) => m,
-                Err(e) => self.panic(format!(
-                    "Failed to load module: {}. Error: {:?}",
-                    module_name, e
-                )),
+    ($($name: ident => $file: expr),*) => {
+        $(
+            #[test]
+            #[ignore = "requires the spec test submodule"]
+            pub fn $name() {
+                run_spec($file);
             }
-        };
-        let addr = match self.host.instantiate(module_name, module) {
-            Ok(a) => a,
-            Err(e) => self.panic(format!(
-                "Failed to instantiate module: {}. Error: {:?}",
-                module_name, e
-            )),
-        };
-        self.active_module = Some(addr);
-    }
-
-    pub fn invoke(
-        &mut self,
-        module: Option<&str>,
-        field: &str,
-        params: Vec<Value>,
-    ) -> Result<Vec<Value>, Trap> {
-        let module = match (module, self.active_module) {
-            (None, None) => self.panic("No active module!"),
-            (Some(_name), _) => {
-                self.panic("Not yet implemented: Invoke with module.");
-            }
-            (_, Some(addr)) => addr,
-        };
-
-        self.invoke_core(module, field, params)
-    }
-
-    pub fn assert_return(&self, expected: Vec<Value>, actual: Result<Vec<Value>, Trap>) {
-        // Extract the actual value
-        let actual = match actual {
-            Ok(mut v) => self.unwrap_val(v),
-            Err(t) => self.panic(format!("Trapped: {}", t)),
-        };
-
-        // Expect up to one item in the return value
-        let expected = self.unwrap_val(expected);
-
-        // Special-case NaN == NaN here
-        let success = match (expected, actual) {
-            (Value::F32(e), Value::F32(a)) if e.is_nan() && a.is_nan() => true,
-            (Value::F64(e), Value::F64(a)) if e.is_nan() && a.is_nan() => true,
-            (e, v) => e == v,
-        };
-
-        if !success {
-            self.panic(format!(
-                "Expected: {} (0x{:X}), Actual: {} (0x{:X})",
-                expected, expected, actual, actual
-            ));
-        }
-    }
-
-    pub fn assert_nan(&self, actual: Result<Vec<Value>, Trap>) {
-        match actual {
-            Err(e) => self.panic(format!("Expected: NaN, Actual: <Trap: {}>", e)),
-            Ok(vals) => {
-                match self.unwrap_val(vals) {
-                    Value::F32(f) if f.is_nan() => { /* success */ }
-                    Value::F64(f) if f.is_nan() => { /* success */ }
-                    v => {
-                        self.panic(format!("Expected: NaN, Actual: {}", v));
-                    }
-                }
-            }
-        };
-    }
-
-    pub fn assert_trap(&self, expected: &str, actual: Result<Vec<Value>, Trap>) {
-        match actual {
-            Ok(v) => {
-                self.panic(format!(
-                    "Expected: <Trap: {}>, Actual: {} (0x{:X})",
-                    expected,
-                    self.unwrap_val(v.clone()),
-                    self.unwrap_val(v)
-                ));
-            }
-            Err(ref t) if t.cause() != expected => {
-                self.panic(format!(
-                    "Expected: <Trap: {}>, Actual: <Trap: {}>",
-                    expected,
-                    t.cause()
-                ));
-            }
-            _ => { /* all good! */ }
-        }
-    }
-
-    fn invoke_core(
-        &mut self,
-        module: ModuleAddr,
-        field: &str,
-        params: Vec<Value>,
-    ) -> Result<Vec<Value>, Trap> {
-        // Create a thread
-        let mut thread = Thread::new();
-
-        let func_addr = {
-            let export = match self.host.resolve_import(module, field) {
-                Ok(e) => e,
-                Err(e) => {
-                    self.panic(format!(
-                        "Failed to resolve import: [{}]::[{}]. Error: {:?}",
-                        module, field, e
-                    ));
-                }
-            };
-            match export.value() {
-                ExternVal::Func(func_addr) => *func_addr,
-                e => {
-                    return Err(format!(
-                        "Export '{}' from module '{}' is not a function, it's a {:?}",
-                        field, module, e
-                    )
-                    .into())
-                }
-            }
-        };
-
-        thread.call(&mut self.host, module, func_addr, params)
-    }
-
-    #[inline]
-    fn unwrap_val(&self, mut vals: Vec<Value>) -> Value {
-        if vals.len() > 1 {
-            self.panic("Multiple return values are not supported.");
-        }
+        )*
+    };
+}
 
-        vals.drain(..).next().unwrap_or(Value::Nil)
-    }
+spec_tests!(
+    address,
+    align,
+    binary,
+    block,
+    br,
+    br_if,
+    br_table,
+    call,
+    call_indirect,
+    comments,
+    conversions,
+    custom,
+    data,
+    elem,
+    endianness,
+    exports,
+    f32,
+    f32_bitwise,
+    f32_cmp,
+    f64,
+    f64_bitwise,
+    f64_cmp,
+    fac,
+    float_exprs,
+    float_literals,
+    float_memory,
+    float_misc,
+    forward,
+    func,
+    func_ptrs,
+    globals,
+    i32,
+    i64,
+    imports,
+    int_exprs,
+    int_literals,
+    labels,
+    linking,
+    load,
+    local_get,
+    local_set,
+    local_tee,
+    memory,
+    memory_grow,
+    memory_redundancy,
+    memory_size,
+    memory_trap,
+    names,
+    nop,
+    select,
+    stack,
+    start,
+    store,
+    switch,
+    token,
+    traps,
+    unreachable,
+    unwind
+);
+
+// Scripts whose names aren't valid identifiers
+spec_tests!(
+    break_drop => "break-drop",
+    const_ => "const",
+    if_ => "if",
+    inline_module => "inline-module",
+    left_to_right => "left-to-right",
+    loop_ => "loop",
+    return_ => "return",
+    skip_stack_guard_page => "skip-stack-guard-page",
+    type_ => "type",
+    unreached_invalid => "unreached-invalid",
+    utf8_custom_section_id => "utf8-custom-section-id",
+    utf8_import_field => "utf8-import-field",
+    utf8_import_module => "utf8-import-module",
+    utf8_invalid_encoding => "utf8-invalid-encoding"
+);
+
+fn run_spec(name: &str) {
+    let path: PathBuf = [
+        env!("CARGO_MANIFEST_DIR"),
+        "vendor",
+        "webassembly",
+        "test",
+        "core",
+    ]
+    .iter()
+    .collect::<PathBuf>()
+    .join(format!("{}.wast", name));
+
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) => panic!(
+            "Couldn't read {} ({}), check out the spec tests with `git submodule update --init`",
+            path.display(),
+            e
+        ),
+    };
 
-    #[inline]
-    fn panic<S: AsRef<str>>(&self, message: S) -> ! {
-        panic!("{} (line {})", message.as_ref(), self.current_line);
+    let script = match Script::parse(&text) {
+        Ok(script) => script,
+        Err(e) => panic!("{}:{}", path.display(), e),
+    };
+    let report = Runner::new().run(&script);
+
+    let failures: Vec<_> = report
+        .failures()
+        .map(|f| format!("{}:{}", path.display(), f))
+        .collect();
+    if !failures.is_empty() {
+        panic!(
+            "{} of {} directives failed:\n{}",
+            report.failed(),
+            report.results().len(),
+            failures.join("\n")
+        );
     }
 }